@@ -40,6 +40,19 @@ impl AnnotatedString {
         });
     }
 
+    // 在开头插入带注解的文本（不属于原始文本，例如边缘标记）
+    pub fn prepend_annotated(&mut self, string: &str, annotation_type: AnnotationType) {
+        self.replace(0, 0, string);
+        self.add_annotation(annotation_type, 0, string.len());
+    }
+
+    // 在末尾追加带注解的文本
+    pub fn append_annotated(&mut self, string: &str, annotation_type: AnnotationType) {
+        let start = self.string.len();
+        self.string.push_str(string);
+        self.add_annotation(annotation_type, start, self.string.len());
+    }
+
     // 从左侧截断字符串直到指定索引
     pub fn truncate_left_until(&mut self, until: ByteIdx) {
         self.replace(0, until, "");
@@ -9,5 +9,14 @@ pub enum AnnotationType {
     Char,
     LifetimeSpecifier,
     Comment,
-    String
+    String,
+    Dim,
+    Selection,
+    SecondaryCaret,
+    #[cfg_attr(not(feature = "spellcheck"), allow(dead_code))]
+    Misspelled, // 拼写检查发现的错误单词，以下划线显示
+    Key,         // 对象的键，例如 JSON 中冒号前的字符串
+    Punctuation, // 括号、冒号、逗号等结构符号
+    Error,       // 语法错误，例如未闭合的字符串或不匹配的括号，以下划线显示
+    MatchingBracket, // 光标处的括号及其配对括号
 }
@@ -0,0 +1,75 @@
+use std::{env, path::PathBuf};
+
+use super::logger::LOG_ENV_VAR;
+
+// 命令行参数：第一个非选项参数为文件名，其余以 "--" 开头的为选项。
+// 文件名为 "-" 或使用 --stdin 时从标准输入读取文档，未指定文件名而标准输入不是终端时也是如此；
+// 使用 --stdout 时在确认退出后将文档写入标准输出。
+// --light / --dark 指定终端背景的深浅，未指定时参考 COLORFGBG 环境变量。
+// --log <路径> 将调试日志追加写入该文件，未指定时参考 TZT_LOG 环境变量。
+// --readonly 以只读模式打开文档，关闭只读模式（Alt-Shift-R）之前不能保存。
+#[derive(Default)]
+pub struct Args {
+    pub file_name: Option<String>,
+    pub no_color: bool,
+    pub stdin: bool,
+    pub stdout: bool,
+    pub readonly: bool,
+    pub light: bool,
+    pub dark: bool,
+    pub log: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut args = Self::default();
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--no-color" => args.no_color = true,
+                "--stdin" | "-" => args.stdin = true,
+                "--stdout" => args.stdout = true,
+                "--readonly" => args.readonly = true,
+                "--light" => args.light = true,
+                "--dark" => args.dark = true,
+                "--log" => args.log = iter.next().map(PathBuf::from),
+                _ if arg.starts_with("--log=") => {
+                    args.log = Some(PathBuf::from(&arg["--log=".len()..]));
+                }
+                _ if arg.starts_with("--") => {} // 忽略无法识别的选项
+                _ => {
+                    if args.file_name.is_none() {
+                        args.file_name = Some(arg);
+                    }
+                }
+            }
+        }
+        args
+    }
+
+    // 调试日志文件：--log 优先，否则取非空的 TZT_LOG 环境变量
+    pub fn log_file(&self) -> Option<PathBuf> {
+        self.log.clone().or_else(|| {
+            env::var_os(LOG_ENV_VAR)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        })
+    }
+
+    // 是否使用颜色输出：遵循 NO_COLOR 约定（存在且非空即禁用）和 --no-color 选项
+    pub fn use_color(&self) -> bool {
+        !self.no_color && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+    }
+
+    // 终端是否为浅色背景：命令行选项优先，否则按 COLORFGBG（形如 "15;0"，最后一项为背景色号）
+    // 判断，背景色号为 7 或 9 以上视为浅色；都无法判断时按深色背景处理
+    pub fn light_background(&self) -> bool {
+        if self.light || self.dark {
+            return self.light;
+        }
+        env::var("COLORFGBG")
+            .ok()
+            .and_then(|value| value.rsplit(';').next()?.parse::<u8>().ok())
+            .is_some_and(|background| background == 7 || background >= 9)
+    }
+}
@@ -0,0 +1,50 @@
+// Ctrl-C / Ctrl-X 复制或剪切的文本，供 Ctrl-V 粘贴。
+// 启用 clipboard 特性时同时使用系统剪贴板，以便与其他程序互相复制粘贴；未启用该特性，
+// 或系统剪贴板不可用（例如没有图形环境）时只使用编辑器内部的寄存器。
+// 系统剪贴板的错误不会中断操作，而是记录下来，由调用者通过 take_error 取走并提示
+#[derive(Default)]
+pub struct Clipboard {
+    register: String,
+    #[cfg(feature = "clipboard")]
+    system: Option<arboard::Clipboard>, // 首次使用时打开；X11 下剪贴板内容由它持有，需要一直保留
+    error: Option<String>,
+}
+
+impl Clipboard {
+    // 保存复制或剪切的文本，内部寄存器总会更新
+    pub fn set(&mut self, text: String) {
+        #[cfg(feature = "clipboard")]
+        if let Err(err) = self
+            .system()
+            .and_then(|clipboard| clipboard.set_text(text.as_str()))
+        {
+            self.error = Some(err.to_string());
+        }
+        self.register = text;
+    }
+
+    // 要粘贴的文本：优先使用系统剪贴板中的文本，无法读取时退回内部寄存器
+    pub fn get(&mut self) -> String {
+        #[cfg(feature = "clipboard")]
+        match self.system().and_then(arboard::Clipboard::get_text) {
+            Ok(text) => return text,
+            Err(arboard::Error::ContentNotAvailable) => {} // 系统剪贴板中没有文本，不算错误
+            Err(err) => self.error = Some(err.to_string()),
+        }
+        self.register.clone()
+    }
+
+    // 返回并清除最近一次访问系统剪贴板时的错误
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+
+    #[cfg(feature = "clipboard")]
+    fn system(&mut self) -> Result<&mut arboard::Clipboard, arboard::Error> {
+        let clipboard = match self.system.take() {
+            Some(clipboard) => clipboard,
+            None => arboard::Clipboard::new()?,
+        };
+        Ok(self.system.insert(clipboard))
+    }
+}
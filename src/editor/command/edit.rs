@@ -1,15 +1,19 @@
 use crossterm::event::{
-    KeyCode::{Backspace, Char, Delete, Enter, Tab},
+    KeyCode::{BackTab, Backspace, Char, Delete, Enter, Tab},
     KeyEvent, KeyModifiers,
 };
 
-// Edit 枚举，表示各种编辑命令，如插入字符、插入新行、删除字符、向后删除字符
-#[derive(Clone, Copy)]
+// Edit 枚举，表示各种编辑命令，如插入字符、插入新行、删除字符、向后删除字符、向后删除单词、
+// 交换字符、减少缩进
+#[derive(Clone, Copy, Debug)]
 pub enum Edit {
     Insert(char),
     InsertNewline,
     Delete,
     DeleteBackward,
+    DeleteWordBackward,
+    Transpose,
+    Dedent,
 }
 
 impl TryFrom<KeyEvent> for Edit {
@@ -21,9 +25,16 @@ impl TryFrom<KeyEvent> for Edit {
                 Ok(Self::Insert(character))
             }
             (Tab, KeyModifiers::NONE) => Ok(Self::Insert('\t')),
+            // 终端把 Shift-Tab 作为单独的 BackTab 键发送，通常带有 SHIFT 修饰键
+            (BackTab, KeyModifiers::NONE | KeyModifiers::SHIFT) => Ok(Self::Dedent),
             (Enter, KeyModifiers::NONE) => Ok(Self::InsertNewline),
             (Backspace, KeyModifiers::NONE) => Ok(Self::DeleteBackward),
             (Delete, KeyModifiers::NONE) => Ok(Self::Delete),
+            // 许多终端把 Ctrl-Backspace 当作 Ctrl-H（替换）或普通退格发送，Ctrl-W 在各终端中都可用
+            (Backspace, KeyModifiers::CONTROL) | (Char('w'), KeyModifiers::CONTROL) => {
+                Ok(Self::DeleteWordBackward)
+            }
+            (Char('t'), KeyModifiers::CONTROL) => Ok(Self::Transpose),
             _ => Err(format!(
                 "Unsupported key code {:?} with modifiers {:?}",
                 event.code, event.modifiers
@@ -0,0 +1,111 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+// 有名字的按键，字母、数字和符号键直接写字符本身
+const NAMED_KEYS: [(&str, KeyCode); 14] = [
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("tab", KeyCode::Tab),
+    ("enter", KeyCode::Enter),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("esc", KeyCode::Esc),
+];
+
+// 修饰键在配置中的前缀，以及提示信息中显示的写法
+const MODIFIER_NAMES: [(&str, &str, KeyModifiers); 3] = [
+    ("ctrl-", "Ctrl-", KeyModifiers::CONTROL),
+    ("alt-", "Alt-", KeyModifiers::ALT),
+    ("shift-", "Shift-", KeyModifiers::SHIFT),
+];
+
+// 配置文件中用文字描述的按键，形如 "ctrl-n"、"alt-shift-p"、"f3"、"down"，不区分大小写
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        event.code == self.code && event.modifiers == self.modifiers
+    }
+    // 按下该按键时终端产生的事件
+    pub fn key_event(self) -> KeyEvent {
+        KeyEvent::new(self.code, self.modifiers)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let lowercase = text.trim().to_lowercase();
+        let mut rest = lowercase.as_str();
+        let mut modifiers = KeyModifiers::NONE;
+        while let Some((prefix, _, modifier)) = MODIFIER_NAMES
+            .iter()
+            .find(|(prefix, _, _)| rest.len() > prefix.len() && rest.starts_with(prefix))
+        {
+            modifiers |= *modifier;
+            rest = &rest[prefix.len()..];
+        }
+        let code = if let Some(&(_, code)) = NAMED_KEYS.iter().find(|(name, _)| *name == rest) {
+            code
+        } else if let Some(number) = rest.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            KeyCode::F(number)
+        } else {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                // 终端按下 Shift 时送来的是大写字母
+                (Some(character), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                    KeyCode::Char(character.to_ascii_uppercase())
+                }
+                (Some(character), None) => KeyCode::Char(character),
+                _ => return Err(format!("无法识别的按键: {text}")),
+            }
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for (_, display_name, modifier) in MODIFIER_NAMES {
+            if self.modifiers.contains(modifier) {
+                write!(formatter, "{display_name}")?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(character) => write!(formatter, "{}", character.to_ascii_uppercase()),
+            KeyCode::F(number) => write!(formatter, "F{number}"),
+            code => {
+                let name = NAMED_KEYS
+                    .iter()
+                    .find(|(_, named)| *named == code)
+                    .map_or("?", |(name, _)| name);
+                write!(formatter, "{name}")
+            }
+        }
+    }
+}
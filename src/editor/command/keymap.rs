@@ -0,0 +1,227 @@
+use crossterm::event::KeyEvent;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::Path,
+    sync::{LazyLock, RwLock},
+};
+
+use super::{Edit, KeyBinding, Move, MultiCursor, System};
+use crate::editor::IndentStyle;
+
+// 默认的按键绑定。keys.toml 中的绑定在此基础上添加或覆盖，因此该文件是可选的
+const DEFAULT_KEY_BINDINGS: &[(&str, System)] = &[
+    ("ctrl-q", System::Quit),
+    ("ctrl-s", System::Save),
+    ("ctrl-f", System::Search),
+    ("ctrl-l", System::FindAll),
+    ("ctrl-n", System::NewBuffer),
+    ("ctrl-z", System::Undo),
+    ("ctrl-y", System::Redo),
+    ("ctrl-c", System::Copy),
+    ("ctrl-x", System::Cut),
+    ("ctrl-v", System::Paste),
+    ("ctrl-h", System::Replace),
+    ("ctrl-d", System::DuplicateLine),
+    ("ctrl-p", System::CommandPalette),
+    // 传统终端把 Ctrl-/ 作为 0x1F 发送，crossterm 将其解析为 Ctrl-7
+    ("ctrl-/", System::ToggleComment),
+    ("ctrl-7", System::ToggleComment),
+    // 同理，Ctrl-] 作为 0x1D 发送，被解析为 Ctrl-5
+    ("ctrl-]", System::JumpToMatchingBracket),
+    ("ctrl-5", System::JumpToMatchingBracket),
+    ("alt-i", System::CheckIndentation),
+    ("alt-t", System::ConvertIndentation(IndentStyle::Spaces)),
+    ("alt-g", System::ToggleIndentGuides),
+    ("alt-f", System::Fold),
+    ("alt-s", System::ToggleStickyLines),
+    ("alt-w", System::ShowStatistics),
+    ("alt-o", System::QuitWithOutput),
+    ("alt-.", System::RepeatLastEdit),
+    ("alt-n", System::SearchNext),
+    ("alt-l", System::SetFileType),
+    ("alt-m", System::NextMisspelling),
+    ("alt-e", System::ExportSelection),
+    ("alt-c", System::DuplicateSelection),
+    ("alt-p", System::SurroundSelection),
+    ("alt-z", System::ToggleZenMode),
+    ("alt-b", System::ToggleStatusBar),
+    ("alt-h", System::NextChange),
+    ("alt-up", System::MoveLineUp),
+    ("alt-down", System::MoveLineDown),
+    ("alt-shift-t", System::ConvertIndentation(IndentStyle::Tabs)),
+    ("alt-shift-f", System::Unfold),
+    ("alt-shift-n", System::SearchPrev),
+    ("alt-shift-h", System::PrevChange),
+    ("alt-shift-l", System::ToggleLineNumbers),
+    ("alt-shift-i", System::ToggleSoftIndent),
+    ("alt-shift-r", System::ToggleReadOnly),
+    ("esc", System::Dismiss),
+    ("f5", System::InsertDateTime),
+    ("insert", System::ToggleOverwrite),
+    ("f3", System::SearchNext),
+    // xterm 类终端发送的 Shift-F3（CSI 1;2R）会被当作光标位置报告，此时可用 Alt-Shift-N
+    ("shift-f3", System::SearchPrev),
+];
+
+// keys.toml 中解除按键绑定时使用的命令名称
+const UNBIND: &str = "none";
+
+// 按键到系统命令的绑定表。首次使用时只包含默认绑定，启动时由 load_key_bindings 加入 keys.toml
+static KEY_BINDINGS: LazyLock<RwLock<Vec<(KeyBinding, System)>>> =
+    LazyLock::new(|| RwLock::new(default_key_bindings()));
+
+// 解析默认绑定。测试保证每一项都能解析，因此不会有绑定被丢弃
+fn default_key_bindings() -> Vec<(KeyBinding, System)> {
+    DEFAULT_KEY_BINDINGS
+        .iter()
+        .filter_map(|&(key, command)| key.parse().ok().map(|key| (key, command)))
+        .collect()
+}
+
+// 建立按键绑定表：先载入默认绑定，再读取按键配置文件（如 ~/.config/tzt/keys.toml）。
+// 文件中每一项把按键映射到命令名称，例如 "alt-q" = "quit"；命令名称为 "none" 时解除该按键的绑定。
+// 文件不存在时只使用默认绑定。无法识别的按键或命令、与编辑和移动按键冲突的绑定，
+// 以及重复绑定同一按键的项会被忽略，返回这些问题的说明。
+pub fn load_key_bindings(path: Option<&Path>) -> Vec<String> {
+    let mut errors = Vec::new();
+    let entries = match path.map(fs::read_to_string) {
+        None => BTreeMap::new(),
+        Some(Err(err)) if err.kind() == ErrorKind::NotFound => BTreeMap::new(),
+        Some(Err(err)) => {
+            errors.push(err.to_string());
+            BTreeMap::new()
+        }
+        Some(Ok(contents)) => toml::from_str::<BTreeMap<String, String>>(&contents)
+            .unwrap_or_else(|err| {
+                errors.push(err.message().to_string());
+                BTreeMap::new()
+            }),
+    };
+    let bindings = apply_entries(&entries, &mut errors);
+    if let Ok(mut key_bindings) = KEY_BINDINGS.write() {
+        *key_bindings = bindings;
+    }
+    errors
+}
+
+// 在默认绑定上应用 keys.toml 的各项，返回最终的绑定表；被忽略的项的说明加入 errors
+fn apply_entries(
+    entries: &BTreeMap<String, String>,
+    errors: &mut Vec<String>,
+) -> Vec<(KeyBinding, System)> {
+    let mut bindings = default_key_bindings();
+    let mut configured: Vec<KeyBinding> = Vec::new();
+    for (key, name) in entries {
+        let key_binding = match key.parse::<KeyBinding>() {
+            Ok(key_binding) => key_binding,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+        let command = System::from_name(name);
+        if command.is_none() && !name.trim().eq_ignore_ascii_case(UNBIND) {
+            errors.push(format!("未知的命令: {name}"));
+        } else if is_reserved(key_binding.key_event()) {
+            errors.push(format!("{key_binding} 已用于编辑或移动光标，不能绑定到 {name}"));
+        } else if configured.contains(&key_binding) {
+            errors.push(format!("{key_binding} 被重复绑定，已忽略 {key} = \"{name}\""));
+        } else {
+            configured.push(key_binding);
+            bindings.retain(|(bound, _)| *bound != key_binding);
+            if let Some(command) = command {
+                bindings.push((key_binding, command));
+            }
+        }
+    }
+    bindings
+}
+
+// 查找按键绑定的系统命令
+pub fn lookup(event: &KeyEvent) -> Option<System> {
+    KEY_BINDINGS.read().ok().and_then(|key_bindings| {
+        key_bindings
+            .iter()
+            .find(|(key_binding, _)| key_binding.matches(event))
+            .map(|&(_, command)| command)
+    })
+}
+
+// 按键是否已由编辑、移动或多光标命令使用：这些命令先于系统命令匹配，绑定到它们的按键不会生效
+fn is_reserved(event: KeyEvent) -> bool {
+    Edit::try_from(event).is_ok()
+        || Move::try_from(event).is_ok()
+        || Move::try_from_selection(event).is_ok()
+        || Move::try_from_block_selection(event).is_ok()
+        || MultiCursor::try_from(event).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_entries, KeyBinding, System, DEFAULT_KEY_BINDINGS};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::collections::BTreeMap;
+
+    // 按 keys.toml 的内容建立绑定表，返回绑定表和被忽略的项的说明
+    fn apply(contents: &str) -> (Vec<(KeyBinding, System)>, Vec<String>) {
+        let entries: BTreeMap<String, String> = toml::from_str(contents).unwrap();
+        let mut errors = Vec::new();
+        let bindings = apply_entries(&entries, &mut errors);
+        (bindings, errors)
+    }
+
+    fn bound(bindings: &[(KeyBinding, System)], key: &str) -> Option<System> {
+        let key_binding: KeyBinding = key.parse().unwrap();
+        bindings
+            .iter()
+            .find(|(bound, _)| *bound == key_binding)
+            .map(|&(_, command)| command)
+    }
+
+    #[test]
+    fn every_default_binding_parses() {
+        for (key, _) in DEFAULT_KEY_BINDINGS {
+            key.parse::<KeyBinding>().expect(key);
+        }
+    }
+
+    #[test]
+    fn defaults_apply_without_loading_keys_toml() {
+        let event = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+        assert!(matches!(System::try_from(event), Ok(System::Save)));
+    }
+
+    #[test]
+    fn keys_toml_adds_and_overrides_bindings() {
+        let (bindings, errors) = apply("\"alt-q\" = \"quit\"\n\"ctrl-s\" = \"search\"\n");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(matches!(bound(&bindings, "alt-q"), Some(System::Quit)));
+        assert!(matches!(bound(&bindings, "ctrl-s"), Some(System::Search)));
+        // 其余默认绑定不受影响
+        assert!(matches!(bound(&bindings, "ctrl-q"), Some(System::Quit)));
+    }
+
+    #[test]
+    fn none_unbinds_a_key() {
+        let (bindings, errors) = apply("\"ctrl-q\" = \"None\"\n");
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(bound(&bindings, "ctrl-q").is_none());
+    }
+
+    #[test]
+    fn ignores_unknown_commands_reserved_keys_and_duplicates() {
+        let (bindings, errors) = apply("\"alt-q\" = \"no_such_command\"\n\"left\" = \"save\"\n");
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors[0].contains("no_such_command"), "{errors:?}");
+        assert!(errors[1].contains("save"), "{errors:?}");
+        assert!(bound(&bindings, "alt-q").is_none());
+        assert!(bound(&bindings, "left").is_none());
+        // 同一按键的两种写法：按键名排序后保留第一项，忽略后一项
+        let (bindings, errors) = apply("\"Alt-Q\" = \"quit\"\n\"alt-q\" = \"save\"\n");
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert!(errors[0].contains("save"), "{errors:?}");
+        assert!(matches!(bound(&bindings, "alt-q"), Some(System::Quit)));
+    }
+}
@@ -8,17 +8,53 @@ pub use edit::Edit;
 mod movecommand;
 pub use movecommand::Move;
 
+mod multicursor;
+pub use multicursor::MultiCursor;
+
 mod system;
 pub use system::System;
 
+mod keybinding;
+pub use keybinding::KeyBinding;
+
+mod keymap;
+pub use keymap::load_key_bindings;
+
 //  Command 枚举，用于表示不同类型的命令：移动命令、编辑命令和系统命令
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum Command {
     Move(Move),
+    BlockSelect(Move),
+    Select(Move), // 扩展字符选区的移动
     Edit(Edit),
+    MultiCursor(MultiCursor),
     System(System),
 }
 
+impl From<Move> for Command {
+    fn from(command: Move) -> Self {
+        Self::Move(command)
+    }
+}
+
+impl From<Edit> for Command {
+    fn from(command: Edit) -> Self {
+        Self::Edit(command)
+    }
+}
+
+impl From<MultiCursor> for Command {
+    fn from(command: MultiCursor) -> Self {
+        Self::MultiCursor(command)
+    }
+}
+
+impl From<System> for Command {
+    fn from(command: System) -> Self {
+        Self::System(command)
+    }
+}
+
 impl TryFrom<Event> for Command {
     type Error = String;
     // 将 Event 转换为 Command
@@ -27,6 +63,9 @@ impl TryFrom<Event> for Command {
             Event::Key(key_event) => Edit::try_from(key_event)
                 .map(Command::Edit)
                 .or_else(|_| Move::try_from(key_event).map(Command::Move))
+                .or_else(|_| Move::try_from_selection(key_event).map(Command::Select))
+                .or_else(|_| Move::try_from_block_selection(key_event).map(Command::BlockSelect))
+                .or_else(|_| MultiCursor::try_from(key_event).map(Command::MultiCursor))
                 .or_else(|_| System::try_from(key_event).map(Command::System))
                 .map_err(|_err| format!("Event not supported: {key_event:?}")),
             Event::Resize(width_u16, height_u16) => Ok(Self::System(System::Resize(Size {
@@ -4,18 +4,55 @@ use crossterm::event::{
 };
 
 // Move 枚举，表示各种移动命令，如向上、向下、向左、向右移动等
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum Move {
-    PageUp,
-    PageDown,
-    StartOfLine,
-    EndOfLine,
+    PageUp,          // PageUp
+    PageDown,        // PageDown
+    StartOfLine,     // Home
+    EndOfLine,       // End
+    StartOfDocument, // Ctrl-Home
+    EndOfDocument,   // Ctrl-End
     Up,
     Left,
     Right,
     Down,
 }
 
+impl Move {
+    // 将 Shift 组合的移动键（方向键、Page Up / Page Down、Home / End，以及 Ctrl-Shift-Home / End）
+    // 转换为扩展字符选区的移动
+    pub fn try_from_selection(event: KeyEvent) -> Result<Self, String> {
+        if event.modifiers == KeyModifiers::SHIFT
+            || event.modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT
+        {
+            Self::try_from(KeyEvent {
+                modifiers: event.modifiers - KeyModifiers::SHIFT,
+                ..event
+            })
+        } else {
+            Err(format!(
+                "Unsupported key code {:?} or modifier {:?}",
+                event.code, event.modifiers
+            ))
+        }
+    }
+
+    // 将 Alt+Shift 组合的移动键转换为块选区的移动
+    pub fn try_from_block_selection(event: KeyEvent) -> Result<Self, String> {
+        if event.modifiers == KeyModifiers::ALT | KeyModifiers::SHIFT {
+            Self::try_from(KeyEvent {
+                modifiers: KeyModifiers::NONE,
+                ..event
+            })
+        } else {
+            Err(format!(
+                "Unsupported key code {:?} or modifier {:?}",
+                event.code, event.modifiers
+            ))
+        }
+    }
+}
+
 impl TryFrom<KeyEvent> for Move {
     type Error = String;
     // 将 KeyEvent 转换为 Move
@@ -36,10 +73,71 @@ impl TryFrom<KeyEvent> for Move {
                 End => Ok(Self::EndOfLine),
                 _ => Err(format!("Unsupported code: {code:?}")),
             }
+        } else if modifiers == KeyModifiers::CONTROL {
+            match code {
+                Home => Ok(Self::StartOfDocument),
+                End => Ok(Self::EndOfDocument),
+                _ => Err(format!("Unsupported CONTROL+{code:?} combination")),
+            }
         } else {
             Err(format!(
                 "Unsupported key code {code:?} or modifier {modifiers:?}"
             ))
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::Move;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn maps_every_movement_key() {
+        let none = KeyModifiers::NONE;
+        let control = KeyModifiers::CONTROL;
+        for (event, expected) in [
+            (key(KeyCode::Up, none), Move::Up),
+            (key(KeyCode::Down, none), Move::Down),
+            (key(KeyCode::Left, none), Move::Left),
+            (key(KeyCode::Right, none), Move::Right),
+            (key(KeyCode::PageUp, none), Move::PageUp),
+            (key(KeyCode::PageDown, none), Move::PageDown),
+            (key(KeyCode::Home, none), Move::StartOfLine),
+            (key(KeyCode::End, none), Move::EndOfLine),
+            (key(KeyCode::Home, control), Move::StartOfDocument),
+            (key(KeyCode::End, control), Move::EndOfDocument),
+        ] {
+            let actual = Move::try_from(event).unwrap();
+            assert_eq!(format!("{actual:?}"), format!("{expected:?}"), "{event:?}");
+        }
+        // Ctrl 只与 Home / End 组合
+        assert!(Move::try_from(key(KeyCode::PageUp, control)).is_err());
+        assert!(Move::try_from(key(KeyCode::Home, KeyModifiers::ALT)).is_err());
+        assert!(Move::try_from(key(KeyCode::Char('a'), none)).is_err());
+    }
+
+    #[test]
+    fn maps_selection_keys() {
+        let shift = KeyModifiers::SHIFT;
+        let control_shift = KeyModifiers::CONTROL | KeyModifiers::SHIFT;
+        let alt_shift = KeyModifiers::ALT | KeyModifiers::SHIFT;
+        assert!(matches!(
+            Move::try_from_selection(key(KeyCode::PageDown, shift)),
+            Ok(Move::PageDown)
+        ));
+        assert!(matches!(
+            Move::try_from_selection(key(KeyCode::End, control_shift)),
+            Ok(Move::EndOfDocument)
+        ));
+        assert!(Move::try_from_selection(key(KeyCode::End, KeyModifiers::NONE)).is_err());
+        assert!(matches!(
+            Move::try_from_block_selection(key(KeyCode::Home, alt_shift)),
+            Ok(Move::StartOfLine)
+        ));
+        assert!(Move::try_from_block_selection(key(KeyCode::Home, shift)).is_err());
+    }
+}
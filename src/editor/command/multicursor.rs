@@ -0,0 +1,41 @@
+use crossterm::event::{
+    KeyCode::{Char, Down, Up},
+    KeyEvent, KeyModifiers,
+};
+
+// MultiCursor 枚举，表示添加或移除光标的命令
+#[derive(Clone, Copy, Debug)]
+pub enum MultiCursor {
+    AddAbove,
+    AddBelow,
+    AddNextOccurrence,
+    RemoveLast,
+}
+
+impl TryFrom<KeyEvent> for MultiCursor {
+    type Error = String;
+    // 将 KeyEvent 转换为 MultiCursor
+    fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
+        let KeyEvent {
+            code, modifiers, ..
+        } = event;
+
+        if modifiers == KeyModifiers::CONTROL | KeyModifiers::ALT {
+            match code {
+                Up => Ok(Self::AddAbove),
+                Down => Ok(Self::AddBelow),
+                _ => Err(format!("Unsupported CONTROL+ALT+{code:?} combination")),
+            }
+        } else if modifiers == KeyModifiers::ALT {
+            match code {
+                Char('d') => Ok(Self::AddNextOccurrence),
+                Char('u') => Ok(Self::RemoveLast),
+                _ => Err(format!("Unsupported ALT+{code:?} combination")),
+            }
+        } else {
+            Err(format!(
+                "Unsupported key code {code:?} or modifier {modifiers:?}"
+            ))
+        }
+    }
+}
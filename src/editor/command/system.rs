@@ -1,39 +1,138 @@
+use super::keymap;
 use crate::prelude::*;
-use crossterm::event::{
-    KeyCode::{self, Char},
-    KeyEvent, KeyModifiers,
-};
+use crate::editor::IndentStyle;
+use crossterm::event::KeyEvent;
 // System 枚举，表示各种系统命令，如保存、调整大小、退出、取消和搜索
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum System {
     Save,
     Resize(Size),
     Quit,
     Dismiss,
     Search,
+    FindAll,
+    CheckIndentation,
+    ConvertIndentation(IndentStyle),
+    ToggleIndentGuides,
+    Fold,
+    Unfold,
+    ToggleStickyLines,
+    ShowStatistics,
+    InsertDateTime,
+    QuitWithOutput,
+    RepeatLastEdit,
+    ToggleOverwrite,
+    SearchNext,
+    SearchPrev,
+    SetFileType,
+    NextMisspelling,
+    ExportSelection,
+    NewBuffer,
+    DuplicateSelection,
+    SurroundSelection,
+    ToggleZenMode,
+    ToggleStatusBar,
+    NextChange,
+    PrevChange,
+    Undo,
+    Redo,
+    Copy,
+    Cut,
+    Paste,
+    Replace,
+    ToggleLineNumbers,
+    ToggleSoftIndent,
+    ToggleComment,
+    DuplicateLine,
+    MoveLineUp,
+    MoveLineDown,
+    ToggleReadOnly,
+    JumpToMatchingBracket,
+    CommandPalette,
+}
+
+// 按键配置文件中使用的命令名称。Resize 由终端事件产生，不能绑定到按键
+const COMMAND_NAMES: &[(&str, System)] = &[
+    ("save", System::Save),
+    ("quit", System::Quit),
+    ("dismiss", System::Dismiss),
+    ("search", System::Search),
+    ("find_all", System::FindAll),
+    ("check_indentation", System::CheckIndentation),
+    ("convert_to_spaces", System::ConvertIndentation(IndentStyle::Spaces)),
+    ("convert_to_tabs", System::ConvertIndentation(IndentStyle::Tabs)),
+    ("toggle_indent_guides", System::ToggleIndentGuides),
+    ("fold", System::Fold),
+    ("unfold", System::Unfold),
+    ("toggle_sticky_lines", System::ToggleStickyLines),
+    ("show_statistics", System::ShowStatistics),
+    ("insert_date_time", System::InsertDateTime),
+    ("quit_with_output", System::QuitWithOutput),
+    ("repeat_last_edit", System::RepeatLastEdit),
+    ("toggle_overwrite", System::ToggleOverwrite),
+    ("search_next", System::SearchNext),
+    ("search_prev", System::SearchPrev),
+    ("set_file_type", System::SetFileType),
+    ("next_misspelling", System::NextMisspelling),
+    ("export_selection", System::ExportSelection),
+    ("new_buffer", System::NewBuffer),
+    ("duplicate_selection", System::DuplicateSelection),
+    ("surround_selection", System::SurroundSelection),
+    ("toggle_zen_mode", System::ToggleZenMode),
+    ("toggle_status_bar", System::ToggleStatusBar),
+    ("next_change", System::NextChange),
+    ("prev_change", System::PrevChange),
+    ("undo", System::Undo),
+    ("redo", System::Redo),
+    ("copy", System::Copy),
+    ("cut", System::Cut),
+    ("paste", System::Paste),
+    ("replace", System::Replace),
+    ("toggle_line_numbers", System::ToggleLineNumbers),
+    ("toggle_soft_indent", System::ToggleSoftIndent),
+    ("toggle_comment", System::ToggleComment),
+    ("duplicate_line", System::DuplicateLine),
+    ("move_line_up", System::MoveLineUp),
+    ("move_line_down", System::MoveLineDown),
+    ("toggle_read_only", System::ToggleReadOnly),
+    ("jump_to_matching_bracket", System::JumpToMatchingBracket),
+    ("command_palette", System::CommandPalette),
+];
+
+impl System {
+    // 根据命令名称查找命令，忽略大小写，连字符与下划线等同（toggle-line-numbers）
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = normalize_name(name);
+        COMMAND_NAMES
+            .iter()
+            .find(|(command_name, _)| *command_name == name)
+            .map(|&(_, command)| command)
+    }
+
+    // 以 prefix 开头的命令名称，按注册顺序排列，规则同 from_name
+    pub fn names_with_prefix(prefix: &str) -> Vec<&'static str> {
+        let prefix = normalize_name(prefix);
+        COMMAND_NAMES
+            .iter()
+            .map(|&(name, _)| name)
+            .filter(|name| name.starts_with(&prefix))
+            .collect()
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().to_ascii_lowercase().replace('-', "_")
 }
 
 impl TryFrom<KeyEvent> for System {
     type Error = String;
-    // 将 KeyEvent 转换为 System
+    // 在按键表中查找 KeyEvent 对应的 System，按键表由默认绑定和 keys.toml 组成
     fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
-        let KeyEvent {
-            code, modifiers, ..
-        } = event;
-
-        if modifiers == KeyModifiers::CONTROL {
-            match code {
-                Char('q') => Ok(Self::Quit),
-                Char('s') => Ok(Self::Save),
-                Char('f') => Ok(Self::Search),
-                _ => Err(format!("Unsupported CONTROL+{code:?} combination")),
-            }
-        } else if modifiers == KeyModifiers::NONE && matches!(code, KeyCode::Esc) {
-            Ok(Self::Dismiss)
-        } else {
-            Err(format!(
-                "Unsupported key code {code:?} or modifier {modifiers:?}"
-            ))
-        }
+        keymap::lookup(&event).ok_or_else(|| {
+            format!(
+                "Unsupported key code {:?} or modifier {:?}",
+                event.code, event.modifiers
+            )
+        })
     }
-}
\ No newline at end of file
+}
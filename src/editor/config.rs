@@ -0,0 +1,84 @@
+use serde::Deserialize;
+use std::{env, fs, io::ErrorKind, path::PathBuf};
+
+use super::command::KeyBinding;
+
+// 用户配置，从配置目录下的 config.toml 读取，未列出的项使用默认值
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[cfg_attr(not(feature = "datetime"), allow(dead_code))]
+    pub date_format: String, // 插入日期时间时使用的 strftime 格式
+    pub visual_bell: bool,   // 无法执行的按键是否闪烁屏幕提示
+    pub quit_prompt: bool,   // 有未保存的更改时，Ctrl-Q 询问是否保存；关闭后需连按 Ctrl-Q 退出
+    #[cfg_attr(not(feature = "spellcheck"), allow(dead_code))]
+    pub dictionary: Option<PathBuf>, // 拼写检查单词表，默认为配置目录下的 dictionary.txt
+    pub horizontal_scroll_step: usize,   // 光标越过左右边缘时一次水平滚动的列数
+    pub horizontal_scroll_margin: usize, // 光标与左右边缘（或边缘的溢出标记）之间至少保留的列数
+    pub paste_indent_into_block: bool, // 粘贴到新开启的花括号块中时，按块内的缩进对齐粘贴的各行
+    // 搜索提示中切换到下一个 / 上一个匹配项的按键（如 "ctrl-n"），设置后代替对应的方向键
+    pub search_next_key: Option<KeyBinding>,
+    pub search_prev_key: Option<KeyBinding>,
+    pub search_wrap: bool, // 查找到达文档末尾（或开头）时从另一端继续；关闭后停在最后一个匹配处
+    pub clear_message_on_key: bool, // 按下任意键时清除提示消息（错误和警告除外），而不是等到过期
+    pub line_numbers: bool, // 在文本左侧显示行号，可用 Alt-Shift-L 临时切换
+    // 在文本左侧显示一列行标记：* 为包含搜索匹配项的行；相对已保存的内容，
+    // + 为新增的行，! 为修改过的行，_ 标记有行被删除的位置
+    pub sign_column: bool,
+    pub gutter_separator: Option<char>, // 行标记与文本之间的分隔字符（如 "│"），默认不显示
+    pub undo_limit: usize, // 最多可以撤销的步骤数
+    pub tab_width: usize,  // 制表符展开到的制表位间隔，也是缩进换算时一级缩进的宽度
+    // 软缩进：Tab 插入空格直到下一个制表位，行首缩进中的退格一次删除一级缩进；可用 Alt-Shift-I 切换
+    pub soft_indent: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            date_format: String::from("%Y-%m-%dT%H:%M:%S%:z"), // ISO 8601
+            visual_bell: false, // 默认保持安静
+            quit_prompt: true,
+            dictionary: None,
+            horizontal_scroll_step: 1,
+            horizontal_scroll_margin: 0,
+            paste_indent_into_block: true,
+            search_next_key: None,
+            search_prev_key: None,
+            search_wrap: true,
+            clear_message_on_key: false,
+            line_numbers: false,
+            sign_column: false,
+            gutter_separator: None,
+            undo_limit: 1000,
+            tab_width: 4,
+            soft_indent: false,
+        }
+    }
+}
+
+impl Config {
+    // 读取配置文件，文件不存在时返回默认配置，无法读取或解析时返回错误原因
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = config_dir().map(|dir| dir.join("config.toml")) else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|err| err.message().to_string()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+// 返回配置目录：优先使用 $XDG_CONFIG_HOME/tzt，否则为 ~/.config/tzt
+pub fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(|home| PathBuf::from(home).join(".config"))
+        })
+        .map(|dir| dir.join("tzt"))
+}
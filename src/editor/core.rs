@@ -0,0 +1,125 @@
+use super::command::{
+    Command::{self, BlockSelect, Edit, Move, MultiCursor, Select, System},
+    Edit::Insert,
+    MultiCursor::{AddAbove, AddBelow, AddNextOccurrence, RemoveLast},
+    System::{
+        DuplicateLine, DuplicateSelection, MoveLineDown, MoveLineUp, Redo, ToggleComment, Undo,
+    },
+};
+use super::config::Config;
+use super::uicomponents::{file_type_for_name, register_builtin_highlighters, Buffer, View};
+use super::Annotation;
+use crate::prelude::*;
+use std::{ops::Range, sync::Once};
+
+static REGISTER_BUILTIN_HIGHLIGHTERS: Once = Once::new();
+
+// 执行只作用于文档的命令：移动、选择、编辑、多光标，以及撤销、移动行等不需要提示或消息的命令。
+// 返回是否有任何效果（例如文档开头的退格没有效果）；其他命令需要终端、提示或文件，返回 None，
+// 由调用者处理。Editor 和 Core 共用这里的逻辑，由 Editor 把没有效果的命令转换为提示
+pub fn apply(view: &mut View, command: Command, soft_indent: bool) -> Option<bool> {
+    let effective = match command {
+        Edit(edit_command) => view.handle_edit_command(edit_command, soft_indent),
+        Move(move_command) => view.handle_move_command(move_command),
+        BlockSelect(move_command) => {
+            view.handle_block_select_command(move_command);
+            true
+        }
+        Select(move_command) => {
+            view.handle_select_command(move_command);
+            true
+        }
+        MultiCursor(AddAbove) => {
+            view.add_caret_above();
+            true
+        }
+        MultiCursor(AddBelow) => {
+            view.add_caret_below();
+            true
+        }
+        MultiCursor(AddNextOccurrence) => view.add_caret_at_next_occurrence(),
+        MultiCursor(RemoveLast) => {
+            view.remove_last_caret();
+            true
+        }
+        System(Undo) => view.undo(),
+        System(Redo) => view.redo(),
+        System(DuplicateLine) => {
+            view.duplicate_line();
+            true
+        }
+        System(DuplicateSelection) => view.duplicate_selection(),
+        System(MoveLineUp) => view.move_line(true),
+        System(MoveLineDown) => view.move_line(false),
+        System(ToggleComment) => view.toggle_line_comment(),
+        // 其他命令需要终端、提示或文件
+        System(_) => return None,
+    };
+    Some(effective)
+}
+
+// 不依赖终端的编辑核心，用于测试编辑逻辑或嵌入其他程序：输入命令，读取文档和光标位置。
+// 视图的尺寸为 0，翻页不移动光标
+pub struct Core {
+    view: View,
+    soft_indent: bool,
+}
+
+impl Core {
+    pub fn from_text(text: &str) -> Self {
+        let mut view = View::default();
+        view.set_undo_limit(Config::default().undo_limit);
+        // 从内存读取字符串不会失败
+        let _ = view.load_from_reader(text.as_bytes());
+        Self {
+            view,
+            soft_indent: false,
+        }
+    }
+
+    // 执行命令并返回是否有任何效果，每条命令是一个撤销步骤（连续输入的字符合并为一步）。
+    // 不作用于文档的命令（保存、搜索等）不做任何事，返回 false
+    pub fn apply(&mut self, command: impl Into<Command>) -> bool {
+        let command = command.into();
+        let effective = apply(&mut self.view, command, self.soft_indent).unwrap_or(false);
+        self.view.commit_history(matches!(command, Edit(Insert(_))));
+        effective
+    }
+
+    pub fn set_soft_indent(&mut self, soft_indent: bool) {
+        self.soft_indent = soft_indent;
+    }
+
+    // 改变制表符宽度，影响列位置、缩进换算和软缩进
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.view.set_tab_width(tab_width);
+    }
+
+    // 主光标的位置
+    pub const fn caret(&self) -> Location {
+        self.view.caret()
+    }
+
+    pub const fn buffer(&self) -> &Buffer {
+        self.view.buffer()
+    }
+
+    // 按名称设置文件类型以启用语法高亮，名称可以是 "text"、语言的显示名称（如 "Rust"）
+    // 或扩展名（如 "rs"），均忽略大小写。只识别内置语言，名称未知时返回 false
+    pub fn set_file_type(&mut self, name: &str) -> bool {
+        REGISTER_BUILTIN_HIGHLIGHTERS.call_once(register_builtin_highlighters);
+        let Some(file_type) = file_type_for_name(name) else {
+            return false;
+        };
+        self.view.set_file_type(file_type);
+        true
+    }
+
+    // 返回 lines 范围内每一行的语法注解（不含搜索、选区和光标注解），按行顺序排列，
+    // 超出文档的行为空列表。注解的 start..end 是该行文本内左闭右开的字节索引，而不是字素索引
+    // 或显示列：总是落在字符边界上，可以直接切片 buffer() 中该行的字符串；同一行的注解互不重叠。
+    // 多行注释等状态需要从文档开头推导，因此每次调用都从第 0 行开始高亮
+    pub fn syntax_annotations(&self, lines: Range<LineIdx>) -> Vec<Vec<Annotation>> {
+        self.view.syntax_annotations(lines)
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt::{self, Display};
+use unicode_segmentation::UnicodeSegmentation;
+
+// 文档或选区的统计信息
+#[derive(Default, Eq, PartialEq, Debug)]
+pub struct DocumentStatistics {
+    pub lines: usize,
+    pub words: usize,
+    pub graphemes: usize, // 字符数按字素计算，不含换行符
+    pub bytes: usize,
+    pub is_selection: bool,
+}
+
+impl DocumentStatistics {
+    // 计入一行文本（不含换行符）
+    pub fn add_line(&mut self, text: &str) {
+        self.lines = self.lines.saturating_add(1);
+        self.words = self.words.saturating_add(text.unicode_words().count());
+        self.graphemes = self.graphemes.saturating_add(text.graphemes(true).count());
+        self.bytes = self.bytes.saturating_add(text.len());
+    }
+}
+
+impl Display for DocumentStatistics {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scope = if self.is_selection { "选区" } else { "全文" };
+        write!(
+            formatter,
+            "{scope}：{} 行，{} 个单词，{} 个字符，{} 字节",
+            self.lines, self.words, self.graphemes, self.bytes
+        )
+    }
+}
@@ -1,14 +1,26 @@
 use crate::prelude::*;
+use std::{
+    fmt::{self, Display},
+    path::PathBuf,
+};
 
 use super::FileType;
 
+// 文档的当前状态，状态栏和窗口标题据此绘制；Display 输出供外部工具读取
 #[derive(Default, Eq, PartialEq, Debug)]
 pub struct DocumentStatus {
     pub total_lines: usize,
     pub current_line_idx: LineIdx,
+    pub current_col: ColIdx,
     pub is_modified: bool,
+    pub is_read_only: bool,      // 文件没有写入权限
+    pub is_read_only_mode: bool, // 处于只读模式，拒绝修改文档
+    pub caret_count: usize,      // 光标数，包括主光标；文档为空时也至少为 1
     pub file_name: String,
+    pub path: Option<PathBuf>, // 绝对路径，未命名的文档为 None
     pub file_type: FileType,
+    pub encoding: &'static str,
+    pub line_ending: &'static str,
 }
 
 impl DocumentStatus {
@@ -19,6 +31,31 @@ impl DocumentStatus {
             String::new()
         }
     }
+    pub fn read_only_indicator_to_string(&self) -> String {
+        if self.is_read_only_mode {
+            "[RO]".to_string()
+        } else {
+            String::new()
+        }
+    }
+    pub fn caret_count_indicator_to_string(&self) -> String {
+        if self.caret_count > 1 {
+            format!("[{} carets]", self.caret_count)
+        } else {
+            String::new()
+        }
+    }
+    // 窗口标题中的状态前缀：● 表示有未保存的更改，[RO] 表示文件只读或处于只读模式
+    pub fn title_prefix_to_string(&self) -> String {
+        let mut prefix = String::new();
+        if self.is_modified {
+            prefix.push_str("● ");
+        }
+        if self.is_read_only || self.is_read_only_mode {
+            prefix.push_str("[RO] ");
+        }
+        prefix
+    }
     pub fn line_count_to_string(&self) -> String {
         format!("{} lines", self.total_lines)
     }
@@ -33,3 +70,26 @@ impl DocumentStatus {
         self.file_type.to_string()
     }
 }
+
+// 每行一项 "键=值"，未命名文档的 path 为空
+impl Display for DocumentStatus {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self
+            .path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        writeln!(formatter, "path={path}")?;
+        writeln!(formatter, "name={}", self.file_name)?;
+        writeln!(formatter, "modified={}", self.is_modified)?;
+        writeln!(formatter, "read_only={}", self.is_read_only)?;
+        writeln!(formatter, "read_only_mode={}", self.is_read_only_mode)?;
+        writeln!(formatter, "file_type={}", self.file_type)?;
+        writeln!(formatter, "line={}", self.current_line_idx.saturating_add(1))?;
+        writeln!(formatter, "col={}", self.current_col.saturating_add(1))?;
+        writeln!(formatter, "lines={}", self.total_lines)?;
+        writeln!(formatter, "carets={}", self.caret_count)?;
+        writeln!(formatter, "encoding={}", self.encoding)?;
+        write!(formatter, "line_ending={}", self.line_ending)
+    }
+}
@@ -2,16 +2,32 @@ use std::fmt::{Display, Result, Formatter};
 
 #[derive(Default, Eq, PartialEq, Debug, Copy, Clone)]
 pub enum FileType {
-    Rust,
     #[default]
     Text,
+    Syntax(&'static str), // 已注册语法高亮器的语言，携带其显示名称
+}
+
+impl FileType {
+    // 自动缩进是否按花括号调整：以 '{' 结尾的行之后多缩进一级，单独一行的 '}' 与对应的 '{' 行对齐
+    pub fn indents_by_braces(self) -> bool {
+        self == Self::Syntax("Rust")
+    }
+
+    // 行注释标记，没有行注释（或尚未支持）的文件类型返回 None
+    pub fn comment_token(self) -> Option<&'static str> {
+        match self {
+            Self::Syntax("Rust") => Some("//"),
+            Self::Syntax("Python") => Some("#"),
+            Self::Text | Self::Syntax(_) => None,
+        }
+    }
 }
 
 impl Display for FileType {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
         match self {
-            Self::Rust => write!(formatter, "Rust"),
             Self::Text => write!(formatter, "Text"),
+            Self::Syntax(name) => write!(formatter, "{name}"),
         }
     }
 }
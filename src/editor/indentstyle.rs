@@ -0,0 +1,7 @@
+// 缩进风格：使用制表符或空格缩进
+#[derive(Default, Eq, PartialEq, Debug, Copy, Clone)]
+pub enum IndentStyle {
+    #[default]
+    Tabs,
+    Spaces,
+}
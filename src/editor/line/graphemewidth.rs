@@ -1,8 +1,12 @@
-// 枚举 GraphemeWidth，表示字符的宽度，可以是 Half（半宽度）或 Full（全宽度）
+use crate::prelude::*;
+
+// 枚举 GraphemeWidth，表示字符的宽度，可以是 Half（半宽度）、Full（全宽度），
+// 或者制表符展开到下一个制表位所占的列数（随所在列变化）
 #[derive(Copy, Clone, Debug)]
 pub enum GraphemeWidth {
     Half,
     Full,
+    Tab(ColIdx),
 }
 // 将 GraphemeWidth 转换为 usize 类型
 impl From<GraphemeWidth> for usize {
@@ -10,6 +14,7 @@ impl From<GraphemeWidth> for usize {
         match val {
             GraphemeWidth::Half => 1,
             GraphemeWidth::Full => 2,
+            GraphemeWidth::Tab(width) => width,
         }
     }
 }
\ No newline at end of file
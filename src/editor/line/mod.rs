@@ -6,7 +6,7 @@ use std::{
     ops::{Deref, Range},
 };
 use crate::prelude::*;
-use crate::editor::{AnnotatedString, Annotation};
+use crate::editor::{AnnotatedString, Annotation, IndentStyle};
 
 mod graphemewidth;
 use graphemewidth::GraphemeWidth;
@@ -14,20 +14,66 @@ use graphemewidth::GraphemeWidth;
 mod textfragment;
 use textfragment::TextFragment;
 
-#[derive(Default, Clone)]
+// 超过该字节数的行（如压缩后的 JS / JSON）视为超长行，不做语法高亮等逐行扫描
+const LONG_LINE_LEN: ByteIdx = 10_000;
+
+#[derive(Clone)]
 pub struct Line {
     fragments: Vec<TextFragment>, // fragments（文本片段向量）
+    col_starts: Vec<ColIdx>, // 每个字素的起始列，末尾额外存放整行宽度；空行的默认值为空向量
+    // 编辑之后不逐个平移其后的片段：下标不小于 shifted_from 的片段的 start 和 col_starts
+    // 还要分别加上 byte_shift 和 col_shift 才是实际位置。偏移可能为负，按 wrapping 运算存放
+    shifted_from: GraphemeIdx,
+    byte_shift: ByteIdx,
+    col_shift: ColIdx,
+    shifted_tabs: usize, // 下标不小于 shifted_from 的片段中制表符的个数
     string: String, // string（字符串）
+    tab_width: usize, // 制表符展开到的制表位间隔，至少为 1
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self {
+            fragments: Vec::new(),
+            col_starts: Vec::new(),
+            shifted_from: 0,
+            byte_shift: 0,
+            col_shift: 0,
+            shifted_tabs: 0,
+            string: String::new(),
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
 }
 
 impl Line {
-    // 通过字符串构建一个 Line 实例
+    // 通过字符串构建一个 Line 实例，制表符宽度取默认值
     pub fn from(line_str: &str) -> Self {
+        Self::with_tab_width(line_str, DEFAULT_TAB_WIDTH)
+    }
+
+    // 通过字符串构建一个 Line 实例，制表符按 tab_width 展开（为 0 时按 1 处理）
+    pub fn with_tab_width(line_str: &str, tab_width: usize) -> Self {
         debug_assert!(line_str.is_empty() || line_str.lines().count() == 1);
-        let fragments = Self::str_to_fragments(line_str);
-        Self {
-            fragments,
+        let mut line = Self {
             string: String::from(line_str),
+            tab_width: tab_width.max(1),
+            ..Self::default()
+        };
+        line.rebuild_fragments();
+        line
+    }
+
+    pub const fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    // 改变制表符宽度，宽度不变时不做任何事
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        let tab_width = tab_width.max(1);
+        if tab_width != self.tab_width {
+            self.tab_width = tab_width;
+            self.rebuild_col_starts();
         }
     }
 
@@ -36,32 +82,217 @@ impl Line {
     fn str_to_fragments(line_str: &str) -> Vec<TextFragment> {
         line_str
             .grapheme_indices(true)
-            .map(|(byte_idx, grapheme)| {
-                let (replacement, rendered_width) = Self::get_replacement_character(grapheme)
-                    .map_or_else(
-                        || {
-                            let unicode_width = grapheme.width();
-                            let rendered_width = match unicode_width {
-                                0 | 1 => GraphemeWidth::Half,
-                                _ => GraphemeWidth::Full,
-                            };
-                            (None, rendered_width)
-                        },
-                        |replacement| (Some(replacement), GraphemeWidth::Half),
-                    );
-
-                TextFragment {
-                    grapheme: grapheme.to_string(),
-                    rendered_width,
-                    replacement,
-                    start: byte_idx,
-                }
-            })
+            .map(|(byte_idx, grapheme)| Self::grapheme_to_fragment(byte_idx, grapheme))
             .collect()
     }
-   
+
+    fn grapheme_to_fragment(byte_idx: ByteIdx, grapheme: &str) -> TextFragment {
+        if grapheme == "\t" {
+            // 制表符的宽度取决于所在的列，由 rebuild_col_starts 计算
+            return TextFragment {
+                grapheme: grapheme.to_string(),
+                rendered_width: GraphemeWidth::Tab(1),
+                replacement: Some(' '),
+                start: byte_idx,
+            };
+        }
+        let (replacement, rendered_width) = Self::get_replacement_character(grapheme)
+            .map_or_else(
+                || {
+                    let unicode_width = grapheme.width();
+                    let rendered_width = match unicode_width {
+                        0 | 1 => GraphemeWidth::Half,
+                        _ => GraphemeWidth::Full,
+                    };
+                    (None, rendered_width)
+                },
+                |replacement| (Some(replacement), GraphemeWidth::Half),
+            );
+
+        TextFragment {
+            grapheme: grapheme.to_string(),
+            rendered_width,
+            replacement,
+            start: byte_idx,
+        }
+    }
+
     fn rebuild_fragments(&mut self) {
         self.fragments = Self::str_to_fragments(&self.string);
+        self.shifted_from = self.fragments.len();
+        self.rebuild_col_starts();
+    }
+
+    // 字符串中 edit（修改前的字节范围）被替换为 new_len 字节之后更新片段。
+    // 只从编辑位置的前一个字素开始重新切分，直到新的字素边界与编辑之后的旧边界重合。
+    // 其后的片段不逐个平移，而是累加到 byte_shift 和 col_shift 中；与上一次编辑的位置之间的片段
+    // 需要换算，因此在同一处连续编辑时，超长行中每次编辑的开销与行长无关。
+    fn update_fragments(&mut self, edit: Range<ByteIdx>, new_len: ByteIdx) {
+        if self.col_starts.is_empty() {
+            self.col_starts.push(0);
+        }
+        let first = self
+            .partition_fragments(|start| start <= edit.start)
+            .saturating_sub(2);
+        self.move_shifted_from(first);
+        let resegment_start = self.fragment_start(first).unwrap_or(0);
+        let new_edit_end = edit.start.saturating_add(new_len);
+        let mut resegmented = Vec::new();
+        let mut replaced_end = self.fragments.len();
+        for (offset, grapheme) in self.string[resegment_start..].grapheme_indices(true) {
+            let byte_idx = resegment_start.saturating_add(offset);
+            if byte_idx >= new_edit_end {
+                let old_byte_idx = byte_idx.saturating_sub(new_edit_end).saturating_add(edit.end);
+                let idx = self.partition_fragments(|start| start < old_byte_idx);
+                if self.fragment_start(idx) == Some(old_byte_idx) {
+                    replaced_end = idx;
+                    break;
+                }
+            }
+            resegmented.push(Self::grapheme_to_fragment(byte_idx, grapheme));
+        }
+
+        // 重新切分的片段按实际位置存放，并计算其中制表符的宽度
+        let mut col = self.col_start(first).unwrap_or(0);
+        let old_end_col = self.col_start(replaced_end).unwrap_or(col);
+        let tab_width = self.tab_width;
+        let mut new_col_starts = Vec::with_capacity(resegmented.len());
+        for fragment in &mut resegmented {
+            new_col_starts.push(col);
+            col = col.saturating_add(Self::measure(fragment, col, tab_width));
+        }
+        let removed_tabs = self.fragments[first..replaced_end]
+            .iter()
+            .filter(|fragment| fragment.is_tab())
+            .count();
+        self.shifted_tabs = self.shifted_tabs.saturating_sub(removed_tabs);
+        self.shifted_from = first.saturating_add(resegmented.len());
+        self.fragments.splice(first..replaced_end, resegmented);
+        self.col_starts.splice(first..replaced_end, new_col_starts);
+        self.byte_shift = self
+            .byte_shift
+            .wrapping_add(new_edit_end)
+            .wrapping_sub(edit.end);
+
+        // 列的偏移不是制表符宽度的整数倍时，其后第一个制表符的宽度会改变，之后的偏移又恢复为
+        // 整数倍，因此只需逐个换算到该制表符为止
+        if self.shifted_tabs > 0 && !col.abs_diff(old_end_col).is_multiple_of(self.tab_width) {
+            while let Some(fragment) = self.fragments.get_mut(self.shifted_from) {
+                fragment.start = fragment.start.wrapping_add(self.byte_shift);
+                let is_tab = fragment.is_tab();
+                self.col_starts[self.shifted_from] = col;
+                col = col.saturating_add(Self::measure(fragment, col, tab_width));
+                self.shifted_from = self.shifted_from.saturating_add(1);
+                if is_tab {
+                    self.shifted_tabs = self.shifted_tabs.saturating_sub(1);
+                    break;
+                }
+            }
+            self.col_shift = col.wrapping_sub(self.col_starts[self.shifted_from]);
+        } else {
+            self.col_shift = self.col_shift.wrapping_add(col).wrapping_sub(old_end_col);
+        }
+    }
+
+    // 移动待平移部分的起点：向后移动时把经过的片段换算为实际位置，向前移动时换回待平移的坐标
+    fn move_shifted_from(&mut self, to: GraphemeIdx) {
+        while self.shifted_from < to {
+            let idx = self.shifted_from;
+            let fragment = &mut self.fragments[idx];
+            fragment.start = fragment.start.wrapping_add(self.byte_shift);
+            if fragment.is_tab() {
+                self.shifted_tabs = self.shifted_tabs.saturating_sub(1);
+            }
+            self.col_starts[idx] = self.col_starts[idx].wrapping_add(self.col_shift);
+            self.shifted_from = idx.saturating_add(1);
+        }
+        while self.shifted_from > to {
+            let idx = self.shifted_from.saturating_sub(1);
+            let fragment = &mut self.fragments[idx];
+            fragment.start = fragment.start.wrapping_sub(self.byte_shift);
+            if fragment.is_tab() {
+                self.shifted_tabs = self.shifted_tabs.saturating_add(1);
+            }
+            self.col_starts[idx] = self.col_starts[idx].wrapping_sub(self.col_shift);
+            self.shifted_from = idx;
+        }
+    }
+
+    // 片段从 col 列开始时的宽度；制表符的宽度随之更新
+    fn measure(fragment: &mut TextFragment, col: ColIdx, tab_width: usize) -> ColIdx {
+        if let GraphemeWidth::Tab(width) = &mut fragment.rendered_width {
+            *width = next_tab_stop(col, tab_width).saturating_sub(col);
+        }
+        fragment.rendered_width.into()
+    }
+
+    // 第 idx 个片段的起始字节
+    fn fragment_start(&self, idx: GraphemeIdx) -> Option<ByteIdx> {
+        let start = self.fragments.get(idx)?.start;
+        Some(if idx < self.shifted_from {
+            start
+        } else {
+            start.wrapping_add(self.byte_shift)
+        })
+    }
+
+    // 第 idx 个字素的起始列，idx == grapheme_count 时为整行宽度
+    fn col_start(&self, idx: GraphemeIdx) -> Option<ColIdx> {
+        let col = *self.col_starts.get(idx)?;
+        Some(if idx < self.shifted_from {
+            col
+        } else {
+            col.wrapping_add(self.col_shift)
+        })
+    }
+
+    // 按实际的起始字节二分查找第一个不满足 pred 的片段
+    fn partition_fragments(&self, pred: impl Fn(ByteIdx) -> bool) -> GraphemeIdx {
+        let (settled, shifted) = self
+            .fragments
+            .split_at(min(self.shifted_from, self.fragments.len()));
+        let idx = settled.partition_point(|fragment| pred(fragment.start));
+        if idx < settled.len() {
+            return idx;
+        }
+        idx.saturating_add(
+            shifted.partition_point(|fragment| pred(fragment.start.wrapping_add(self.byte_shift))),
+        )
+    }
+
+    // 按实际的列二分查找第一个不满足 pred 的 col_starts 下标
+    fn partition_cols(&self, pred: impl Fn(ColIdx) -> bool) -> usize {
+        let (settled, shifted) = self
+            .col_starts
+            .split_at(min(self.shifted_from, self.col_starts.len()));
+        let idx = settled.partition_point(|&col| pred(col));
+        if idx < settled.len() {
+            return idx;
+        }
+        idx.saturating_add(shifted.partition_point(|&col| pred(col.wrapping_add(self.col_shift))))
+    }
+
+    // 重新计算列宽前缀和，使按列查找不必每次遍历整行；同时按所在列更新各制表符的宽度
+    fn rebuild_col_starts(&mut self) {
+        for fragment in self.fragments.iter_mut().skip(self.shifted_from) {
+            fragment.start = fragment.start.wrapping_add(self.byte_shift);
+        }
+        let mut col: ColIdx = 0;
+        self.col_starts = Vec::with_capacity(self.fragments.len().saturating_add(1));
+        self.col_starts.push(col);
+        for fragment in &mut self.fragments {
+            col = col.saturating_add(Self::measure(fragment, col, self.tab_width));
+            self.col_starts.push(col);
+        }
+        self.shifted_from = self.fragments.len();
+        self.byte_shift = 0;
+        self.col_shift = 0;
+        self.shifted_tabs = 0;
+    }
+
+    // 判断是否为超长行
+    pub fn is_very_long(&self) -> bool {
+        self.string.len() > LONG_LINE_LEN
     }
 
     // 根据输入字符串返回一个替代字符，用于表示特定的控制字符或空白字符
@@ -69,7 +300,6 @@ impl Line {
         let width = for_str.width();
         match for_str {
             " " => None,
-            "\t" => Some(' '),
             _ if width > 0 && for_str.trim().is_empty() => Some('␣'),
             _ if width == 0 => {
                 let mut chars = for_str.chars();
@@ -100,76 +330,164 @@ impl Line {
         if range.start >= range.end {
             return AnnotatedString::default();
         }
-    
+
+        // 只处理与可见范围相交的片段（包括跨越两侧边界的片段），超长行也只需处理一屏的内容
+        let first = self.grapheme_idx_at_col(range.start);
+        let last = min(
+            self.grapheme_idx_at_col(range.end).saturating_add(1),
+            self.grapheme_count(),
+        );
+        let window_start = self.grapheme_idx_to_byte_idx(first);
+        let window_end = self.grapheme_idx_to_byte_idx(last);
+        let window_len = window_end.saturating_sub(window_start);
+
         // 创建新的注解字符串
-        let mut result = AnnotatedString::from(&self.string);
-    
-        // 应用注解
+        let mut result = AnnotatedString::from(&self.string[window_start..window_end]);
+
+        // 应用注解，裁剪到窗口内并转换为相对窗口的字节索引
         if let Some(annotations) = annotations {
             for annotation in annotations {
-                result.add_annotation(annotation.annotation_type, annotation.start, annotation.end);
+                let start = annotation.start.clamp(window_start, window_end);
+                let end = annotation.end.clamp(window_start, window_end);
+                if start < end {
+                    result.add_annotation(
+                        annotation.annotation_type,
+                        start.saturating_sub(window_start),
+                        end.saturating_sub(window_start),
+                    );
+                }
             }
         }
-    
-        // 处理替代字符并截断
-        let mut fragment_start = self.width();
-        let mut truncate_left = false;
-        let mut truncate_right = false;
-        let mut replace_range = (None, None);
-    
-        for fragment in self.fragments.iter().rev() {
+
+        // 从右向左遍历片段：截断不可见的部分，并对可见片段应用替代字符
+        let mut fragment_start = self.width_until(last);
+        for (idx, fragment) in self.fragments[first..last].iter().enumerate().rev() {
             let fragment_end = fragment_start;
             fragment_start = fragment_start.saturating_sub(fragment.rendered_width.into());
-    
+            let fragment_byte_start = self
+                .grapheme_idx_to_byte_idx(first.saturating_add(idx))
+                .saturating_sub(window_start);
+            let fragment_byte_end = fragment_byte_start.saturating_add(fragment.grapheme.len());
+
             if fragment_start > range.end {
-                continue; // 没有到达可见范围，继续
+                continue; // 尚未到达可见范围，继续
             }
-    
+
+            // 片段跨越右边界：截断右侧，并用省略号替换该片段（制表符换成可见部分的空格）
             if fragment_start < range.end && fragment_end > range.end {
-                replace_range = (Some(fragment.start), None);
-                truncate_right = true;
-                break;
+                let visible_width = range.end.saturating_sub(fragment_start);
+                result.replace(
+                    fragment_byte_start,
+                    window_len,
+                    &Self::clipped_fragment(fragment, visible_width),
+                );
+                continue;
             } else if fragment_start == range.end {
-                truncate_right = true;
-                replace_range = (Some(fragment.start), None);
-                break;
+                // 片段恰好从可见范围的末尾开始：截断右侧
+                result.truncate_right_from(fragment_byte_start);
+                continue;
             }
-    
+
+            // 片段结束于可见范围的起始处：截断左侧，剩余片段均不可见
             if fragment_end <= range.start {
-                truncate_left = true;
-                replace_range = (None, Some(fragment.start.saturating_add(fragment.grapheme.len())));
+                result.truncate_left_until(fragment_byte_end);
                 break;
             } else if fragment_start < range.start && fragment_end > range.start {
-                replace_range = (Some(0), Some(fragment.start.saturating_add(fragment.grapheme.len())));
-                truncate_left = true;
+                // 片段跨越左边界：截断左侧，并用省略号替换该片段（制表符换成可见部分的空格）
+                let visible_width = fragment_end.saturating_sub(range.start);
+                let clipped = Self::clipped_fragment(fragment, visible_width);
+                result.replace(0, fragment_byte_end, &clipped);
                 break;
             }
-    
-            if fragment_start >= range.start && fragment_end <= range.end {
-                if let Some(replacement) = fragment.replacement {
-                    let start = fragment.start;
-                    let end = start.saturating_add(fragment.grapheme.len());
-                    result.replace(start, end, &replacement.to_string());
-                }
+
+            // 片段完全可见：如有需要则应用替代字符，制表符展开为占满其宽度的多个空格
+            if let Some(replacement) = fragment.replacement {
+                let width: ColIdx = fragment.rendered_width.into();
+                result.replace(
+                    fragment_byte_start,
+                    fragment_byte_end,
+                    &replacement.to_string().repeat(width),
+                );
             }
         }
-    
-        if truncate_left {
-            if let Some(end) = replace_range.1 {
-                result.truncate_left_until(end);
-            }
+
+        result
+    }
+
+    // 只有一部分可见的片段的显示内容
+    fn clipped_fragment(fragment: &TextFragment, visible_width: ColIdx) -> String {
+        match fragment.rendered_width {
+            GraphemeWidth::Tab(_) => " ".repeat(visible_width),
+            GraphemeWidth::Half | GraphemeWidth::Full => String::from("⋯"),
         }
-        if truncate_right {
-            if let Some(start) = replace_range.0 {
-                result.truncate_right_from(start);
+    }
+
+    // 返回行首缩进在制表符展开后的宽度
+    pub fn indent_width(&self) -> ColIdx {
+        self.leading_whitespace().chars().fold(0, |col: ColIdx, character| {
+            if character == '\t' {
+                next_tab_stop(col, self.tab_width)
             } else {
-                result.replace(self.string.len(), self.string.len(), "⋯");
+                col.saturating_add(1)
             }
-        } else if let Some(start) = replace_range.0 {
-            result.replace(start, self.string.len(), "⋯");
+        })
+    }
+
+    // 返回缩进参考线所在的显示列：制表符展开后每个制表位一条，只位于行首缩进之内。
+    // 空白行没有自己的缩进层级，返回 None。
+    pub fn indent_guide_cols(&self) -> Option<Vec<ColIdx>> {
+        if self.leading_whitespace().len() == self.string.len() {
+            return None;
         }
-    
-        result
+        Some((0..self.indent_width()).step_by(self.tab_width).collect())
+    }
+
+    // 返回行首的空白字符（缩进）
+    pub fn leading_whitespace(&self) -> &str {
+        let end = self
+            .string
+            .find(|character: char| character != ' ' && character != '\t')
+            .unwrap_or(self.string.len());
+        &self.string[..end]
+    }
+
+    // 返回减少一级缩进需要删除的行首字素数：一个制表符，或最多一个制表符宽度的空格
+    pub fn dedent_len(&self) -> GraphemeIdx {
+        if self.string.starts_with('\t') {
+            return 1;
+        }
+        self.string
+            .chars()
+            .take(self.tab_width)
+            .take_while(|&character| character == ' ')
+            .count()
+    }
+
+    // 判断行首缩进是否同时包含制表符和空格
+    pub fn has_mixed_indentation(&self) -> bool {
+        let indentation = self.leading_whitespace();
+        indentation.contains('\t') && indentation.contains(' ')
+    }
+
+    // 按指定风格重写行首缩进（按当前的制表符宽度换算），只改动行首空白，返回是否有改动
+    pub fn convert_indentation(&mut self, style: IndentStyle) -> bool {
+        let indentation = self.leading_whitespace();
+        let width = self.indent_width();
+        let new_indentation = match style {
+            IndentStyle::Spaces => " ".repeat(width),
+            IndentStyle::Tabs => format!(
+                "{}{}",
+                "\t".repeat(width / self.tab_width),
+                " ".repeat(width % self.tab_width)
+            ),
+        };
+        if new_indentation == indentation {
+            return false;
+        }
+        let indentation_len = indentation.len();
+        self.string.replace_range(..indentation_len, &new_indentation);
+        self.rebuild_fragments();
+        true
     }
 
     //  返回行中的字素数量
@@ -177,16 +495,19 @@ impl Line {
         self.fragments.len()
     }
 
-    // 计算直到指定字素的列宽
+    // 计算直到指定字素的列宽，超出行尾时返回整行宽度
     pub fn width_until(&self, grapheme_idx: GraphemeIdx) -> ColIdx {
-        self.fragments
-            .iter()
-            .take(grapheme_idx)
-            .map(|fragment| match fragment.rendered_width {
-                GraphemeWidth::Half => 1,
-                GraphemeWidth::Full => 2,
-            })
-            .sum()
+        self.col_start(min(grapheme_idx, self.col_starts.len().saturating_sub(1)))
+            .unwrap_or(0)
+    }
+
+    // 返回覆盖指定列的字素索引，是 width_until 的逆运算：
+    // 列落在双宽字素的后半格时返回该字素本身（即 width_until(结果) <= col），
+    // 列超出行宽时返回 grapheme_count
+    pub fn grapheme_idx_at_col(&self, col: ColIdx) -> GraphemeIdx {
+        // col_starts[1..] 是每个字素的结束列，找到第一个结束列大于 col 的字素；
+        // col_starts[0] 总是 0，不大于 col
+        self.partition_cols(|end| end <= col).saturating_sub(1)
     }
 
     // 返回整行的列宽
@@ -194,16 +515,21 @@ impl Line {
         self.width_until(self.grapheme_count())
     }
 
-    // 在指定字素索引处插入字符
-    // 将一个字符插入到行中，或者如果 at == grapheme_count + 1，则将其附加到行尾
+    // 在指定字素索引处插入字符，要求 at <= grapheme_count；
+    // at == grapheme_count 时将其附加到行尾
     pub fn insert_char(&mut self, character: char, at: GraphemeIdx) {
-        debug_assert!(at.saturating_sub(1) <= self.grapheme_count());
-        if let Some(fragment) = self.fragments.get(at) {
-            self.string.insert(fragment.start, character);
-        } else {
-            self.string.push(character);
-        }
-        self.rebuild_fragments();
+        debug_assert!(at <= self.grapheme_count());
+        let byte_idx = self.grapheme_idx_to_byte_idx(at);
+        self.string.insert(byte_idx, character);
+        self.update_fragments(byte_idx..byte_idx, character.len_utf8());
+    }
+
+    // 在指定字素索引处插入一段不含换行的文本，要求 at <= grapheme_count
+    pub fn insert_str(&mut self, text: &str, at: GraphemeIdx) {
+        debug_assert!(at <= self.grapheme_count());
+        let byte_idx = self.grapheme_idx_to_byte_idx(at);
+        self.string.insert_str(byte_idx, text);
+        self.update_fragments(byte_idx..byte_idx, text.len());
     }
 
     // 追加字符
@@ -214,12 +540,101 @@ impl Line {
     // 删除指定字素索引处的字符
     pub fn delete(&mut self, at: GraphemeIdx) {
         debug_assert!(at <= self.grapheme_count());
-        if let Some(fragment) = self.fragments.get(at) {
-            let start = fragment.start;
-            let end = fragment.start.saturating_add(fragment.grapheme.len());
+        if let (Some(fragment), Some(start)) = (self.fragments.get(at), self.fragment_start(at)) {
+            let end = start.saturating_add(fragment.grapheme.len());
             self.string.drain(start..end);
-            self.rebuild_fragments();
+            self.update_fragments(start..end, 0);
+        }
+    }
+
+    // 删除指定字素范围内的字符
+    pub fn delete_range(&mut self, range: Range<GraphemeIdx>) {
+        let end = min(range.end, self.grapheme_count());
+        if range.start >= end {
+            return;
+        }
+        let byte_range = self.byte_range(range.start..end);
+        self.string.drain(byte_range.clone());
+        self.update_fragments(byte_range, 0);
+    }
+
+    // 交换指定字素与其前一个字素。按片段整体交换，全角字符和带组合字符的字素不会被拆开
+    pub fn transpose(&mut self, at: GraphemeIdx) {
+        let (Some(previous), Some(current)) = (
+            at.checked_sub(1).and_then(|previous| self.fragments.get(previous)),
+            self.fragments.get(at),
+        ) else {
+            return;
+        };
+        let swapped = format!("{}{}", current.grapheme, previous.grapheme);
+        let byte_range = self.grapheme_idx_to_byte_idx(at.saturating_sub(1))
+            ..self
+                .grapheme_idx_to_byte_idx(at)
+                .saturating_add(current.grapheme.len());
+        self.string.replace_range(byte_range.clone(), &swapped);
+        self.update_fragments(byte_range, swapped.len());
+    }
+
+    // 返回包含指定字素或紧挨在其之前的单词的字素范围
+    pub fn word_at(&self, grapheme_idx: GraphemeIdx) -> Option<Range<GraphemeIdx>> {
+        let byte_idx = self.byte_range(grapheme_idx..grapheme_idx).start;
+        self.string
+            .split_word_bound_indices()
+            .find(|(start, word)| {
+                word.chars()
+                    .any(|character| character.is_alphanumeric() || character == '_')
+                    && *start <= byte_idx
+                    && byte_idx <= start.saturating_add(word.len())
+            })
+            .map(|(start, word)| {
+                let end = start.saturating_add(word.len());
+                let to_grapheme_idx = |byte_idx: ByteIdx| {
+                    self.byte_idx_to_grapheme_idx(byte_idx)
+                        .unwrap_or(self.grapheme_count())
+                };
+                to_grapheme_idx(start)..to_grapheme_idx(end)
+            })
+    }
+
+    // 返回指定字素之前最近的单词边界：先跳过空白，再跳过一串同类的片段（单词或标点）。
+    // 片段按 Unicode 单词分割划分。按单词向左移动时应使用同样的扫描，使删除与移动的边界一致
+    pub fn previous_word_start(&self, grapheme_idx: GraphemeIdx) -> GraphemeIdx {
+        let byte_idx = self.byte_range(grapheme_idx..grapheme_idx).start;
+        let is_word = |segment: &str| {
+            segment
+                .chars()
+                .any(|character| character.is_alphanumeric() || character == '_')
+        };
+        let is_whitespace = |segment: &str| segment.chars().all(char::is_whitespace);
+        let mut segments = self
+            .string
+            .split_word_bound_indices()
+            .take_while(|(start, _)| *start < byte_idx)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .skip_while(|(_, segment)| is_whitespace(segment));
+        // 光标前只有空白时删除到行首
+        let Some((mut start, first)) = segments.next() else {
+            return 0;
+        };
+        let kind = is_word(first);
+        for (segment_start, segment) in segments {
+            if is_whitespace(segment) || is_word(segment) != kind {
+                break;
+            }
+            start = segment_start;
         }
+        self.byte_idx_to_grapheme_idx(start).unwrap_or(0)
+    }
+
+    // 将字素范围转换为字节范围，超出行尾的索引对应字符串末尾
+    pub fn byte_range(&self, range: Range<GraphemeIdx>) -> Range<ByteIdx> {
+        let to_byte_idx = |grapheme_idx: GraphemeIdx| {
+            self.fragment_start(grapheme_idx)
+                .unwrap_or(self.string.len())
+        };
+        to_byte_idx(range.start)..to_byte_idx(range.end)
     }
 
     // 删除行末尾的字符
@@ -229,50 +644,38 @@ impl Line {
 
     // 将另一行的内容附加到当前行，并更新 fragments
     pub fn append(&mut self, other: &Self) {
+        let len = self.string.len();
         self.string.push_str(&other.string);
-        self.rebuild_fragments();
+        self.update_fragments(len..len, other.string.len());
     }
 
-    // 在指定字素索引处拆分行，并返回拆分后的剩余部分
+    // 在指定字素索引处拆分行，并返回拆分后的剩余部分。
+    // 在行尾（追加位置）或其后拆分时没有剩余部分，返回空行，原行保持不变
     pub fn split(&mut self, at: GraphemeIdx) -> Self {
-        if let Some(fragment) = self.fragments.get(at) {
-            let remainder = self.string.split_off(fragment.start);
-            self.rebuild_fragments();
-            Self::from(&remainder)
-        } else {
-            Self::default()
+        let start = self.grapheme_idx_to_byte_idx(min(at, self.grapheme_count()));
+        if start == self.string.len() {
+            return Self::with_tab_width("", self.tab_width);
         }
+        let remainder = self.string.split_off(start);
+        self.update_fragments(start..start.saturating_add(remainder.len()), 0);
+        Self::with_tab_width(&remainder, self.tab_width)
     }
 
     // 将字节索引转换为字素索引
-    fn byte_idx_to_grapheme_idx(&self, byte_idx: ByteIdx) -> Option<GraphemeIdx> {
+    pub fn byte_idx_to_grapheme_idx(&self, byte_idx: ByteIdx) -> Option<GraphemeIdx> {
         if byte_idx > self.string.len() {
             return None;
         }
-        self.fragments
-            .iter()
-            .position(|fragment| fragment.start >= byte_idx)
+        // 片段按起始字节排序，二分查找第一个起始字节不小于 byte_idx 的片段
+        let grapheme_idx = self.partition_fragments(|start| start < byte_idx);
+        (grapheme_idx < self.grapheme_count()).then_some(grapheme_idx)
     }
 
-    // 将字素索引转换为字节索引
+    // 将字素索引转换为字节索引；行尾（grapheme_count，即追加位置）对应字符串长度
     fn grapheme_idx_to_byte_idx(&self, grapheme_idx: GraphemeIdx) -> ByteIdx {
         debug_assert!(grapheme_idx <= self.grapheme_count());
-        if grapheme_idx == 0 || self.grapheme_count() == 0 {
-            return 0;
-        }
-        self.fragments.get(grapheme_idx).map_or_else(
-            || {
-                #[cfg(debug_assertions)]
-                {
-                    panic!("Fragment not found for grapheme index: {grapheme_idx:?}");
-                }
-                #[cfg(not(debug_assertions))]
-                {
-                    0
-                }
-            },
-            |fragment| fragment.start,
-        )
+        self.fragment_start(grapheme_idx)
+            .unwrap_or(self.string.len())
     }
 
     // 从指定字素索引向前搜索查询字符串，并返回匹配的字素索引
@@ -302,11 +705,7 @@ impl Line {
         if from_grapheme_idx == 0 {
             return None;
         }
-        let end_byte_index = if from_grapheme_idx == self.grapheme_count() {
-            self.string.len()
-        } else {
-            self.grapheme_idx_to_byte_idx(from_grapheme_idx)
-        };
+        let end_byte_index = self.grapheme_idx_to_byte_idx(from_grapheme_idx);
         self.find_all(query, 0..end_byte_index)
             .last()
             .map(|(_, grapheme_idx)| *grapheme_idx)
@@ -359,14 +758,13 @@ impl Line {
                     let end_idx = grapheme_idx.saturating_add(grapheme_count);
                     self.fragments
                         .get(grapheme_idx..end_idx)
-                        .map(|fragments| {
+                        .and_then(|fragments| {
                             let fragment_graphemes: Vec<&str> = fragments
                                 .iter()
                                 .map(|fragment| fragment.grapheme.as_str())
                                 .collect();
                             (query_graphemes == fragment_graphemes).then_some((start, grapheme_idx))
                         })
-                        .flatten() // 处理 Option<Option<(ByteIdx, GraphemeIdx)>> 类型
                 })
             })
             .collect()
@@ -385,4 +783,225 @@ impl Deref for Line {
     fn deref(&self) -> &Self::Target {
         &self.string
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::Line;
+
+    #[test]
+    fn tab_width_is_per_line() {
+        let narrow = Line::with_tab_width("a\tb", 2);
+        let wide = Line::with_tab_width("a\tb", 8);
+        assert_eq!(narrow.width_until(2), 2);
+        assert_eq!(wide.width_until(2), 8);
+        assert_eq!(narrow.indent_width(), 0);
+        assert_eq!(Line::with_tab_width("\t\tx", 3).indent_width(), 6);
+    }
+
+    #[test]
+    fn set_tab_width_recomputes_columns() {
+        let mut line = Line::from("\tx");
+        assert_eq!(line.width(), 5);
+        line.set_tab_width(2);
+        assert_eq!(line.width(), 3);
+        assert_eq!(line.grapheme_idx_at_col(2), 1);
+        // 宽度为 0 时按 1 处理
+        line.set_tab_width(0);
+        assert_eq!(line.tab_width(), 1);
+        assert_eq!(line.width(), 2);
+    }
+
+    #[test]
+    fn split_keeps_tab_width() {
+        let mut line = Line::with_tab_width("ab\tc", 2);
+        let remainder = line.split(2);
+        assert_eq!(remainder.tab_width(), 2);
+        assert_eq!(remainder.width(), 3);
+        assert_eq!(line.split(2).tab_width(), 2);
+    }
+
+    #[test]
+    fn grapheme_idx_to_byte_idx_handles_line_end_and_multi_byte() {
+        // "é" 由两个码位组成，"中" 占三个字节
+        let line = Line::from("ae\u{301}中b");
+        assert_eq!(line.grapheme_count(), 4);
+        assert_eq!(line.grapheme_idx_to_byte_idx(0), 0);
+        assert_eq!(line.grapheme_idx_to_byte_idx(1), 1);
+        assert_eq!(line.grapheme_idx_to_byte_idx(2), 4);
+        assert_eq!(line.grapheme_idx_to_byte_idx(3), 7);
+        // 行尾（追加位置）对应字符串长度
+        assert_eq!(line.grapheme_idx_to_byte_idx(4), line.len());
+        assert_eq!(Line::default().grapheme_idx_to_byte_idx(0), 0);
+    }
+
+    #[test]
+    fn insert_char_handles_full_width_graphemes() {
+        let mut line = Line::from("中文");
+        line.insert_char('前', 0);
+        line.insert_char('间', 2);
+        line.insert_char('后', line.grapheme_count());
+        assert_eq!(line.to_string(), "前中间文后");
+        assert_eq!(line.grapheme_count(), 5);
+        assert_eq!(line.width(), 10);
+        // 插入后的列位置和字节位置都按新的字素计算
+        assert_eq!(line.width_until(3), 6);
+        assert_eq!(line.grapheme_idx_at_col(7), 3);
+        assert_eq!(line.byte_range(2..3), 6..9);
+    }
+
+    #[test]
+    fn split_at_line_end_and_after_multi_byte_grapheme() {
+        // 在行尾或其后拆分时原行不变，返回空行
+        let mut line = Line::from("ab中");
+        assert!(line.split(3).is_empty());
+        assert!(line.split(9).is_empty());
+        assert_eq!(line.to_string(), "ab中");
+        // 在多字节字素之后拆分
+        let mut line = Line::from("中文ab");
+        let remainder = line.split(1);
+        assert_eq!(line.to_string(), "中");
+        assert_eq!(line.width(), 2);
+        assert_eq!(remainder.to_string(), "文ab");
+        assert_eq!(remainder.grapheme_count(), 3);
+        assert_eq!(remainder.width(), 4);
+    }
+
+    #[test]
+    fn previous_word_start_over_mixed_punctuation() {
+        let line = Line::from("let x = foo.bar(baz);  ");
+        let end = line.grapheme_count();
+        // 先跳过空白，再跳过一串标点
+        assert_eq!(line.previous_word_start(end), 19);
+        assert_eq!(line.previous_word_start(19), 16);
+        assert_eq!(line.previous_word_start(16), 15);
+        // 按 Unicode 单词分割，字母之间的 '.' 不断开单词
+        assert_eq!(line.previous_word_start(15), 8);
+        assert_eq!(line.previous_word_start(8), 6);
+        assert_eq!(line.previous_word_start(6), 4);
+        assert_eq!(line.previous_word_start(4), 0);
+        assert_eq!(line.previous_word_start(0), 0);
+        // 连续的标点作为一组，下划线和数字属于单词，多字节字符按字素计数
+        let line = Line::from("中文_1 ->> x");
+        assert_eq!(line.previous_word_start(10), 9);
+        assert_eq!(line.previous_word_start(8), 5);
+        assert_eq!(line.previous_word_start(5), 0);
+        // 光标前只有空白时到行首
+        assert_eq!(Line::from("   x").previous_word_start(3), 0);
+    }
+
+    // 计时结果取决于机器，默认不运行：cargo test --release -- --ignored visible_substr
+    #[test]
+    #[ignore = "计时测试"]
+    fn visible_substr_of_very_long_line_is_fast() {
+        use std::time::{Duration, Instant};
+        let line = Line::from(&"ab\t中文, ".repeat(1 << 17));
+        assert!(line.len() >= 1 << 20);
+        let width = line.width();
+        let start = Instant::now();
+        for idx in 0..1000 {
+            let col = width / 1000 * idx;
+            let visible = line.get_annotated_visible_substr(col..col.saturating_add(200), None);
+            assert!(!visible.to_string().is_empty());
+        }
+        // 只处理可见的片段，每次耗时与行长无关
+        let elapsed = start.elapsed();
+        assert!(elapsed < Duration::from_secs(1), "{elapsed:?}");
+    }
+
+    // 按字节和列位置逐一比较增量维护的结果与重新构建的行
+    fn assert_same_positions(line: &Line, context: &str) {
+        let rebuilt = Line::with_tab_width(line, line.tab_width());
+        assert_eq!(line.grapheme_count(), rebuilt.grapheme_count(), "{context}");
+        for idx in 0..=rebuilt.grapheme_count() {
+            assert_eq!(line.byte_range(idx..idx), rebuilt.byte_range(idx..idx), "{context}");
+            assert_eq!(line.width_until(idx), rebuilt.width_until(idx), "{context} {idx}");
+        }
+        for col in 0..rebuilt.width().saturating_add(2) {
+            let expected = rebuilt.grapheme_idx_at_col(col);
+            assert_eq!(line.grapheme_idx_at_col(col), expected, "{context} {col}");
+        }
+        for byte_idx in 0..=rebuilt.len() {
+            let expected = rebuilt.byte_idx_to_grapheme_idx(byte_idx);
+            assert_eq!(line.byte_idx_to_grapheme_idx(byte_idx), expected, "{context}");
+        }
+        let width = rebuilt.width();
+        assert_eq!(line.get_visible_graphemes(1..width), rebuilt.get_visible_graphemes(1..width));
+    }
+
+    #[test]
+    fn edits_keep_positions_consistent() {
+        // 固定种子的线性同余随机数，覆盖制表符、全角字符和组合字符在各种位置的编辑
+        let mut seed: u64 = 1;
+        let mut next = |bound: usize| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            usize::try_from(seed >> 33).unwrap_or(0) % bound.max(1)
+        };
+        let pieces = ["a", "\t", "中", "\u{301}", " ", "xy\tz", "e\u{301}中"];
+        let mut line = Line::with_tab_width("ab\tc中\td", 4);
+        for step in 0..2000 {
+            let at = next(line.grapheme_count().saturating_add(1));
+            let piece = pieces[next(pieces.len())];
+            let operation = next(7);
+            match operation {
+                0 | 1 => line.insert_str(piece, at),
+                2 => line.insert_char(piece.chars().next().unwrap_or('a'), at),
+                3 => line.delete(at),
+                4 => line.delete_range(at..at.saturating_add(next(4))),
+                5 => line.transpose(at),
+                _ if line.grapheme_count() > 40 => drop(line.split(at)),
+                _ => line.append(&Line::from(piece)),
+            }
+            assert_same_positions(&line, &format!("step {step}, operation {operation}, at {at}"));
+        }
+    }
+
+    // 返回各片段存放的起始字节和列（未换算待平移的偏移），用于统计一次编辑改写了多少项
+    fn stored_positions(line: &Line) -> Vec<(usize, usize)> {
+        line.fragments
+            .iter()
+            .map(|fragment| fragment.start)
+            .zip(line.col_starts.iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn editing_a_very_long_line_touches_only_nearby_fragments() {
+        for text in ["ab中文, ".repeat(20_000), "ab\t中, ".repeat(20_000)] {
+            let mut line = Line::from(&text);
+            line.insert_char('x', 1000);
+            let before = stored_positions(&line);
+            // 在同一处连续输入和删除：每次只改写编辑位置附近的片段，与行长无关
+            for offset in 0..100 {
+                line.insert_char('y', 1001 + offset);
+            }
+            for _ in 0..50 {
+                line.delete(1050);
+            }
+            let after = stored_positions(&line);
+            // 编辑位置之后的片段下标整体后移，因此分别从行首和行尾对齐，统计中间改写过的片段
+            let unchanged_head = before.iter().zip(&after).take_while(|(old, new)| old == new);
+            let unchanged_tail = before
+                .iter()
+                .rev()
+                .zip(after.iter().rev())
+                .take_while(|(old, new)| old == new);
+            let changed = after
+                .len()
+                .saturating_sub(unchanged_head.count())
+                .saturating_sub(unchanged_tail.count());
+            assert!(changed < 200, "{changed}");
+            // 抽样比较换算后的位置与重新构建的行
+            let rebuilt = Line::from(&line);
+            assert_eq!(line.grapheme_count(), rebuilt.grapheme_count());
+            for idx in (0..=rebuilt.grapheme_count()).step_by(997).chain([1050, 1051]) {
+                assert_eq!(line.byte_range(idx..idx), rebuilt.byte_range(idx..idx));
+                let col = rebuilt.width_until(idx);
+                assert_eq!(line.width_until(idx), col);
+                assert_eq!(line.grapheme_idx_at_col(col), rebuilt.grapheme_idx_at_col(col));
+            }
+            assert_eq!(line.width(), rebuilt.width());
+        }
+    }
+}
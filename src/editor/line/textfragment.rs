@@ -10,3 +10,9 @@ pub struct TextFragment {
     pub replacement: Option<char>,
     pub start: ByteIdx,
 }
+
+impl TextFragment {
+    pub const fn is_tab(&self) -> bool {
+        matches!(self.rendered_width, GraphemeWidth::Tab(_))
+    }
+}
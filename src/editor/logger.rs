@@ -0,0 +1,57 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Error, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// 调试日志的环境变量，值为日志文件路径；命令行的 --log 优先
+pub const LOG_ENV_VAR: &str = "TZT_LOG";
+
+// 追加写入文件的简单日志后端。终端处于原始模式和备用屏幕中，日志绝不能写到终端上
+struct FileLogger {
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let Ok(mut file) = self.file.lock() else {
+            return;
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        // 写日志失败时无处可报告，直接忽略
+        let _ = writeln!(
+            file,
+            "{}.{:03} {:<5} {}",
+            now.as_secs(),
+            now.subsec_millis(),
+            record.level(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+// 启用写入指定文件的日志。未调用时日志级别保持为 Off，各处的日志宏只做一次级别比较
+pub fn init(path: &Path) -> Result<(), Error> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    log::set_boxed_logger(Box::new(FileLogger {
+        file: Mutex::new(file),
+    }))
+    .map_err(Error::other)?;
+    log::set_max_level(LevelFilter::Debug);
+    Ok(())
+}
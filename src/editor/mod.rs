@@ -1,33 +1,71 @@
-use crossterm::event::{read, Event, KeyEvent, KeyEventKind};
+use crossterm::{
+    cursor::SetCursorStyle,
+    event::{poll, read, Event, KeyEvent, KeyEventKind},
+};
 use std::{
-    env,
-    io::Error,
+    cmp::min,
+    fs,
+    io::{self, stdout, Error, ErrorKind, IsTerminal},
     panic::{set_hook, take_hook},
+    path::PathBuf,
+    time::Instant,
 };
+use unicode_width::UnicodeWidthStr;
 use crate::prelude::*;
 
+mod args;
+use args::Args;
+
+mod config;
+use config::Config;
+
+mod logger;
+
+mod clipboard;
+use clipboard::Clipboard;
+
+mod core;
+pub use core::Core;
+
 mod annotatedstring;
 use annotatedstring::AnnotatedString;
 
-mod command;
+pub mod command;
 use command::{
-    Command::{self, Edit, Move, System},
-    Edit::InsertNewline,
+    load_key_bindings,
+    Command::{self, BlockSelect, Edit, Move, MultiCursor, Select, System},
+    Edit::{Insert, InsertNewline},
     Move::{Down, Left, Right, Up},
-    System::{Dismiss, Quit, Resize, Save, Search},
+    MultiCursor::AddNextOccurrence,
+    System::{
+        CheckIndentation, ConvertIndentation, Dismiss, DuplicateSelection, ExportSelection, FindAll,
+        Fold, Quit, Resize, SurroundSelection,
+        Save, Search, InsertDateTime, NextMisspelling, QuitWithOutput, RepeatLastEdit, SearchNext, SearchPrev,
+        NewBuffer, SetFileType, ShowStatistics, ToggleIndentGuides, ToggleOverwrite, ToggleStickyLines,
+        ToggleStatusBar, ToggleZenMode, Unfold, NextChange, PrevChange, Undo, Redo,
+        Copy, Cut, Paste, Replace, ToggleLineNumbers, ToggleSoftIndent, ToggleComment,
+        DuplicateLine, MoveLineUp, MoveLineDown, ToggleReadOnly, JumpToMatchingBracket,
+        CommandPalette,
+    },
 };
 
 mod line;
-use line::Line;
+pub use line::Line;
 
 mod terminal;
-use terminal::Terminal;
+use terminal::{Terminal, FLASH_DURATION};
 
 mod uicomponents;
-use uicomponents::{View, CommandBar, MessageBar, StatusBar, UIComponent};
+use uicomponents::{
+    file_type_for_name, register_builtin_highlighters, register_grammar_files,
+    registered_file_types, CommandBar, MatchList, MessageBar, StatusBar, UIComponent, View,
+};
+pub use uicomponents::Buffer;
+#[cfg(feature = "spellcheck")]
+use uicomponents::load_dictionary;
 
 mod annotation;
-use annotation::Annotation;
+pub use annotation::Annotation;
 
 pub mod annotationtype;
 pub use annotationtype::AnnotationType;
@@ -35,22 +73,59 @@ pub use annotationtype::AnnotationType;
 mod documentstatus;
 use documentstatus::DocumentStatus;
 
+mod documentstatistics;
+use documentstatistics::DocumentStatistics;
+
 mod filetype;
 use filetype::FileType;
 
+mod indentstyle;
+use indentstyle::IndentStyle;
+
 const QUIT_TIMES: u8 = 3;
+const MATCH_LIST_MAX_HEIGHT: usize = 10;
+const GOTO_COMMAND: &str = "goto"; // 命令面板中跳转到指定行的命令，不对应系统命令
+const READ_ONLY_MESSAGE: &str = "文档是只读的（Alt-Shift-R 关闭只读模式）。";
 
 #[derive(Eq, PartialEq, Default)]
 enum PromptType {
     Search,
     Save,
+    MatchList,
+    ConfirmQuit,
+    ConfirmNewBuffer,
+    SetFileType,
+    ExportSelection,
+    SurroundSelection,
+    Replace, // 查找替换，所处的阶段见 Editor::replace_step
+    Command, // 命令面板：按名称执行命令
     #[default]
     None,
 }
 
+// 查找替换的各个阶段：输入查询，输入替换文本，逐个确认匹配项
+#[derive(Default)]
+enum ReplaceStep {
+    #[default]
+    Query,
+    Replacement,
+    Confirm { replacement: String, count: usize },
+}
+
 impl PromptType {
     fn is_prompt(&self) -> bool {
-        matches!(self, Self::Search | Self::Save)
+        matches!(
+            self,
+            Self::Search
+                | Self::Save
+                | Self::ConfirmQuit
+                | Self::ConfirmNewBuffer
+                | Self::SetFileType
+                | Self::ExportSelection
+                | Self::SurroundSelection
+                | Self::Replace
+                | Self::Command
+        )
     }
 }
 
@@ -61,16 +136,38 @@ pub struct Editor {
     status_bar: StatusBar,
     message_bar: MessageBar,
     command_bar: CommandBar,
+    match_list: MatchList,
+    config: Config,
     prompt_type: PromptType,
     terminal_size: Size,
     title: String,
     quit_times: u8,
+    quit_after_save: bool, // 确认退出时选择了保存，保存成功后退出
+    // 以 --stdout 启动时，退出后将文档写入标准输出
+    write_to_stdout: bool,
+    output_confirmed: bool,
+    // 最近一次连续编辑的命令序列，供 Alt-. 重复
+    last_edits: Vec<command::Edit>,
+    is_edit_run: bool,
+    // 最近一次报告过的渲染错误，避免重复提示
+    last_render_error: Option<String>,
+    // 隐藏状态栏和消息栏，把这些行让给文档区域；提示期间命令栏仍然占用最后一行
+    hide_status_bar: bool,
+    hide_message_bar: bool,
+    clipboard: Clipboard, // Ctrl-C / Ctrl-X 复制或剪切的文本，供 Ctrl-V 粘贴
+    replace_step: ReplaceStep,
+    soft_indent: bool, // 软缩进模式，初始值来自配置
+    readonly_arg: bool, // 以 --readonly 启动，只读模式下不允许保存
+    // 开启只读模式时文档已有未保存的修改，此时退出仍需确认；以 --readonly 启动时为 false
+    modified_before_read_only: bool,
+    flash_until: Option<Instant>, // 视觉铃声反转屏幕的结束时间，之后的刷新中恢复
 }
 
 impl Editor {
     fn initialize_panic_hook() {
         let current_hook = take_hook();
         set_hook(Box::new(move |panic_info| {
+            log::error!("{panic_info}");
             let _ = Terminal::terminate();
             current_hook(panic_info);
         }));
@@ -79,21 +176,103 @@ impl Editor {
     // 初始化编辑器
     pub fn new() -> Result<Self, Error> {
         Self::initialize_panic_hook();
+        let args = Args::parse();
+        let log_error = args.log_file().and_then(|path| {
+            logger::init(&path)
+                .err()
+                .map(|err| format!("{}: {err}", path.display()))
+        });
+        log::info!("启动 {NAME}，参数: {:?}", std::env::args().skip(1).collect::<Vec<_>>());
+        Terminal::set_color_enabled(args.use_color());
+        Terminal::set_light_background(args.light_background());
+        register_builtin_highlighters();
+        let (config, config_error) = match Config::load() {
+            Ok(config) => (config, None),
+            Err(err) => (Config::default(), Some(err)),
+        };
+        let grammar_errors = config::config_dir()
+            .map(|dir| register_grammar_files(&dir.join("grammars")))
+            .unwrap_or_default();
+        let key_binding_errors =
+            load_key_bindings(config::config_dir().map(|dir| dir.join("keys.toml")).as_deref());
+        #[cfg(feature = "spellcheck")]
+        let dictionary_error = config
+            .dictionary
+            .clone()
+            .or_else(|| config::config_dir().map(|dir| dir.join("dictionary.txt")))
+            .and_then(|path| load_dictionary(&path).err());
         // 初始化终端
         Terminal::initialize()?;
 
         let mut editor = Self::default();
+        editor.soft_indent = config.soft_indent;
+        editor.config = config;
+        editor.write_to_stdout = args.stdout;
+        editor.readonly_arg = args.readonly;
+        editor.view.set_tab_width(editor.config.tab_width); // 须在读取任何文本之前设置
+        editor.view.set_undo_limit(editor.config.undo_limit);
+        editor.view.set_wrap_search(editor.config.search_wrap);
+        editor.view.set_horizontal_scrolling(
+            editor.config.horizontal_scroll_step,
+            editor.config.horizontal_scroll_margin,
+        );
+        editor.view.set_gutter(
+            editor.config.line_numbers,
+            editor.config.sign_column,
+            editor.config.gutter_separator,
+        );
         let size = Terminal::size().unwrap_or_default();
         editor.handle_resize_command(size);
         editor.update_message("帮助信息: Ctrl + F = 查找 | Ctrl + S = 保存 | Ctrl + Q = 退出");
 
-        let args: Vec<String> = env::args().collect();
-        if let Some(file_name) = args.get(1) {
+        // 没有指定文件名时，标准输入是管道或文件（例如 cat foo | tzt）也从中读取；
+        // 终端交互不受影响，crossterm 在标准输入不是终端时改用 /dev/tty
+        let stdin = io::stdin();
+        if args.stdin || (args.file_name.is_none() && !stdin.is_terminal()) {
+            if stdin.is_terminal() {
+                editor.update_message("WARNING! 标准输入不是管道或文件，未读取任何内容。");
+            } else if let Err(err) = editor.view.load_from_reader(stdin.lock()) {
+                editor.update_message(&format!("ERROR: 无法读取标准输入: {err}"));
+            } else if editor.view.get_status().total_lines == 0 {
+                editor.update_message("标准输入为空，已打开空白文档。");
+            }
+        } else if let Some(file_name) = &args.file_name {
             debug_assert!(!file_name.is_empty());
             if editor.view.load(file_name).is_err() {
                 editor.update_message(&format!("ERROR: 无法打开文件: {file_name}"));
+            } else {
+                let mixed_count = editor.view.mixed_indentation_lines().len();
+                let long_count = editor.view.very_long_line_count();
+                if long_count > 0 {
+                    editor.update_message(&format!(
+                        "WARNING! 有 {long_count} 个超长行（如压缩后的代码），这些行不进行语法高亮。"
+                    ));
+                } else if mixed_count > 0 {
+                    editor.update_message(&format!(
+                        "WARNING! 有 {mixed_count} 行的缩进混用了制表符和空格（Alt-I 定位）。"
+                    ));
+                }
             }
         }
+        if args.readonly {
+            editor.view.toggle_read_only();
+        }
+        if let Some(err) = key_binding_errors.first() {
+            editor.update_message(&format!("WARNING! 按键配置 keys.toml 有误: {err}"));
+        }
+        if let Some(err) = grammar_errors.first() {
+            editor.update_message(&format!("WARNING! 无法加载语法定义 {err}"));
+        }
+        #[cfg(feature = "spellcheck")]
+        if let Some(err) = dictionary_error {
+            editor.update_message(&format!("WARNING! 无法加载拼写检查单词表: {err}"));
+        }
+        if let Some(err) = log_error {
+            editor.update_message(&format!("WARNING! 无法打开日志文件 {err}"));
+        }
+        if let Some(err) = config_error {
+            editor.update_message(&format!("WARNING! 无法加载配置文件 config.toml: {err}"));
+        }
         editor.refresh_status();
         Ok(editor)
     }
@@ -105,6 +284,12 @@ impl Editor {
             if self.should_quit {
                 break;
             }
+            // 屏幕反转期间最多等待到反转结束，超时后回到循环开头刷新以恢复屏幕
+            if let Some(until) = self.flash_until {
+                if !poll(until.saturating_duration_since(Instant::now())).unwrap_or(false) {
+                    continue;
+                }
+            }
             match read() {
                 Ok(event) => self.evaluate_event(event),
                 Err(err) => {
@@ -114,8 +299,9 @@ impl Editor {
                     }
                     #[cfg(not(debug_assertions))]
                     {
+                        log::error!("读取事件失败: {err}");
                         // 错误提示
-                        self.update_message("读取事件时发生错误，请重试。");
+                        self.update_message(&format!("读取事件时发生错误，请重试: {err}"));
                     }
                 }
             }
@@ -124,42 +310,131 @@ impl Editor {
     }
 
     fn refresh_screen(&mut self) {
+        // 先于终端大小的检查，否则反转的屏幕一直不恢复，事件循环也会以零超时反复等待
+        if self.flash_until.is_some_and(|until| Instant::now() >= until) {
+            self.flash_until = None;
+            let _ = Terminal::end_flash(); // 与 ring_bell 相同，视觉铃声失败时不做提示
+        }
         if self.terminal_size.height == 0 || self.terminal_size.width == 0 {
             return;
         }
         let bottom_bar_row = self.terminal_size.height.saturating_sub(1);
-        let _ = Terminal::hide_caret();
+        // 收集本次刷新中的绘制和终端错误，最后统一报告
+        let mut errors = Vec::new();
+        errors.extend(Terminal::hide_caret().err());
         if self.in_prompt() {
-            self.command_bar.render(bottom_bar_row);
-        } else {
-            self.message_bar.render(bottom_bar_row);
+            errors.extend(self.command_bar.render(bottom_bar_row).err());
+        } else if self.is_bottom_bar_visible() {
+            errors.extend(self.message_bar.render(bottom_bar_row).err());
         }
-        if self.terminal_size.height > 1 {
-            self.status_bar
-                .render(self.terminal_size.height.saturating_sub(2));
+        let status_bar_row = self
+            .terminal_size
+            .height
+            .saturating_sub(1)
+            .saturating_sub(usize::from(self.is_bottom_bar_visible()));
+        if !self.hide_status_bar && self.terminal_size.height > 1 {
+            errors.extend(self.status_bar.render(status_bar_row).err());
         }
-        if self.terminal_size.height > 2 {
-            self.view.render(0);
+        if self.terminal_size.height > self.bar_rows() {
+            if self.prompt_type == PromptType::None {
+                self.view.reveal_caret();
+            }
+            self.view.track_caret_bracket();
+            if self.view.needs_redraw() {
+                self.match_list.set_needs_redraw(true); // 视图重绘会覆盖匹配列表，因此需要一同重绘
+            }
+            errors.extend(self.view.render(0).err());
+            if self.prompt_type == PromptType::MatchList {
+                let origin = self.match_list_origin();
+                errors.extend(self.match_list.render(origin).err());
+            }
         }
         let new_caret_pos = if self.in_prompt() {
+            // 命令栏与终端同宽，光标最多停在最右一列
+            let col = self.command_bar.caret_position_col();
+            debug_assert!(col < self.terminal_size.width);
             Position {
                 row: bottom_bar_row,
-                col: self.command_bar.caret_position_col(),
+                col,
+            }
+        } else if self.prompt_type == PromptType::MatchList {
+            Position {
+                row: self
+                    .match_list_origin()
+                    .saturating_add(self.match_list.selected_row()),
+                col: 0,
             }
         } else {
             self.view.caret_position()
         };
-        debug_assert!(new_caret_pos.col <= self.terminal_size.width);
-        debug_assert!(new_caret_pos.row <= self.terminal_size.height);
+        // 各组件已将光标限制在自身范围内，这里再限制一次，保证不会移出屏幕的最后一行和最后一列
+        debug_assert!(new_caret_pos.col < self.terminal_size.width);
+        debug_assert!(new_caret_pos.row < self.terminal_size.height);
+        let new_caret_pos = Position {
+            row: min(new_caret_pos.row, bottom_bar_row),
+            col: min(
+                new_caret_pos.col,
+                self.terminal_size.width.saturating_sub(1),
+            ),
+        };
 
-        let _ = Terminal::move_caret_to(new_caret_pos);
-        let _ = Terminal::show_caret();
-        let _ = Terminal::execute();
+        errors.extend(Terminal::move_caret_to(new_caret_pos).err());
+        errors.extend(Terminal::set_cursor_style(self.cursor_style()).err());
+        errors.extend(Terminal::show_caret().err());
+        errors.extend(Terminal::execute().err());
+        self.report_render_errors(&errors);
     }
 
+    // 在消息栏提示渲染错误并记入日志。
+    // 同一错误持续出现时只报告一次，避免每次刷新都重复写日志
+    fn report_render_errors(&mut self, errors: &[Error]) {
+        let Some(first) = errors.first() else {
+            self.last_render_error = None;
+            return;
+        };
+        let description = first.to_string();
+        if self.last_render_error.as_ref() == Some(&description) {
+            return;
+        }
+        self.last_render_error = Some(description);
+        // 第一个错误随消息栏的提示记入日志
+        for err in errors.iter().skip(1) {
+            log::error!("渲染错误: {err}");
+        }
+        self.update_message(&format!("ERROR: 渲染出错: {first}"));
+    }
+
+    // 根据当前模式选择光标形状：覆盖模式为方块，输入文字时为竖线，在匹配列表中选择时为下划线
+    fn cursor_style(&self) -> SetCursorStyle {
+        match self.prompt_type {
+            PromptType::Search
+            | PromptType::Save
+            | PromptType::ConfirmQuit
+            | PromptType::ConfirmNewBuffer
+            | PromptType::SetFileType
+            | PromptType::ExportSelection
+            | PromptType::SurroundSelection
+            | PromptType::Replace
+            | PromptType::Command => {
+                SetCursorStyle::SteadyBar
+            }
+            PromptType::MatchList => SetCursorStyle::SteadyUnderScore,
+            PromptType::None if self.view.is_overwrite() => SetCursorStyle::SteadyBlock,
+            PromptType::None => SetCursorStyle::SteadyBar,
+        }
+    }
+
+    // 状态没有变化时跳过，避免每次按键都重新生成状态和窗口标题
     fn refresh_status(&mut self) {
+        if !self.view.take_status_changed() {
+            return;
+        }
         let status = self.view.get_status();
-        let title = format!("{} - {NAME}", status.file_name);
+        let title = format!(
+            "{}{} - {NAME}",
+            status.title_prefix_to_string(),
+            status.file_name
+        );
         self.status_bar.update_status(status);
         if title != self.title && matches!(Terminal::set_title(&title), Ok(())) {
             self.title = title;
@@ -167,6 +442,11 @@ impl Editor {
     }
 
     fn evaluate_event(&mut self, event: Event) {
+        if let Event::Paste(text) = &event {
+            log::debug!("粘贴: {} 字节", text.len());
+            self.handle_paste(text);
+            return;
+        }
         let should_process = match &event {
             Event::Key(KeyEvent { kind, .. }) => kind == &KeyEventKind::Press,
             Event::Resize(_, _) => true,
@@ -174,22 +454,107 @@ impl Editor {
         };
 
         if should_process {
+            log::debug!("事件: {event:?}");
+            // 先清除旧消息，这次按键执行的命令设置的消息照常显示
+            if self.config.clear_message_on_key && matches!(event, Event::Key(_)) {
+                self.message_bar.clear_transient_message();
+            }
+            if self.handle_search_navigation_key(&event) {
+                return;
+            }
             if let Ok(command) = Command::try_from(event) {
                 self.process_command(command);
+            } else {
+                self.ring_bell(); // 没有绑定任何命令的按键
             }
         }
     }
 
+    // 在文档中粘贴文本；在输入型提示中只粘贴第一行，逐字符输入以保持与键入相同的效果
+    fn handle_paste(&mut self, text: &str) {
+        match self.prompt_type {
+            PromptType::None => {
+                self.reset_quit_times();
+                if self.view.is_read_only() {
+                    self.update_message(READ_ONLY_MESSAGE);
+                    return;
+                }
+                self.view.paste(text, self.config.paste_indent_into_block);
+                self.view.commit_history(false);
+            }
+            PromptType::Replace if matches!(self.replace_step, ReplaceStep::Confirm { .. }) => {}
+            PromptType::Search
+            | PromptType::Save
+            | PromptType::SetFileType
+            | PromptType::ExportSelection
+            | PromptType::Replace
+            | PromptType::Command => {
+                let first_line = text.lines().next().unwrap_or_default();
+                for character in first_line.chars() {
+                    self.process_command(Edit(Insert(character)));
+                }
+            }
+            // 确认提示只接受单个按键，匹配列表中没有输入框
+            PromptType::MatchList
+            | PromptType::ConfirmQuit
+            | PromptType::ConfirmNewBuffer
+            | PromptType::SurroundSelection => {}
+        }
+    }
+
+    // 搜索提示中，由配置的 search_next_key / search_prev_key 切换匹配项，已处理时返回 true
+    fn handle_search_navigation_key(&mut self, event: &Event) -> bool {
+        let Event::Key(key_event) = event else {
+            return false;
+        };
+        if self.prompt_type != PromptType::Search {
+            return false;
+        }
+        if self.config.search_next_key.is_some_and(|key| key.matches(key_event)) {
+            self.view.search_next();
+        } else if self.config.search_prev_key.is_some_and(|key| key.matches(key_event)) {
+            self.view.search_prev();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    // 按键已收到但无法执行时给出提示，由配置项 visual_bell 控制。
+    // 反转的屏幕在 FLASH_DURATION 之后的刷新中恢复
+    fn ring_bell(&mut self) {
+        if self.config.visual_bell && Terminal::start_flash().is_ok() {
+            self.flash_until = Instant::now().checked_add(FLASH_DURATION);
+        }
+    }
+
     //处理命令
     fn process_command(&mut self, command: Command) {
+        log::debug!("命令: {command:?}");
+        let is_typing = self.prompt_type == PromptType::None && matches!(command, Edit(Insert(_)));
         match command {
             System(Resize(size)) => self.handle_resize_command(size),
             _ => match self.prompt_type {
                 PromptType::Search => self.process_command_during_search(command),
                 PromptType::Save => self.process_command_during_save(command),
+                PromptType::MatchList => self.process_command_during_match_list(command),
+                PromptType::ConfirmQuit => self.process_command_during_confirm_quit(command),
+                PromptType::ConfirmNewBuffer => {
+                    self.process_command_during_confirm_new_buffer(command);
+                }
+                PromptType::SetFileType => self.process_command_during_set_file_type(command),
+                PromptType::ExportSelection => {
+                    self.process_command_during_export_selection(command);
+                }
+                PromptType::SurroundSelection => {
+                    self.process_command_during_surround_selection(command);
+                }
+                PromptType::Replace => self.process_command_during_replace(command),
+                PromptType::Command => self.process_command_during_command(command),
                 PromptType::None => self.process_command_no_prompt(command),
             }
         }
+        self.view.commit_history(is_typing);
     }
 
     fn process_command_no_prompt(&mut self, command: Command) {
@@ -198,21 +563,229 @@ impl Editor {
             return;
         }
         self.reset_quit_times(); // 重置退出计数
+        if self.view.is_read_only() && Self::modifies_document(command) {
+            self.update_message(READ_ONLY_MESSAGE);
+            return;
+        }
+        self.record_edit_run(command);
+        if let Some(effective) = core::apply(&mut self.view, command, self.soft_indent) {
+            if !effective {
+                self.report_ineffective_command(command);
+            }
+            return;
+        }
 
         match command {
-            System(Quit | Resize(_) | Dismiss) => {} // 退出和调整大小已经在上面处理，其他不适用
+            System(Quit | Resize(_)) => {} // 退出和调整大小已经在上面处理
+            // 只作用于文档的命令已经由 core::apply 处理
+            System(
+                Undo | Redo | DuplicateLine | DuplicateSelection | MoveLineUp | MoveLineDown
+                | ToggleComment,
+            )
+            | Edit(_)
+            | Move(_)
+            | BlockSelect(_)
+            | Select(_)
+            | MultiCursor(_) => {}
+            System(Dismiss) => {
+                self.view.clear_selection();
+                self.view.collapse_carets();
+            }
             System(Search) => self.set_prompt(PromptType::Search),
+            System(Replace) => {
+                self.replace_step = ReplaceStep::Query;
+                self.set_prompt(PromptType::Replace);
+            }
+            System(SetFileType) => self.set_prompt(PromptType::SetFileType),
+            System(CommandPalette) => self.set_prompt(PromptType::Command),
+            System(ExportSelection) => {
+                if self.view.has_selection() {
+                    self.set_prompt(PromptType::ExportSelection);
+                } else {
+                    self.update_message("没有选区，请先用 Alt-Shift-方向键选择要导出的文本。");
+                }
+            }
+            System(SurroundSelection) => {
+                if self.view.has_selection() {
+                    self.set_prompt(PromptType::SurroundSelection);
+                } else {
+                    self.update_message("没有选区，请先用 Alt-Shift-方向键选择要包围的文本。");
+                }
+            }
+            System(ToggleZenMode) => {
+                let hide = !(self.hide_status_bar && self.hide_message_bar);
+                self.hide_status_bar = hide;
+                self.hide_message_bar = hide;
+                self.layout();
+            }
+            System(ToggleStatusBar) => {
+                self.hide_status_bar = !self.hide_status_bar;
+                self.layout();
+            }
+            System(SearchNext) => {
+                let result = self.view.repeat_search_next();
+                self.report_repeat_search(result);
+            }
+            System(SearchPrev) => {
+                let result = self.view.repeat_search_prev();
+                self.report_repeat_search(result);
+            }
             System(Save) => self.handle_save_command(),
-            Edit(edit_command) => self.view.handle_edit_command(edit_command),
-            Move(move_command) => self.view.handle_move_command(move_command),
+            System(NewBuffer) => {
+                if self.view.get_status().is_modified {
+                    self.set_prompt(PromptType::ConfirmNewBuffer);
+                } else {
+                    self.new_buffer();
+                }
+            }
+            System(FindAll) => self.update_message("请先按 Ctrl-F 输入查询，再按 Ctrl-L 列出全部匹配。"),
+            System(CheckIndentation) => self.handle_check_indentation_command(),
+            System(ConvertIndentation(style)) => self.handle_convert_indentation_command(style),
+            System(ToggleSoftIndent) => {
+                self.soft_indent = !self.soft_indent;
+                if self.soft_indent {
+                    self.update_message("软缩进：Tab 插入空格。");
+                } else {
+                    self.update_message("Tab 插入制表符。");
+                }
+            }
+            System(JumpToMatchingBracket) => match self.view.jump_to_matching_bracket() {
+                None => self.update_message("光标处没有括号。"),
+                Some(false) => self.update_message("没有找到配对的括号。"),
+                Some(true) => {}
+            },
+            System(ToggleReadOnly) => {
+                if self.view.toggle_read_only() {
+                    self.modified_before_read_only = self.view.get_status().is_modified;
+                    self.update_message("只读模式：不能修改文档。");
+                } else {
+                    self.update_message("已关闭只读模式。");
+                }
+            }
+            System(ToggleLineNumbers) => {
+                if self.view.toggle_line_numbers() {
+                    self.update_message("已显示行号。");
+                } else {
+                    self.update_message("已隐藏行号。");
+                }
+            }
+            System(ToggleIndentGuides) => {
+                if self.view.toggle_indent_guides() {
+                    self.update_message("已显示缩进参考线。");
+                } else {
+                    self.update_message("已隐藏缩进参考线。");
+                }
+            }
+            System(InsertDateTime) => self.handle_insert_date_time_command(),
+            System(RepeatLastEdit) => self.handle_repeat_last_edit_command(),
+            System(QuitWithOutput) => {
+                if self.write_to_stdout {
+                    self.output_confirmed = true;
+                    self.should_quit = true;
+                } else {
+                    self.update_message("仅在以 --stdout 启动时可以输出到标准输出。");
+                }
+            }
+            System(NextMisspelling) => self.handle_next_misspelling_command(),
+            System(Copy) => {
+                let text = self.view.copy_text();
+                self.copy_to_clipboard(text, "已复制");
+            }
+            System(Cut) => {
+                let text = self.view.cut_text();
+                self.copy_to_clipboard(text, "已剪切");
+            }
+            System(Paste) => {
+                let text = self.clipboard.get();
+                if text.is_empty() {
+                    self.update_message("剪贴板为空。");
+                } else {
+                    self.view.paste_text(&text);
+                }
+                if let Some(err) = self.clipboard.take_error() {
+                    self.update_message(&format!(
+                        "WARNING! 无法读取系统剪贴板，已改用编辑器内的剪贴板: {err}"
+                    ));
+                }
+            }
+            System(NextChange) => self.handle_move_to_change_command(true),
+            System(PrevChange) => self.handle_move_to_change_command(false),
+            System(ShowStatistics) => {
+                let statistics = self.view.get_statistics();
+                self.update_message(&statistics.to_string());
+            }
+            System(ToggleOverwrite) => {
+                if self.view.toggle_overwrite() {
+                    self.update_message("覆盖模式：输入的字符将替换光标处的字符。");
+                } else {
+                    self.update_message("插入模式。");
+                }
+            }
+            System(ToggleStickyLines) => {
+                if self.view.toggle_sticky_lines() {
+                    self.update_message("已开启上下文固定行。");
+                } else {
+                    self.update_message("已关闭上下文固定行。");
+                }
+            }
+            System(Fold) => {
+                if !self.view.fold_at_caret() {
+                    self.update_message("光标处没有可以折叠的代码块。");
+                }
+            }
+            System(Unfold) => {
+                if !self.view.unfold_at_caret() {
+                    self.update_message("当前行没有折叠。");
+                }
+            }
+        }
+    }
+
+    // 只作用于文档的命令没有效果时给出提示：能说明原因的显示消息，其他的按配置闪烁屏幕
+    fn report_ineffective_command(&mut self, command: Command) {
+        match command {
+            System(Undo) => self.update_message("没有可以撤销的修改。"),
+            System(Redo) => self.update_message("没有可以重做的修改。"),
+            System(DuplicateSelection) => {
+                self.update_message("没有选区，请先用 Alt-Shift-方向键选择要复制的文本。");
+            }
+            System(ToggleComment) => {
+                let file_type = self.view.get_status().file_type;
+                self.update_message(&format!("文件类型 {file_type} 没有行注释标记。"));
+            }
+            MultiCursor(AddNextOccurrence) => {
+                self.update_message("没有更多可添加光标的匹配项。");
+            }
+            // 文档开头的退格和末尾的删除没有效果，与无法移动时一样提示
+            _ => self.ring_bell(),
         }
     }
 
     // 处理调整大小命令
     fn handle_resize_command(&mut self, size: Size) {
         self.terminal_size = size;
+        self.layout();
+    }
+
+    // 最后一行显示消息栏或命令栏
+    fn is_bottom_bar_visible(&self) -> bool {
+        !self.hide_message_bar || self.in_prompt()
+    }
+    // 文档区域下方各栏占用的行数
+    fn bar_rows(&self) -> usize {
+        usize::from(!self.hide_status_bar).saturating_add(usize::from(self.is_bottom_bar_visible()))
+    }
+
+    // 按终端尺寸和各栏是否显示重新分配各组件的区域
+    fn layout(&mut self) {
+        let size = self.terminal_size;
+        let view_height = size.height.saturating_sub(self.bar_rows());
         self.view.resize(Size {
-            height: size.height.saturating_sub(2),
+            height: view_height,
+            width: size.width,
+        });
+        self.match_list.resize(Size {
+            height: MATCH_LIST_MAX_HEIGHT.min(view_height),
             width: size.width,
         });
         let bar_size = Size {
@@ -224,10 +797,74 @@ impl Editor {
         self.command_bar.resize(bar_size);
     }
 
-    // 处理退出命令
+    fn report_repeat_search(&mut self, result: Option<bool>) {
+        match result {
+            None => self.update_message("没有上一次搜索，请先按 Ctrl-F 搜索。"),
+            Some(false) if !self.config.search_wrap => {
+                self.update_message("已到达文档一端，没有更多匹配项。");
+            }
+            Some(false) => self.update_message("没有找到匹配项。"),
+            Some(true) if self.view.search_wrapped() => {
+                self.update_message("搜索已绕回，从文档的另一端继续。");
+            }
+            Some(true) => {}
+        }
+    }
+
+    // 记录连续的编辑命令，任何非编辑命令都会结束当前这段编辑
+    fn record_edit_run(&mut self, command: Command) {
+        if let Edit(edit_command) = command {
+            if !self.is_edit_run {
+                self.last_edits.clear();
+                self.is_edit_run = true;
+            }
+            self.last_edits.push(edit_command);
+        } else {
+            self.is_edit_run = false;
+        }
+    }
+
+    // 在当前光标处重放最近一次连续编辑
+    fn handle_repeat_last_edit_command(&mut self) {
+        if self.last_edits.is_empty() {
+            self.update_message("还没有可以重复的编辑。");
+            return;
+        }
+        for edit_command in self.last_edits.clone() {
+            self.view.handle_edit_command(edit_command, self.soft_indent);
+        }
+    }
+
+    // 修改文档内容的命令，在只读模式下被拒绝
+    const fn modifies_document(command: Command) -> bool {
+        matches!(
+            command,
+            Edit(_)
+                | System(
+                    Cut | Paste | Undo | Redo | Replace | ToggleComment | DuplicateLine
+                        | MoveLineUp | MoveLineDown | DuplicateSelection | SurroundSelection
+                        | ConvertIndentation(_) | InsertDateTime | RepeatLastEdit,
+                )
+        )
+    }
+
+    // 处理退出命令。只读模式下不会再修改文档，因此不提示未保存的更改
     fn handle_quit_command(&mut self) {
-        if !self.view.get_status().is_modified || self.quit_times + 1 == QUIT_TIMES {
+        // 只读模式下无法修改文档，只有开启只读之前没有修改时才不必确认
+        let is_unmodified_read_only = self.view.is_read_only() && !self.modified_before_read_only;
+        if !self.view.get_status().is_modified || is_unmodified_read_only {
+            self.should_quit = true;
+        } else if self.config.quit_prompt {
+            self.set_prompt(PromptType::ConfirmQuit);
+        } else if self.quit_times + 1 == QUIT_TIMES {
             self.should_quit = true;
+        } else if self.write_to_stdout {
+            self.update_message(&format!(
+                "WARNING! 有未保存的更改。Alt-O 输出并退出，再按 Ctrl-Q {} 次放弃。",
+                QUIT_TIMES - self.quit_times - 1
+            ));
+
+            self.quit_times += 1;
         } else if self.view.get_status().is_modified {
             self.update_message(&format!(
                 "WARNING! 文件有未保存的更改。再按 Ctrl-Q {} 次以退出。",
@@ -237,6 +874,150 @@ impl Editor {
             self.quit_times += 1;
         }
     }
+
+    // 处理确认退出提示下的命令：y 保存后退出，n 放弃更改退出，Esc 取消
+    fn process_command_during_confirm_quit(&mut self, command: Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                self.set_prompt(PromptType::None);
+                self.quit_after_save = true;
+                self.handle_save_command(); // 未命名或没有写入权限时会进入另存为提示
+                if !self.view.get_status().is_modified {
+                    self.should_quit = true;
+                } else if self.prompt_type != PromptType::Save {
+                    self.quit_after_save = false; // 保存失败，留在编辑器中
+                }
+            }
+            Edit(Insert('n' | 'N')) => self.should_quit = true,
+            Edit(Insert('o' | 'O')) if self.write_to_stdout => {
+                self.output_confirmed = true;
+                self.should_quit = true;
+            }
+            System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.update_message("已取消退出。");
+            }
+            _ => {} // 其他按键在确认退出时不适用
+        }
+    }
+
+    // 处理包围选区提示下的命令：输入的字符决定包围用的一对字符，Esc 取消
+    fn process_command_during_surround_selection(&mut self, command: Command) {
+        match command {
+            Edit(Insert(character)) => {
+                let (open, close) = surround_pair(character);
+                self.set_prompt(PromptType::None);
+                self.view.surround_selection(open, close);
+            }
+            System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.update_message("已取消包围。");
+            }
+            _ => {} // 其他按键在选择包围字符时不适用
+        }
+    }
+
+    // 处理查找替换提示下的命令：先后输入查询和替换文本（Enter 确认，Esc 取消），
+    // 然后从第一个匹配开始逐个确认：y 替换，n 跳过，a 替换其余全部，Esc 结束
+    fn process_command_during_replace(&mut self, command: Command) {
+        match (&self.replace_step, command) {
+            (ReplaceStep::Query | ReplaceStep::Replacement, System(Dismiss)) => {
+                self.set_prompt(PromptType::None);
+                self.view.dismiss_search();
+            }
+            (ReplaceStep::Query, Edit(InsertNewline)) => {
+                if self.command_bar.value().is_empty() {
+                    return;
+                }
+                self.replace_step = ReplaceStep::Replacement;
+                self.set_prompt(PromptType::Replace);
+            }
+            (ReplaceStep::Query, Edit(edit_command)) => {
+                self.command_bar.handle_edit_command(edit_command);
+                let query = self.command_bar.value();
+                self.view.search(&query);
+            }
+            (ReplaceStep::Replacement, Edit(InsertNewline)) => {
+                let replacement = self.command_bar.value();
+                if self.view.find_replace_candidate(false) {
+                    self.replace_step = ReplaceStep::Confirm {
+                        replacement,
+                        count: 0,
+                    };
+                    self.set_prompt(PromptType::Replace);
+                } else {
+                    self.finish_replace(0);
+                }
+            }
+            (ReplaceStep::Replacement, Edit(edit_command)) => {
+                self.command_bar.handle_edit_command(edit_command);
+            }
+            (ReplaceStep::Confirm { .. }, Edit(Insert(answer))) => self.confirm_replace(answer),
+            (ReplaceStep::Confirm { count, .. }, System(Dismiss)) => self.finish_replace(*count),
+            _ => {} // 其他按键在查找替换中不适用
+        }
+    }
+
+    // 按确认提示的回答处理当前匹配，没有更多匹配时结束替换
+    fn confirm_replace(&mut self, answer: char) {
+        let ReplaceStep::Confirm { replacement, count } = &mut self.replace_step else {
+            return;
+        };
+        let has_next = match answer {
+            'y' | 'Y' => {
+                if self.view.replace_current_match(replacement) {
+                    *count = count.saturating_add(1);
+                }
+                self.view.find_replace_candidate(false)
+            }
+            'n' | 'N' => self.view.find_replace_candidate(true),
+            'a' | 'A' => {
+                // 每次替换后从替换文本之后继续查找，相邻的匹配也不会被跳过
+                while self.view.replace_current_match(replacement) {
+                    *count = count.saturating_add(1);
+                    if !self.view.find_replace_candidate(false) {
+                        break;
+                    }
+                }
+                false
+            }
+            _ => return,
+        };
+        if !has_next {
+            let count = *count;
+            self.finish_replace(count);
+        }
+    }
+
+    fn finish_replace(&mut self, count: usize) {
+        self.replace_step = ReplaceStep::Query;
+        self.set_prompt(PromptType::None);
+        self.view.exit_search();
+        self.update_message(&format!("已替换 {count} 处。"));
+    }
+
+    // 处理确认新建提示下的命令：y 放弃更改并新建空白文档，n 或 Esc 取消
+    fn process_command_during_confirm_new_buffer(&mut self, command: Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                self.set_prompt(PromptType::None);
+                self.new_buffer();
+            }
+            Edit(Insert('n' | 'N')) | System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.update_message("已取消新建。");
+            }
+            _ => {} // 其他按键在确认新建时不适用
+        }
+    }
+
+    // 丢弃当前文档，换成未命名的空白文档
+    fn new_buffer(&mut self) {
+        self.view.new_buffer();
+        self.refresh_status();
+        self.update_message("已新建空白文档。");
+    }
+
     fn reset_quit_times(&mut self) {
         if self.quit_times > 0 {
             self.quit_times = 0;
@@ -244,8 +1025,99 @@ impl Editor {
         }
     }
     
+    // 检查混用缩进，并跳转到当前行之后的第一处（到达末尾后从头开始）
+    fn handle_check_indentation_command(&mut self) {
+        let lines = self.view.mixed_indentation_lines();
+        let current_line_idx = self.view.get_status().current_line_idx;
+        let Some(&target) = lines
+            .iter()
+            .find(|&&line_idx| line_idx > current_line_idx)
+            .or_else(|| lines.first())
+        else {
+            self.update_message("缩进一致：没有混用制表符和空格的行。");
+            return;
+        };
+        self.view.jump_to(Location {
+            line_idx: target,
+            grapheme_idx: 0,
+        });
+        self.update_message(&format!(
+            "有 {} 行的缩进混用了制表符和空格，当前位于第 {} 行。",
+            lines.len(),
+            target.saturating_add(1)
+        ));
+    }
+
+    // 按配置的格式在光标处插入当前日期时间
+    #[cfg(feature = "datetime")]
+    fn handle_insert_date_time_command(&mut self) {
+        use std::fmt::Write;
+        let mut text = String::new();
+        let now = chrono::Local::now();
+        if write!(text, "{}", now.format(&self.config.date_format)).is_err() {
+            self.update_message(&format!(
+                "ERROR: 无效的日期时间格式: {}",
+                self.config.date_format
+            ));
+            return;
+        }
+        self.view.insert_text(&text);
+    }
+    #[cfg(not(feature = "datetime"))]
+    fn handle_insert_date_time_command(&mut self) {
+        self.update_message("未启用日期时间功能（datetime）。");
+    }
+
+    // 保存复制或剪切的文本并报告其长度，写入系统剪贴板失败时改为提示错误
+    fn copy_to_clipboard(&mut self, text: String, action: &str) {
+        let line_count = text.lines().count();
+        if text.is_empty() {
+            let verb = action.trim_start_matches('已'); // "已剪切" 对应 "剪切"
+            self.update_message(&format!("没有可以{verb}的内容。"));
+        } else if text.ends_with('\n') {
+            self.update_message(&format!("{action} {line_count} 行。"));
+        } else {
+            let char_count = text.chars().count();
+            self.update_message(&format!("{action} {char_count} 个字符。"));
+        }
+        self.clipboard.set(text);
+        if let Some(err) = self.clipboard.take_error() {
+            self.update_message(&format!("WARNING! 无法写入系统剪贴板，只能在编辑器内粘贴: {err}"));
+        }
+    }
+
+    fn handle_move_to_change_command(&mut self, forward: bool) {
+        if !self.view.move_to_change(forward) {
+            self.update_message("没有发现变更的行。");
+        }
+    }
+
+    #[cfg(feature = "spellcheck")]
+    fn handle_next_misspelling_command(&mut self) {
+        if !self.view.move_to_next_misspelling() {
+            self.update_message("没有发现拼写错误。");
+        }
+    }
+    #[cfg(not(feature = "spellcheck"))]
+    fn handle_next_misspelling_command(&mut self) {
+        self.update_message("未启用拼写检查功能（spellcheck）。");
+    }
+
+    fn handle_convert_indentation_command(&mut self, style: IndentStyle) {
+        let changed = self.view.convert_indentation(style);
+        let target = match style {
+            IndentStyle::Tabs => "制表符",
+            IndentStyle::Spaces => "空格",
+        };
+        self.update_message(&format!("已将 {changed} 行的缩进转换为{target}。"));
+    }
+
     // 处理保存模式下的命令
     fn handle_save_command(&mut self) {
+        if self.readonly_arg && self.view.is_read_only() {
+            self.update_message("以 --readonly 打开的文档不能保存，请先按 Alt-Shift-R 关闭只读。");
+            return;
+        }
         if self.view.is_file_loaded() {
             self.save(None);
         } else {
@@ -255,8 +1127,8 @@ impl Editor {
 
     fn process_command_during_save(&mut self, command: Command) {
         match command {
-            System(Quit | Resize(_) | Search | Save) | Move(_) => {} // 保存过程中不适用，调整大小已经在此阶段处理
             System(Dismiss) => {
+                self.quit_after_save = false;
                 self.set_prompt(PromptType::None);
                 self.update_message("保存已取消。");
             }
@@ -264,21 +1136,201 @@ impl Editor {
                 let file_name = self.command_bar.value();
                 self.save(Some(&file_name));
                 self.set_prompt(PromptType::None);
+                if self.quit_after_save && !self.view.get_status().is_modified {
+                    self.should_quit = true;
+                }
+                self.quit_after_save = false;
             }
             Edit(edit_command) => self.command_bar.handle_edit_command(edit_command),
+            System(_)
+            | Move(_)
+            | BlockSelect(_)
+            | Select(_)
+            | MultiCursor(_) => {} // 保存过程中不适用，调整大小已经在此阶段处理
         }
     }
     
+    // 处理设置文件类型提示下的命令
+    fn process_command_during_set_file_type(&mut self, command: Command) {
+        match command {
+            System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.update_message("已取消设置文件类型。");
+            }
+            Edit(InsertNewline) => {
+                let name = self.command_bar.value();
+                self.set_prompt(PromptType::None);
+                if let Some(file_type) = file_type_for_name(name.trim()) {
+                    self.view.set_file_type(file_type);
+                    self.update_message(&format!("文件类型已设置为 {file_type}。"));
+                } else {
+                    let available = registered_file_types()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    self.update_message(&format!(
+                        "ERROR: 未知的文件类型: {name}（可用: {available}）"
+                    ));
+                }
+            }
+            Edit(edit_command) => self.command_bar.handle_edit_command(edit_command),
+            System(_)
+            | Move(_)
+            | BlockSelect(_)
+            | Select(_)
+            | MultiCursor(_) => {} // 设置文件类型过程中不适用，调整大小已经在此阶段处理
+        }
+    }
+
+    // 处理命令面板中的命令：Enter 执行输入的命令，名称可以是唯一的前缀；Tab 补全名称
+    fn process_command_during_command(&mut self, command: Command) {
+        match command {
+            System(Dismiss) => self.set_prompt(PromptType::None),
+            Edit(Insert('\t')) => {
+                let input = self.command_bar.value();
+                let candidates = palette_candidates(&input);
+                if !input.contains(char::is_whitespace) && !candidates.is_empty() {
+                    self.command_bar.set_value(&common_prefix(&candidates));
+                }
+            }
+            Edit(InsertNewline) => {
+                let input = self.command_bar.value();
+                self.set_prompt(PromptType::None);
+                self.execute_palette_command(&input);
+            }
+            Edit(edit_command) => self.command_bar.handle_edit_command(edit_command),
+            System(_)
+            | Move(_)
+            | BlockSelect(_)
+            | Select(_)
+            | MultiCursor(_) => {} // 命令面板中不适用，调整大小已经在此阶段处理
+        }
+        if self.prompt_type == PromptType::Command {
+            self.update_command_hint();
+        }
+    }
+
+    // 执行命令面板中输入的命令。goto 跟一个行号参数，其他命令都是不带参数的系统命令
+    fn execute_palette_command(&mut self, input: &str) {
+        let mut words = input.split_whitespace();
+        let Some(name) = words.next() else {
+            return; // 没有输入任何内容时相当于取消
+        };
+        let candidates = palette_candidates(name);
+        // 候选名称都以输入开头，长度相同即完全匹配，例如输入 fold 时不会与 fold 之外的名称冲突
+        let exact = candidates.iter().find(|candidate| candidate.len() == name.len());
+        let resolved = match (exact, candidates.as_slice()) {
+            (Some(&resolved), _) | (None, &[resolved]) => resolved,
+            (None, []) => {
+                self.update_message(&format!("ERROR: 未知的命令: {name}"));
+                return;
+            }
+            (None, _) => {
+                self.update_message(&format!(
+                    "ERROR: 命令 {name} 不明确，可能是: {}",
+                    candidates.join(", ")
+                ));
+                return;
+            }
+        };
+        let argument = words.next();
+        if resolved == GOTO_COMMAND {
+            match argument.and_then(|line_number| line_number.parse::<usize>().ok()) {
+                Some(line_number) => self.view.jump_to(Location {
+                    line_idx: line_number.saturating_sub(1),
+                    grapheme_idx: 0,
+                }),
+                None => self.update_message("ERROR: 用法: goto <行号>"),
+            }
+        } else if argument.is_some() {
+            self.update_message(&format!("ERROR: 命令 {resolved} 不接受参数。"));
+        } else if let Some(command) = command::System::from_name(resolved) {
+            self.process_command(System(command));
+        }
+    }
+
+    // 在命令面板中列出与输入匹配的命令名称，放不下时只列出前几个
+    fn update_command_hint(&mut self) {
+        let candidates = palette_candidates(&self.command_bar.value());
+        let total = candidates.len();
+        let max_width = self.terminal_size.width / 2;
+        let mut shown = candidates.as_slice();
+        let mut hint = if total == 0 {
+            String::from("没有匹配的命令")
+        } else {
+            shown.join(" ")
+        };
+        while UnicodeWidthStr::width(hint.as_str()) > max_width && shown.len() > 1 {
+            shown = &shown[..shown.len().saturating_sub(1)];
+            hint = format!("{} …（共 {total} 个）", shown.join(" "));
+        }
+        self.command_bar.set_hint(&hint);
+    }
+
+    // 处理导出选区提示下的命令
+    fn process_command_during_export_selection(&mut self, command: Command) {
+        match command {
+            System(Dismiss) => {
+                self.set_prompt(PromptType::None);
+                self.update_message("导出已取消。");
+            }
+            Edit(InsertNewline) => {
+                let file_name = self.command_bar.value();
+                self.set_prompt(PromptType::None);
+                match self.view.export_selection(&file_name) {
+                    Ok(line_count) => {
+                        let path = fs::canonicalize(&file_name)
+                            .unwrap_or_else(|_| PathBuf::from(&file_name));
+                        self.update_message(&format!(
+                            "已将选区的 {line_count} 行导出到 {}",
+                            path.display()
+                        ));
+                    }
+                    Err(err) => self.update_message(&format!("ERROR: 文件写入失败: {err}")),
+                }
+            }
+            Edit(edit_command) => self.command_bar.handle_edit_command(edit_command),
+            System(_)
+            | Move(_)
+            | BlockSelect(_)
+            | Select(_)
+            | MultiCursor(_) => {} // 导出过程中不适用，调整大小已经在此阶段处理
+        }
+    }
+
     fn save(&mut self, file_name: Option<&str>) {
         let result = if let Some(name) = file_name {
             self.view.save_as(name)
         } else {
             self.view.save()
         };
-        if result.is_ok() {
-            self.update_message("文件保存成功！");
-        } else {
-            self.update_message("文件写入失败！");
+        match result {
+            Ok(()) if file_name.is_some() => {
+                // 另存为后文件名已改变，立即刷新状态栏和窗口标题，并显示实际写入的完整路径
+                self.refresh_status();
+                let path = self
+                    .view
+                    .get_file_path()
+                    .map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+                    .unwrap_or_default();
+                log::info!("已另存为 {}", path.display());
+                self.update_message(&format!("已另存为 {}", path.display()));
+            }
+            Ok(()) => {
+                if let Some(path) = self.view.get_file_path() {
+                    log::info!("已保存 {}", path.display());
+                }
+                self.update_message("文件保存成功！");
+            }
+            // 原文件不可写时直接进入另存为提示
+            Err(err) if err.kind() == ErrorKind::PermissionDenied && file_name.is_none() => {
+                self.update_message(&format!("ERROR: 文件写入失败: {err}"));
+                self.set_prompt(PromptType::Save);
+                self.command_bar
+                    .set_prompt("没有写入权限，另存为（Esc 取消）: ");
+            }
+            Err(err) => self.update_message(&format!("ERROR: 文件写入失败: {err}")),
         }
     }
 
@@ -298,14 +1350,99 @@ impl Editor {
                 let query = self.command_bar.value();
                 self.view.search(&query);
             }
-            Move(Right | Down) => self.view.search_next(),
-            Move(Up | Left) => self.view.search_prev(),
-            System(Quit | Resize(_) | Search | Save) | Move(_) => {} // 保存过程中不适用，调整大小已经在此阶段处理
+            System(FindAll) => {
+                let query = self.command_bar.value();
+                self.set_prompt(PromptType::None);
+                self.view.exit_search();
+                self.show_match_list(&query);
+            }
+            // 箭头键明确指定方向并记住它；F3 / Shift-F3 沿记住的方向或其反方向继续。
+            // 配置了 search_next_key / search_prev_key 时由它们代替对应的方向键
+            Move(Right | Down) if self.config.search_next_key.is_none() => self.view.search_next(),
+            Move(Up | Left) if self.config.search_prev_key.is_none() => self.view.search_prev(),
+            System(SearchNext) => self.view.search_again(),
+            System(SearchPrev) => self.view.search_again_reversed(),
+            System(_)
+            | Move(_)
+            | BlockSelect(_)
+            | Select(_)
+            | MultiCursor(_) => {} // 搜索过程中不适用，调整大小已经在此阶段处理
+        }
+        if self.prompt_type == PromptType::Search {
+            self.update_search_count();
+        }
+    }
+
+    // 在搜索提示的右侧显示当前是第几个匹配以及匹配总数
+    fn update_search_count(&mut self) {
+        let hint = match self.view.search_match_count() {
+            None => String::new(),
+            Some((0, _)) => "没有匹配".to_string(),
+            Some((total, Some(ordinal))) if self.view.search_wrapped() => {
+                format!("已绕回 第 {ordinal}/{total} 处匹配")
+            }
+            Some((total, Some(ordinal))) => format!("第 {ordinal}/{total} 处匹配"),
+            Some((total, None)) => format!("共 {total} 处匹配"),
+        };
+        self.command_bar.set_hint(&hint);
+    }
+
+    // 列出查询的全部匹配项
+    fn show_match_list(&mut self, query: &str) {
+        self.match_list.set_entries(self.view.find_all(query));
+        if self.match_list.is_empty() {
+            self.update_message("没有找到匹配项。");
+            return;
         }
+        self.set_prompt(PromptType::MatchList);
+        self.update_message(&format!(
+            "共 {} 处匹配（上下键选择，Enter 跳转，Esc 关闭）",
+            self.match_list.len()
+        ));
+    }
+
+    // 处理匹配列表模式下的命令
+    fn process_command_during_match_list(&mut self, command: Command) {
+        match command {
+            Move(Up) => self.match_list.select_prev(),
+            Move(Down) => self.match_list.select_next(),
+            Edit(InsertNewline) => {
+                if let Some(location) = self.match_list.selected_location() {
+                    self.view.jump_to(location);
+                }
+                self.close_match_list();
+            }
+            System(Dismiss) => self.close_match_list(),
+            System(_)
+            | Move(_)
+            | BlockSelect(_)
+            | Select(_)
+            | MultiCursor(_)
+            | Edit(_) => {}
+        }
+    }
+
+    fn close_match_list(&mut self) {
+        self.set_prompt(PromptType::None);
+        self.view.set_needs_redraw(true); // 清除覆盖层
+    }
+
+    // 匹配列表覆盖层位于视图底部
+    fn match_list_origin(&self) -> RowIdx {
+        self.terminal_size
+            .height
+            .saturating_sub(self.bar_rows())
+            .saturating_sub(self.match_list.visible_rows())
     }
 
     // 更新消息栏
     fn update_message(&mut self, new_message: &str) {
+        // 错误和警告提示同时记入日志
+        if new_message.starts_with("ERROR") {
+            log::error!("{new_message}");
+        } else if new_message.starts_with("WARNING") {
+            log::warn!("{new_message}");
+        }
         self.message_bar.update_message(new_message);
     }
 
@@ -316,25 +1453,117 @@ impl Editor {
 
     // 设置提示模式
     fn set_prompt(&mut self, prompt_type: PromptType) {
+        self.command_bar.clear_value();
         match prompt_type {
-            PromptType::None => self.message_bar.set_needs_redraw(true), // 确保消息栏在下一个重绘周期中正确绘制
+            PromptType::None | PromptType::MatchList => self.message_bar.set_needs_redraw(true), // 确保消息栏在下一个重绘周期中正确绘制
             PromptType::Save => self.command_bar.set_prompt("保存为（Esc 取消）: "),
+            PromptType::ExportSelection => self.command_bar.set_prompt("导出选区到（Esc 取消）: "),
+            PromptType::SurroundSelection => self
+                .command_bar
+                .set_prompt("用哪对字符包围选区？（如 ( [ { \" '，Esc 取消）"),
+            PromptType::SetFileType => self
+                .command_bar
+                .set_prompt("文件类型（如 text、rust，Esc 取消）: "),
+            PromptType::Command => {
+                self.command_bar.set_prompt("命令（Tab 补全，Esc 取消）: ");
+                self.update_command_hint();
+            }
+            PromptType::ConfirmQuit if self.write_to_stdout => self
+                .command_bar
+                .set_prompt("有未保存的更改，是否保存？（y 保存 / n 不保存 / o 输出 / Esc 取消）"),
+            PromptType::ConfirmQuit => self
+                .command_bar
+                .set_prompt("有未保存的更改，是否保存？（y 保存 / n 不保存 / Esc 取消）"),
+            PromptType::ConfirmNewBuffer => self
+                .command_bar
+                .set_prompt("有未保存的更改，是否放弃并新建空白文档？（y 放弃 / n 或 Esc 取消）"),
+            PromptType::Replace => match &self.replace_step {
+                ReplaceStep::Query => {
+                    if let Some(query) = self.view.enter_search() {
+                        self.command_bar.set_value(&query);
+                    }
+                    self.command_bar.set_prompt("替换：查找（Esc 取消）: ");
+                }
+                ReplaceStep::Replacement => self.command_bar.set_prompt("替换为（Esc 取消）: "),
+                ReplaceStep::Confirm { .. } => self
+                    .command_bar
+                    .set_prompt("替换此处？（y 替换 / n 跳过 / a 全部替换 / Esc 结束）"),
+            },
             PromptType::Search => {
-                self.view.enter_search();
-                self.command_bar
-                    .set_prompt("搜索（Esc 取消，箭头切换搜索结果）: ");
+                if let Some(query) = self.view.enter_search() {
+                    self.command_bar.set_value(&query);
+                }
+                match (self.config.search_next_key, self.config.search_prev_key) {
+                    (None, None) => self
+                        .command_bar
+                        .set_prompt("搜索（Esc 取消，箭头切换搜索结果）: "),
+                    (next, prev) => {
+                        let next = next.map_or_else(|| String::from("→/↓"), |key| key.to_string());
+                        let prev = prev.map_or_else(|| String::from("←/↑"), |key| key.to_string());
+                        self.command_bar
+                            .set_prompt(&format!("搜索（Esc 取消，{next} / {prev} 切换搜索结果）: "));
+                    }
+                }
+                self.update_search_count(); // 以选中的文本开始搜索时立即显示匹配数
             }
         }
-        self.command_bar.clear_value();
+        let was_bottom_bar_visible = self.is_bottom_bar_visible();
         self.prompt_type = prompt_type;
+        // 隐藏了消息栏时，提示出现和消失都会改变文档区域的高度
+        if self.is_bottom_bar_visible() != was_bottom_bar_visible {
+            self.layout();
+        }
     }
 }
 
 impl Drop for Editor {
     fn drop(&mut self) {
+        log::info!("退出");
         let _ = Terminal::terminate();
         if self.should_quit {
             let _ = Terminal::print("欢迎下次使用。\r\n");
+            let _ = Terminal::execute();
+            // 只有确认输出或没有未保存的更改时才写入，强制放弃更改的退出不输出任何内容
+            if self.write_to_stdout
+                && (self.output_confirmed || !self.view.get_status().is_modified)
+            {
+                let _ = self.view.write_to(&mut stdout().lock());
+            }
         }
     }
 }
+
+// 命令面板中可用的命令名称：与输入的第一个词前缀匹配的系统命令，以及跳转到指定行的 goto
+fn palette_candidates(input: &str) -> Vec<&'static str> {
+    let name = input.split_whitespace().next().unwrap_or_default();
+    let mut candidates = command::System::names_with_prefix(name);
+    if GOTO_COMMAND.starts_with(&name.to_ascii_lowercase()) {
+        candidates.push(GOTO_COMMAND);
+    }
+    candidates
+}
+
+// 各名称的最长公共前缀，用于 Tab 补全
+fn common_prefix(names: &[&str]) -> String {
+    let first = names.first().copied().unwrap_or_default();
+    let len = names.iter().fold(first.len(), |len, name| {
+        first
+            .bytes()
+            .zip(name.bytes())
+            .take(len)
+            .take_while(|(left, right)| left == right)
+            .count()
+    });
+    first[..len].to_string()
+}
+
+// 包围选区用的一对字符：输入开括号或闭括号都得到完整的一对，其他字符两侧相同
+const fn surround_pair(character: char) -> (char, char) {
+    match character {
+        '(' | ')' => ('(', ')'),
+        '[' | ']' => ('[', ']'),
+        '{' | '}' => ('{', '}'),
+        '<' | '>' => ('<', '>'),
+        _ => (character, character),
+    }
+}
@@ -7,6 +7,154 @@ pub struct Attribute {
     pub background: Option<Color>,
 }
 
+impl Attribute {
+    // 浅色背景终端使用的配色：前景色更深，背景色更浅，保证在白底上可读
+    pub const fn for_light_background(annotation_type: AnnotationType) -> Self {
+        match annotation_type {
+            AnnotationType::Match => Self {
+                foreground: Some(Color::Rgb { r: 0, g: 0, b: 0 }),
+                background: Some(Color::Rgb {
+                    r: 200,
+                    g: 200,
+                    b: 200,
+                }),
+            },
+            AnnotationType::SelectedMatch => Self {
+                foreground: Some(Color::Rgb { r: 0, g: 0, b: 0 }),
+                background: Some(Color::Rgb {
+                    r: 255,
+                    g: 215,
+                    b: 0,
+                }),
+            },
+            AnnotationType::Number => Self {
+                foreground: Some(Color::Rgb {
+                    r: 196,
+                    g: 26,
+                    b: 22,
+                }),
+                background: None,
+            },
+            AnnotationType::Keyword => Self {
+                foreground: Some(Color::Rgb {
+                    r: 0,
+                    g: 70,
+                    b: 190,
+                }),
+                background: None,
+            },
+            AnnotationType::Type => Self {
+                foreground: Some(Color::Rgb {
+                    r: 0,
+                    g: 120,
+                    b: 60,
+                }),
+                background: None,
+            },
+            AnnotationType::KnownValue => Self {
+                foreground: Some(Color::Rgb {
+                    r: 110,
+                    g: 60,
+                    b: 170,
+                }),
+                background: None,
+            },
+            AnnotationType::Char => Self {
+                foreground: Some(Color::Rgb {
+                    r: 170,
+                    g: 100,
+                    b: 0,
+                }),
+                background: None,
+            },
+            AnnotationType::LifetimeSpecifier => Self {
+                foreground: Some(Color::Rgb {
+                    r: 0,
+                    g: 128,
+                    b: 128,
+                }),
+                background: None,
+            },
+            AnnotationType::Comment => Self {
+                foreground: Some(Color::Rgb {
+                    r: 60,
+                    g: 120,
+                    b: 60,
+                }),
+                background: None,
+            },
+            AnnotationType::String => Self {
+                foreground: Some(Color::Rgb {
+                    r: 180,
+                    g: 90,
+                    b: 0,
+                }),
+                background: None,
+            },
+            AnnotationType::Dim => Self {
+                foreground: Some(Color::Rgb {
+                    r: 150,
+                    g: 150,
+                    b: 150,
+                }),
+                background: None,
+            },
+            AnnotationType::Selection => Self {
+                foreground: None,
+                background: Some(Color::Rgb {
+                    r: 180,
+                    g: 205,
+                    b: 250,
+                }),
+            },
+            AnnotationType::Misspelled => Self {
+                foreground: Some(Color::Rgb { r: 200, g: 0, b: 0 }),
+                background: None,
+            },
+            AnnotationType::Key => Self {
+                foreground: Some(Color::Rgb {
+                    r: 0,
+                    g: 90,
+                    b: 140,
+                }),
+                background: None,
+            },
+            AnnotationType::Punctuation => Self {
+                foreground: Some(Color::Rgb {
+                    r: 110,
+                    g: 110,
+                    b: 110,
+                }),
+                background: None,
+            },
+            AnnotationType::Error => Self {
+                foreground: Some(Color::Rgb { r: 200, g: 0, b: 0 }),
+                background: None,
+            },
+            AnnotationType::SecondaryCaret => Self {
+                foreground: Some(Color::Rgb {
+                    r: 255,
+                    g: 255,
+                    b: 255,
+                }),
+                background: Some(Color::Rgb {
+                    r: 80,
+                    g: 80,
+                    b: 80,
+                }),
+            },
+            AnnotationType::MatchingBracket => Self {
+                foreground: None,
+                background: Some(Color::Rgb {
+                    r: 250,
+                    g: 220,
+                    b: 150,
+                }),
+            },
+        }
+    }
+}
+
 impl From<AnnotationType> for Attribute {
     fn from(annotation_type: AnnotationType) -> Self {
         match annotation_type {
@@ -97,7 +245,71 @@ impl From<AnnotationType> for Attribute {
                     b: 102,
                 }),
                 background: None,
-            }, 
+            },
+            AnnotationType::Dim => Self {
+                foreground: Some(Color::Rgb {
+                    r: 128,
+                    g: 128,
+                    b: 128,
+                }),
+                background: None,
+            },
+            AnnotationType::Selection => Self {
+                foreground: None,
+                background: Some(Color::Rgb {
+                    r: 68,
+                    g: 90,
+                    b: 140,
+                }),
+            },
+            AnnotationType::Misspelled => Self {
+                foreground: Some(Color::Rgb {
+                    r: 255,
+                    g: 110,
+                    b: 110,
+                }),
+                background: None,
+            },
+            AnnotationType::Key => Self {
+                foreground: Some(Color::Rgb {
+                    r: 156,
+                    g: 220,
+                    b: 254,
+                }),
+                background: None,
+            },
+            AnnotationType::Punctuation => Self {
+                foreground: Some(Color::Rgb {
+                    r: 180,
+                    g: 180,
+                    b: 180,
+                }),
+                background: None,
+            },
+            AnnotationType::Error => Self {
+                foreground: Some(Color::Rgb {
+                    r: 255,
+                    g: 110,
+                    b: 110,
+                }),
+                background: None,
+            },
+            AnnotationType::SecondaryCaret => Self {
+                foreground: Some(Color::Rgb { r: 0, g: 0, b: 0 }),
+                background: Some(Color::Rgb {
+                    r: 200,
+                    g: 200,
+                    b: 200,
+                }),
+            },
+            AnnotationType::MatchingBracket => Self {
+                foreground: None,
+                background: Some(Color::Rgb {
+                    r: 100,
+                    g: 85,
+                    b: 40,
+                }),
+            },
         }
     }
 }
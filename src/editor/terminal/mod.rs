@@ -1,8 +1,9 @@
 use crossterm::{
-    cursor::{Hide, MoveTo, Show},
+    cursor::{Hide, MoveTo, SetCursorStyle, Show},
+    event::{DisableBracketedPaste, EnableBracketedPaste},
     style::{
-        Attribute::{Reset, Reverse},
-        Print, ResetColor, SetBackgroundColor, SetForegroundColor,
+        Attribute::{Reset, Reverse, Underlined},
+        Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
     },
     terminal::{
         disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap,
@@ -10,9 +11,17 @@ use crossterm::{
     },
     queue, Command,
 };
-use std::io::{stdout, Error, Write};
+use std::{
+    fs::{File, OpenOptions},
+    io::{stdout, BufWriter, Error, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
 use crate::prelude::*;
-use crate::editor::AnnotatedString;
+use crate::editor::{AnnotatedString, AnnotationType};
 
 mod attribute;
 use attribute::Attribute;
@@ -25,19 +34,57 @@ use attribute::Attribute;
 /// 如果尝试将插入符号设置为超出这些范围，它也将被截断。
 pub struct Terminal;
 
+// 是否输出颜色，启动时确定一次
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+
+// 终端是否为浅色背景，决定使用哪一套配色，启动时确定一次
+static LIGHT_BACKGROUND: AtomicBool = AtomicBool::new(false);
+
+// 视觉铃声反转屏幕的持续时间
+pub const FLASH_DURATION: Duration = Duration::from_millis(80);
+
+// 标准输出不是终端时（例如被重定向到管道），界面改为输出到 /dev/tty
+static TTY_OUTPUT: Mutex<Option<BufWriter<File>>> = Mutex::new(None);
+
 impl Terminal {
+    pub fn set_color_enabled(enabled: bool) {
+        COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+    }
+
+    fn is_color_enabled() -> bool {
+        COLOR_ENABLED.load(Ordering::Relaxed)
+    }
+
+    pub fn set_light_background(light: bool) {
+        LIGHT_BACKGROUND.store(light, Ordering::Relaxed);
+    }
+
+    fn is_light_background() -> bool {
+        LIGHT_BACKGROUND.load(Ordering::Relaxed)
+    }
+
     pub fn terminate() -> Result<(), Error> {
+        Self::end_flash()?; // 反转屏幕期间退出时也要恢复
         Self::leave_alternate_screen()?;
+        Self::disable_bracketed_paste()?;
         Self::enable_line_wrap()?;
         Self::show_caret()?;
+        Self::set_cursor_style(SetCursorStyle::DefaultUserShape)?;
         Self::execute()?;
         disable_raw_mode()?;
         Ok(())
     }
     
     pub fn initialize() -> Result<(), Error> {
+        if !stdout().is_terminal() {
+            let tty = OpenOptions::new().write(true).open("/dev/tty")?;
+            if let Ok(mut output) = TTY_OUTPUT.lock() {
+                *output = Some(BufWriter::new(tty));
+            }
+        }
         enable_raw_mode()?;
         Self::enter_alternate_screen()?;
+        Self::enable_bracketed_paste()?;
         Self::disable_line_wrap()?;
         Self::clear_screen()?;
         Self::execute()?;
@@ -74,6 +121,17 @@ impl Terminal {
         Ok(())
     }
 
+    // 启用后粘贴的文本作为一个整体事件送达，而不是逐个按键
+    pub fn enable_bracketed_paste() -> Result<(), Error> {
+        Self::queue_command(EnableBracketedPaste)?;
+        Ok(())
+    }
+
+    pub fn disable_bracketed_paste() -> Result<(), Error> {
+        Self::queue_command(DisableBracketedPaste)?;
+        Ok(())
+    }
+
     pub fn hide_caret() -> Result<(), Error> {
         Self::queue_command(Hide)?;
         Ok(())
@@ -94,6 +152,11 @@ impl Terminal {
         Ok(())
     }
 
+    pub fn set_cursor_style(style: SetCursorStyle) -> Result<(), Error> {
+        Self::queue_command(style)?;
+        Ok(())
+    }
+
     pub fn set_title(title: &str) -> Result<(), Error> {
         Self::queue_command(SetTitle(title))?;
         Ok(())
@@ -104,6 +167,18 @@ impl Terminal {
         Ok(())
     }
 
+    // 视觉铃声：用 DECSCNM 反转整个屏幕。不在此等待，
+    // 由调用者在 FLASH_DURATION 之后调用 end_flash 恢复，期间仍可接收输入
+    pub fn start_flash() -> Result<(), Error> {
+        Self::print("\x1b[?5h")?;
+        Self::execute()
+    }
+
+    pub fn end_flash() -> Result<(), Error> {
+        Self::print("\x1b[?5l")?;
+        Self::execute()
+    }
+
     pub fn print_row(row: RowIdx, line_text: &str) -> Result<(), Error> {
         Self::move_caret_to(Position { row, col: 0 })?;
         Self::clear_line()?;
@@ -121,8 +196,19 @@ impl Terminal {
             .into_iter()
             .try_for_each(|part| -> Result<(), Error> {
                 if let Some(annotation_type) = part.annotation_type {
-                    let attribute: Attribute = annotation_type.into();
-                    Self::set_attribute(&attribute)?;
+                    if Self::is_underlined(annotation_type) {
+                        Self::queue_command(SetAttribute(Underlined))?;
+                    }
+                    if Self::is_color_enabled() {
+                        let attribute = if Self::is_light_background() {
+                            Attribute::for_light_background(annotation_type)
+                        } else {
+                            annotation_type.into()
+                        };
+                        Self::set_attribute(&attribute)?;
+                    } else if Self::is_plain_highlighted(annotation_type) {
+                        Self::queue_command(SetAttribute(Reverse))?;
+                    }
                 }
 
                 Self::print(part.string)?;
@@ -142,8 +228,25 @@ impl Terminal {
         Ok(())
     }
 
+    // 无颜色模式下仅用反色突出显示搜索结果、选区和次光标，其余注解均显示为纯文本
+    const fn is_plain_highlighted(annotation_type: AnnotationType) -> bool {
+        matches!(
+            annotation_type,
+            AnnotationType::Match
+                | AnnotationType::SelectedMatch
+                | AnnotationType::Selection
+                | AnnotationType::SecondaryCaret
+        )
+    }
+
+    // 拼写错误和语法错误无论是否启用颜色都以下划线显示
+    const fn is_underlined(annotation_type: AnnotationType) -> bool {
+        matches!(annotation_type, AnnotationType::Misspelled | AnnotationType::Error)
+    }
+
     fn reset_color() -> Result<(), Error> {
         Self::queue_command(ResetColor)?;
+        Self::queue_command(SetAttribute(Reset))?;
         Ok(())
     }
 
@@ -162,12 +265,23 @@ impl Terminal {
         Ok(Size { height, width })
     }
     pub fn execute() -> Result<(), Error> {
-        stdout().flush()?;
-        Ok(())
+        Self::with_output(|output| output.flush())
     }
 
     fn queue_command<T: Command>(command: T) -> Result<(), Error> {
-        queue!(stdout(), command)?;
-        Ok(())
+        Self::with_output(|mut output| queue!(&mut output, command))
+    }
+
+    fn with_output<F>(write: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+    {
+        let mut tty_output = TTY_OUTPUT
+            .lock()
+            .map_err(|_| Error::other("terminal output lock poisoned"))?;
+        match tty_output.as_mut() {
+            Some(tty) => write(tty),
+            None => write(&mut stdout()),
+        }
     }
 }
\ No newline at end of file
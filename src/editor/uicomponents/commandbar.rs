@@ -10,6 +10,7 @@ use super::UIComponent;
 pub struct CommandBar {
     prompt: String,
     value: Line,
+    hint: String, // 右对齐显示在输入值之后的附加信息，例如搜索的匹配数，空间不足时省略
     needs_redraw: bool,
     size: Size,
 }
@@ -18,21 +19,32 @@ impl CommandBar {
     pub fn handle_edit_command(&mut self, command: Edit) {
         match command {
             Edit::Insert(character) => self.value.append_char(character),
-            Edit::Delete | Edit::InsertNewline => {}
+            Edit::Delete | Edit::InsertNewline | Edit::Dedent => {}
+            // 输入总在末尾进行，交换最后两个字素
+            Edit::Transpose => {
+                let count = self.value.grapheme_count();
+                self.value.transpose(count.saturating_sub(1));
+            }
             Edit::DeleteBackward => self.value.delete_last(),
+            Edit::DeleteWordBackward => {
+                let end = self.value.grapheme_count();
+                let start = self.value.previous_word_start(end);
+                self.value.delete_range(start..end);
+            }
         }
         self.set_needs_redraw(true);
     }
 
     pub fn caret_position_col(&self) -> ColIdx {
         let prompt_width = UnicodeWidthStr::width(self.prompt.as_str());
-        let value_width = UnicodeWidthStr::width(self.value.to_string().as_str());
-        
+        // 与绘制时一致，按展开制表符之后的宽度计算
+        let value_width = self.value.width();
+
         // 计算提示符和输入值的实际显示宽度
         let max_width = prompt_width + value_width;
         
-        // 限制光标位置在可显示宽度范围内
-        min(max_width, self.size.width)
+        // 限制光标位置在可显示宽度范围内，最右一列之后已经没有位置
+        min(max_width, self.size.width.saturating_sub(1))
     }
 
     pub fn value(&self) -> String {
@@ -41,6 +53,19 @@ impl CommandBar {
 
     pub fn set_prompt(&mut self, prompt: &str) {
         self.prompt = prompt.to_string();
+        self.hint.clear();
+        self.set_needs_redraw(true);
+    }
+
+    pub fn set_hint(&mut self, hint: &str) {
+        if self.hint != hint {
+            self.hint = hint.to_string();
+            self.set_needs_redraw(true);
+        }
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value = Line::from(value);
         self.set_needs_redraw(true);
     }
 
@@ -61,20 +86,67 @@ impl UIComponent for CommandBar {
         self.size = size;
     }
     fn draw(&mut self, origin: RowIdx) -> Result<(), Error> {
-        let area_for_value = self.size.width.saturating_sub(self.prompt.len()); 
+        // 按显示宽度计算，并在末尾为光标留出一列
+        let prompt_width = UnicodeWidthStr::width(self.prompt.as_str());
+        let area_for_value = self
+            .size
+            .width
+            .saturating_sub(prompt_width)
+            .saturating_sub(1);
         let value_end = self.value.width(); 
         let value_start = value_end.saturating_sub(area_for_value); 
     
         let visible_value = self.value.get_visible_graphemes(value_start..value_end);
     
         let message = format!("{}{}", self.prompt, visible_value);
-        let to_print = if message.len() <= self.size.width {
+        let mut to_print = if message.len() <= self.size.width {
             message
         } else {
             // 如果提示符和值的组合长度超过了可显示区域的宽度，只显示提示符
             format!("{}{}", self.prompt, &visible_value)
         };
+        // 附加信息与输入值（及其后的光标）之间至少隔一列
+        let used_width = UnicodeWidthStr::width(to_print.as_str()).saturating_add(2);
+        let hint_width = UnicodeWidthStr::width(self.hint.as_str());
+        if !self.hint.is_empty() && used_width.saturating_add(hint_width) <= self.size.width {
+            let padding = self
+                .size
+                .width
+                .saturating_sub(used_width)
+                .saturating_sub(hint_width);
+            to_print = format!("{to_print}  {}{}", " ".repeat(padding), self.hint);
+        }
     
         Terminal::print_row(origin, &to_print)
     }    
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandBar, UIComponent};
+    use crate::prelude::*;
+
+    #[test]
+    fn caret_stays_inside_last_column() {
+        let mut command_bar = CommandBar::default();
+        command_bar.resize(Size {
+            height: 1,
+            width: 10,
+        });
+        command_bar.set_prompt("查找: ");
+        assert_eq!(command_bar.caret_position_col(), 6);
+        command_bar.set_value("ab");
+        assert_eq!(command_bar.caret_position_col(), 8);
+        // 输入值超出宽度时光标停在最右一列
+        command_bar.set_value("abcdefgh");
+        assert_eq!(command_bar.caret_position_col(), 9);
+        // 制表符按展开后的宽度计算
+        command_bar.set_value("\t");
+        assert_eq!(command_bar.caret_position_col(), 9);
+        command_bar.resize(Size {
+            height: 1,
+            width: 20,
+        });
+        assert_eq!(command_bar.caret_position_col(), 10);
+    }
+}
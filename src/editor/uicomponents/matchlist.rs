@@ -0,0 +1,106 @@
+use std::io::Error;
+
+use crate::prelude::*;
+use super::super::Terminal;
+use super::UIComponent;
+
+// 匹配列表：以覆盖层的形式列出查询的所有匹配项，选中条目后可跳转到对应位置
+#[derive(Default)]
+pub struct MatchList {
+    entries: Vec<(Location, String)>,
+    selected_idx: usize,
+    scroll_offset: usize,
+    needs_redraw: bool,
+    size: Size,
+}
+
+impl MatchList {
+    pub fn set_entries(&mut self, entries: Vec<(Location, String)>) {
+        self.entries = entries;
+        self.selected_idx = 0;
+        self.scroll_offset = 0;
+        self.set_needs_redraw(true);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // 覆盖层实际占用的行数
+    pub fn visible_rows(&self) -> usize {
+        self.entries.len().min(self.size.height)
+    }
+
+    pub fn selected_location(&self) -> Option<Location> {
+        self.entries.get(self.selected_idx).map(|(location, _)| *location)
+    }
+
+    // 选中条目相对于覆盖层顶部的行
+    pub fn selected_row(&self) -> RowIdx {
+        self.selected_idx.saturating_sub(self.scroll_offset)
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected_idx.saturating_add(1) < self.entries.len() {
+            self.selected_idx += 1;
+            self.scroll_selected_into_view();
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        if self.selected_idx > 0 {
+            self.selected_idx -= 1;
+            self.scroll_selected_into_view();
+        }
+    }
+
+    fn scroll_selected_into_view(&mut self) {
+        let height = self.visible_rows();
+        if self.selected_idx < self.scroll_offset {
+            self.scroll_offset = self.selected_idx;
+        } else if self.selected_idx >= self.scroll_offset.saturating_add(height) {
+            self.scroll_offset = self.selected_idx.saturating_sub(height).saturating_add(1);
+        }
+        self.set_needs_redraw(true);
+    }
+}
+
+impl UIComponent for MatchList {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+        self.scroll_selected_into_view();
+    }
+
+    fn draw(&mut self, origin_row: RowIdx) -> Result<(), Error> {
+        for row in 0..self.visible_rows() {
+            let entry_idx = self.scroll_offset.saturating_add(row);
+            let Some((location, preview)) = self.entries.get(entry_idx) else {
+                break;
+            };
+            let label = format!(
+                "{:>5}:{:<4} {preview}",
+                location.line_idx.saturating_add(1),
+                location.grapheme_idx.saturating_add(1)
+            );
+            let at = origin_row.saturating_add(row);
+            if entry_idx == self.selected_idx {
+                Terminal::print_inverted_row(at, &label)?;
+            } else {
+                Terminal::print_row(at, &label)?;
+            }
+        }
+        Ok(())
+    }
+}
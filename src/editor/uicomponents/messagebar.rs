@@ -44,6 +44,15 @@ impl MessageBar {
         self.cleared_after_expiry = false;
         self.set_needs_redraw(true);
     }
+
+    // 清除普通的提示消息；以 ERROR 或 WARNING 开头的消息保留到过期为止
+    pub fn clear_transient_message(&mut self) {
+        let text = &self.current_message.text;
+        if text.is_empty() || text.starts_with("ERROR") || text.starts_with("WARNING") {
+            return;
+        }
+        self.update_message("");
+    }
 }
 
 impl UIComponent for MessageBar {
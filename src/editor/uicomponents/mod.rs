@@ -1,9 +1,17 @@
 mod view;
-pub use view::View;
+pub use view::{
+    file_type_for_name, register_builtin_highlighters, register_grammar_files,
+    registered_file_types, Buffer, View,
+};
+#[cfg(feature = "spellcheck")]
+pub use view::load_dictionary;
 
 mod commandbar;
 pub use commandbar::CommandBar;
 
+mod matchlist;
+pub use matchlist::MatchList;
+
 mod messagebar;
 pub use messagebar::MessageBar;
 
@@ -2,6 +2,12 @@ use std::io::Error;
 use crate::prelude::*;
 use super::super::{DocumentStatus, Terminal};
 use super::UIComponent;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const ELLIPSIS: &str = "…";
+// 省略后的文件名至少保留的宽度，再窄就不显示文件名
+const MIN_FILE_NAME_WIDTH: usize = 5;
 
 #[derive(Default)]
 pub struct StatusBar {
@@ -17,6 +23,48 @@ impl StatusBar {
             self.set_needs_redraw(true);
         }
     }
+
+    // 按当前宽度排版状态栏的内容，宽度不超过状态栏
+    fn status_text(&self) -> String {
+        let width = self.size.width;
+        // 组装状态栏的第一部分（文件名之后的内容）
+        let line_count = self.current_status.line_count_to_string();
+        let indicators = [
+            self.current_status.modified_indicator_to_string(),
+            self.current_status.read_only_indicator_to_string(),
+            self.current_status.caret_count_indicator_to_string(),
+        ]
+        .into_iter()
+        .filter(|indicator| !indicator.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+        let after_name = format!(" - {line_count} {indicators}");
+
+        // 组装后半部分
+        let position_indicator = self.current_status.position_indicator_to_string();
+        let file_type = self.current_status.file_type_to_string();
+        let back_part = format!("{file_type} | {position_indicator}");
+        let back_width = back_part.width();
+
+        // 文件名是最可舍弃的部分：宽度不足时先省略文件名中间，保证行数和位置可见；
+        // 连省略后的文件名都放不下时只显示后半部分，仍放不下则写出空字符串以确保清除行。
+        let name_budget = width
+            .saturating_sub(after_name.width())
+            .saturating_sub(back_width)
+            .saturating_sub(1); // 前后两部分之间至少留一个空格
+        if name_budget >= MIN_FILE_NAME_WIDTH
+            || self.current_status.file_name.width() <= name_budget
+        {
+            let file_name = elide_middle(&self.current_status.file_name, name_budget);
+            let beginning = format!("{file_name}{after_name}");
+            let padding = width.saturating_sub(beginning.width()).saturating_sub(back_width);
+            format!("{beginning}{}{back_part}", " ".repeat(padding))
+        } else if back_width <= width {
+            format!("{back_part:>width$}")
+        } else {
+            String::new()
+        }
+    }
 }
 
 impl UIComponent for StatusBar {
@@ -33,32 +81,89 @@ impl UIComponent for StatusBar {
     }
 
     fn draw(&mut self, origin_row: RowIdx) -> Result<(), Error> {
-        // 组装状态栏的第一部分
-        let line_count = self.current_status.line_count_to_string();
-        let modified_indicator = self.current_status.modified_indicator_to_string();
+        Terminal::print_inverted_row(origin_row, &self.status_text())?;
+
+        Ok(())
+    }
+}
 
-        let beginning = format!(
-            "{} - {line_count} {modified_indicator}",
-            self.current_status.file_name
-        );
+// 将文本缩短到不超过指定显示宽度：保留开头和结尾（扩展名通常在结尾），中间用省略号代替
+fn elide_middle(text: &str, max_width: usize) -> String {
+    if text.width() <= max_width {
+        return text.to_string();
+    }
+    let available = max_width.saturating_sub(ELLIPSIS.width());
+    let tail_budget = available / 2;
+    let head_budget = available.saturating_sub(tail_budget);
 
-        // 组装后半部分
-        let position_indicator = self.current_status.position_indicator_to_string();
-        let file_type = self.current_status.file_type_to_string();
-        let back_part = format!("{file_type} | {position_indicator}");
+    let graphemes: Vec<&str> = text.graphemes(true).collect();
+    let mut head_width = 0;
+    let head_len = graphemes
+        .iter()
+        .take_while(|grapheme| {
+            head_width += grapheme.width();
+            head_width <= head_budget
+        })
+        .count();
+    let mut tail_width = 0;
+    let tail_len = graphemes[head_len..]
+        .iter()
+        .rev()
+        .take_while(|grapheme| {
+            tail_width += grapheme.width();
+            tail_width <= tail_budget
+        })
+        .count();
+    format!(
+        "{}{ELLIPSIS}{}",
+        graphemes[..head_len].concat(),
+        graphemes[graphemes.len().saturating_sub(tail_len)..].concat()
+    )
+}
 
-        // 组装整个状态栏
-        let remainder_len = self.size.width.saturating_sub(beginning.len());
-        let status = format!("{beginning}{back_part:>remainder_len$}");
+#[cfg(test)]
+mod tests {
+    use super::{DocumentStatus, StatusBar, UIComponent};
+    use crate::prelude::*;
+    use unicode_width::UnicodeWidthStr;
 
-        // 仅在状态适合时打印状态。否则写出一个空字符串以确保清除行。
-        let to_print = if status.len() <= self.size.width {
-            status
-        } else {
-            String::new()
-        };
-        Terminal::print_inverted_row(origin_row, &to_print)?;
+    fn status_text(file_name: &str, is_modified: bool, width: usize) -> String {
+        let mut status_bar = StatusBar::default();
+        status_bar.resize(Size { height: 1, width });
+        status_bar.update_status(DocumentStatus {
+            total_lines: 120,
+            current_line_idx: 9,
+            is_modified,
+            caret_count: 1,
+            file_name: file_name.to_string(),
+            ..DocumentStatus::default()
+        });
+        status_bar.status_text()
+    }
 
-        Ok(())
+    #[test]
+    fn fits_80_and_40_columns() {
+        let file_name = "a_rather_long_file_name_for_testing.rs";
+        let wide = status_text(file_name, true, 80);
+        assert_eq!(wide.width(), 80);
+        assert!(wide.starts_with(file_name));
+        assert!(wide.contains("(modified)"));
+        assert!(wide.ends_with("Text | 10/120"));
+        // 文件名中间被省略，行数和位置仍然可见
+        let narrow = status_text(file_name, false, 40);
+        assert_eq!(narrow.width(), 40);
+        assert!(narrow.starts_with("a_rath…ing.rs - 120 lines"));
+        assert!(narrow.ends_with("Text | 10/120"));
+        // 连省略后的文件名都放不下时只显示后半部分
+        let narrow = status_text(file_name, true, 40);
+        assert_eq!(narrow.trim_start(), "Text | 10/120");
+    }
+
+    #[test]
+    fn keeps_short_names_and_clears_when_too_narrow() {
+        let text = status_text("中文.rs", false, 40);
+        assert_eq!(text.width(), 40);
+        assert!(text.starts_with("中文.rs - 120 lines"));
+        assert!(status_text("x", false, 3).is_empty());
     }
 }
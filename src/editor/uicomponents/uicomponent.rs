@@ -15,22 +15,19 @@ pub trait UIComponent {
     // 更新尺寸。需要由每个组件实现。
     fn set_size(&mut self, size: Size);
 
-    // 如果组件可见且需要重绘，则绘制此组件
-    fn render(&mut self, origin_row: RowIdx) {
+    // 如果组件可见且需要重绘，则绘制此组件。
+    // 调试版本中绘制失败直接 panic；发布版本中返回错误交给调用方显示，组件保持待重绘状态
+    fn render(&mut self, origin_row: RowIdx) -> Result<(), Error> {
         if self.needs_redraw() {
-            if let Err(err) = self.draw(origin_row) {
-                #[cfg(debug_assertions)]
-                {
-                    panic!("无法渲染组件: {err:?}");
-                }
-                #[cfg(not(debug_assertions))]
-                {
-                    let _ = err;
-                }
-            } else {
-                self.set_needs_redraw(false);
+            let result = self.draw(origin_row);
+            #[cfg(debug_assertions)]
+            if let Err(err) = &result {
+                panic!("无法渲染组件: {err:?}");
             }
+            result?;
+            self.set_needs_redraw(false);
         }
+        Ok(())
     }
     // 实际绘制组件的方法，必须由每个组件实现
     fn draw(&mut self, origin_row: RowIdx) -> Result<(), Error>;
@@ -1,27 +1,167 @@
-use super::super::super::AnnotatedString;
+use super::super::super::{AnnotatedString, FileType, IndentStyle};
 use super::FileInfo;
 use super::Highlighter;
 use super::Line;
 use crate::prelude::*;
-use std::fs::{read_to_string, File};
+use std::fmt::{self, Display};
+use std::fs::{set_permissions, File};
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::Error;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::ops::Range;
 
-#[derive(Default)]
+const BOM: char = '\u{feff}';
+
 pub struct Buffer {
     lines: Vec<Line>,
     file_info: FileInfo,
     dirty: bool,
+    has_bom: bool, // 文件以 UTF-8 BOM 开头，保存时需要重新写入
+    missing_final_newline: bool, // 文件的最后一行没有换行符，保存时保持原样
+    crlf: bool, // 文件以 CRLF 换行（按第一个换行符判断），保存时保持原样
+    edited_from: Option<LineIdx>, // 上次取走之后被修改的第一行，用于让语法高亮结果失效
+    saved_hashes: Vec<u64>, // 上次加载或保存时各行内容的哈希
+    // 与 lines 一一对应：各行源自 saved_hashes 中的哪一行，新插入的行为 None
+    origins: Vec<Option<LineIdx>>,
+    changes: Vec<Change>, // 上次取走之后的各次修改，供撤销历史使用
+    tab_width: usize, // 所有行共用的制表符宽度，新建的行也按它展开制表符
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self {
+            lines: Vec::new(),
+            file_info: FileInfo::default(),
+            dirty: false,
+            has_bom: false,
+            missing_final_newline: false,
+            crlf: false,
+            edited_from: None,
+            saved_hashes: Vec::new(),
+            origins: Vec::new(),
+            changes: Vec::new(),
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+}
+
+// 一次修改：从 line_idx 开始的 new_count 行原先是 old 中的各行（连同其来源行）。
+// 撤销时换回 old，换下来的行构成反向的修改
+pub struct Change {
+    line_idx: LineIdx,
+    old: Vec<(Line, Option<LineIdx>)>,
+    new_count: usize,
+}
+
+// 一行相对上次加载或保存时的内容的变化
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    RemovedAbove, // 本行未变，但与上一个保留下来的行之间（或文档末尾之后）有行被删除
+}
+
+fn hash_line(line: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish()
 }
 
 impl Buffer {
     pub const fn is_dirty(&self) -> bool {
         self.dirty
     }
+    fn mark_edited(&mut self, line_idx: LineIdx) {
+        self.dirty = true;
+        self.edited_from = Some(self.edited_from.map_or(line_idx, |from| from.min(line_idx)));
+    }
+    // 所有行当前的内容即为已保存的内容
+    fn mark_saved(&mut self) {
+        self.dirty = false;
+        self.saved_hashes = self.lines.iter().map(|line| hash_line(line)).collect();
+        self.origins = (0..self.lines.len()).map(Some).collect();
+    }
+    // 该行源自已保存文件中的哪一行，新插入的行返回 None
+    pub fn saved_line_idx(&self, line_idx: LineIdx) -> Option<LineIdx> {
+        self.origins.get(line_idx).copied().flatten()
+    }
+    // 逐行与上次加载或保存时的内容比较，未变化的行返回 None
+    pub fn line_change(&self, line_idx: LineIdx) -> Option<LineChange> {
+        let line = self.lines.get(line_idx)?;
+        let Some(origin) = self.saved_line_idx(line_idx) else {
+            return Some(LineChange::Added);
+        };
+        if self.saved_hashes.get(origin) != Some(&hash_line(line)) {
+            return Some(LineChange::Modified);
+        }
+        // 保留下来的行按原顺序排列，源行号不连续说明中间的行被删除了
+        let previous_origin = self.origins[..line_idx].iter().rev().find_map(|origin| *origin);
+        let expected_origin = previous_origin.map_or(0, |previous| previous.saturating_add(1));
+        let is_last = line_idx.saturating_add(1) == self.lines.len();
+        let removed_at_end = is_last && origin.saturating_add(1) < self.saved_hashes.len();
+        (origin > expected_origin || removed_at_end).then_some(LineChange::RemovedAbove)
+    }
+    // 该行中是否有与查询字符串匹配的内容
+    pub fn has_match(&self, line_idx: LineIdx, query: &str) -> bool {
+        !query.is_empty()
+            && self
+                .lines
+                .get(line_idx)
+                .is_some_and(|line| line.search_forward(query, 0).is_some())
+    }
+    // 在修改之前调用：记录从 line_idx 开始的 old_count 行将被替换为 new_count 行
+    fn record(&mut self, line_idx: LineIdx, old_count: usize, new_count: usize) {
+        let end = line_idx.saturating_add(old_count).min(self.lines.len());
+        let start = line_idx.min(end);
+        let old = self.lines[start..end]
+            .iter()
+            .cloned()
+            .zip(self.origins[start..end].iter().copied())
+            .collect();
+        self.changes.push(Change {
+            line_idx,
+            old,
+            new_count,
+        });
+    }
+    // 返回并清除自上次调用以来的各次修改，按发生的顺序排列
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
+    // 撤销一次修改，返回能够重做它的反向修改。撤销本身不会被记录
+    pub fn revert(&mut self, change: Change) -> Change {
+        let Change {
+            line_idx,
+            old,
+            new_count,
+        } = change;
+        let start = line_idx.min(self.lines.len());
+        let end = start.saturating_add(new_count).min(self.lines.len());
+        let old_count = old.len();
+        let (mut lines, origins): (Vec<Line>, Vec<Option<LineIdx>>) = old.into_iter().unzip();
+        // 记录这次修改之后制表符宽度可能已改变
+        for line in &mut lines {
+            line.set_tab_width(self.tab_width);
+        }
+        let removed_lines = self.lines.splice(start..end, lines).collect::<Vec<_>>();
+        let removed_origins = self.origins.splice(start..end, origins).collect::<Vec<_>>();
+        self.mark_edited(start);
+        Change {
+            line_idx: start,
+            old: removed_lines.into_iter().zip(removed_origins).collect(),
+            new_count: old_count,
+        }
+    }
+    // 返回并清除自上次调用以来被修改的第一行
+    pub fn take_edited_from(&mut self) -> Option<LineIdx> {
+        self.edited_from.take()
+    }
     pub const fn get_file_info(&self) -> &FileInfo {
         &self.file_info
     }
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        self.file_info.set_file_type(file_type);
+    }
 
     pub fn grapheme_count(&self, idx: LineIdx) -> GraphemeIdx {
         self.lines.get(idx).map_or(0, Line::grapheme_count)
@@ -32,6 +172,10 @@ impl Buffer {
             .map_or(0, |line| line.width_until(until))
     }
 
+    pub fn width(&self, idx: LineIdx) -> ColIdx {
+        self.lines.get(idx).map_or(0, Line::width)
+    }
+
     pub fn get_highlighted_substring(
         &self,
         line_idx: LineIdx,
@@ -48,24 +192,78 @@ impl Buffer {
             highlighter.highlight(idx, line);
         }
     }
+    pub fn advance_highlighter(&self, idx: LineIdx, highlighter: &mut Highlighter) {
+        if let Some(line) = self.lines.get(idx) {
+            highlighter.advance(idx, line);
+        }
+    }
+    pub fn advance_syntax_highlighter(&self, idx: LineIdx, highlighter: &mut Highlighter) {
+        if let Some(line) = self.lines.get(idx) {
+            highlighter.advance_syntax(idx, line);
+        }
+    }
+
+    // 从文本创建没有文件名的缓冲区，例如嵌入编辑器或不经过终端的测试
+    pub fn from_text(text: &str) -> Self {
+        // 从内存读取字符串不会失败
+        Self::load_from_reader(text.as_bytes(), DEFAULT_TAB_WIDTH).unwrap_or_default()
+    }
 
-    pub fn load(file_name: &str) -> Result<Self, Error> {
-        let contents = read_to_string(file_name)?;
+    pub fn load(file_name: &str, tab_width: usize) -> Result<Self, Error> {
+        let buffer = Self::load_from_reader(File::open(file_name)?, tab_width)?;
+        Ok(Self {
+            file_info: FileInfo::from(file_name),
+            ..buffer
+        })
+    }
+
+    // 从任意输入（如标准输入）读取文档，得到的缓冲区没有文件名
+    pub fn load_from_reader(mut reader: impl Read, tab_width: usize) -> Result<Self, Error> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        // BOM 不属于文本内容，加载时去除，只记录其是否存在
+        let (contents, has_bom) = contents
+            .strip_prefix(BOM)
+            .map_or((contents.as_str(), false), |rest| (rest, true));
+        let tab_width = tab_width.max(1);
         let mut lines = Vec::new();
         for value in contents.lines() {
-            lines.push(Line::from(value));
+            lines.push(Line::with_tab_width(value, tab_width));
         }
-        Ok(Self {
+        // lines() 不区分 "a\n" 和 "a"，需要单独记录最后一行是否以换行符结尾
+        let missing_final_newline = !contents.is_empty() && !contents.ends_with('\n');
+        let crlf = contents
+            .find('\n')
+            .is_some_and(|idx| contents[..idx].ends_with('\r'));
+        let mut buffer = Self {
             lines,
-            file_info: FileInfo::from(file_name),
+            file_info: FileInfo::default(),
             dirty: false,
-        })
+            has_bom,
+            missing_final_newline,
+            crlf,
+            edited_from: None,
+            saved_hashes: Vec::new(),
+            origins: Vec::new(),
+            changes: Vec::new(),
+            tab_width,
+        };
+        buffer.mark_saved();
+        Ok(buffer)
     }
 
-    pub fn search_forward(&self, query: &str, from: Location) -> Option<Location> {
+    // 从 from 向后查找。wrap 为真时到达文档末尾后从开头继续，直到回到 from 所在的行；
+    // 因此唯一的匹配位于 from 之前（包括光标处）时也能找到，且最多只遍历一遍文档
+    pub fn search_forward(&self, query: &str, from: Location, wrap: bool) -> Option<Location> {
         if query.is_empty() {
             return None;
         }
+        // 绕回时取多一个，搜索当前行两次（一次从中间开始，一次从开始）
+        let line_count = if wrap {
+            self.lines.len().saturating_add(1)
+        } else {
+            self.lines.len().saturating_sub(from.line_idx)
+        };
         let mut is_first = true;
         for (line_idx, line) in self
             .lines
@@ -73,8 +271,7 @@ impl Buffer {
             .enumerate()
             .cycle()
             .skip(from.line_idx)
-            .take(self.lines.len().saturating_add(1))
-        // 取多一个，搜索当前行两次（一次从中间开始，一次从开始）
+            .take(line_count)
         {
             let from_grapheme_idx = if is_first {
                 is_first = false;
@@ -91,10 +288,16 @@ impl Buffer {
         }
         None
     }
-    pub fn search_backward(&self, query: &str, from: Location) -> Option<Location> {
+    // 从 from 向前查找，wrap 的含义同 search_forward
+    pub fn search_backward(&self, query: &str, from: Location, wrap: bool) -> Option<Location> {
         if query.is_empty() {
             return None;
         }
+        let line_count = if wrap {
+            self.lines.len().saturating_add(1)
+        } else {
+            from.line_idx.saturating_add(1)
+        };
         let mut is_first = true;
         for (line_idx, line) in self
             .lines
@@ -108,7 +311,7 @@ impl Buffer {
                     .saturating_sub(from.line_idx)
                     .saturating_sub(1),
             )
-            .take(self.lines.len().saturating_add(1))
+            .take(line_count)
         {
             let from_grapheme_idx = if is_first {
                 is_first = false;
@@ -126,11 +329,321 @@ impl Buffer {
         None
     }
 
+    // 收集整个文档中查询字符串的所有匹配位置
+    pub fn find_all(&self, query: &str) -> Vec<Location> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        self.lines
+            .iter()
+            .enumerate()
+            .flat_map(|(line_idx, line)| {
+                line.find_all(query, 0..line.len())
+                    .into_iter()
+                    .map(move |(_, grapheme_idx)| Location {
+                        grapheme_idx,
+                        line_idx,
+                    })
+            })
+            .collect()
+    }
+
+    // 返回查询的匹配总数，以及位于 at 处的匹配是第几个（从 1 开始，at 不是匹配时为 None）。
+    // 搜索时每次按键都会调用，因此只计数而不收集匹配的位置
+    pub fn count_matches(&self, query: &str, at: Location) -> (usize, Option<usize>) {
+        let mut total = 0usize;
+        let mut ordinal = None;
+        if query.is_empty() {
+            return (total, ordinal);
+        }
+        for (line_idx, line) in self.lines.iter().enumerate() {
+            for (_, grapheme_idx) in line.find_all(query, 0..line.len()) {
+                total = total.saturating_add(1);
+                if line_idx == at.line_idx && grapheme_idx == at.grapheme_idx {
+                    ordinal = Some(total);
+                }
+            }
+        }
+        (total, ordinal)
+    }
+
+    // 查找 location 处括号（()[]{}）的配对括号：左括号向后、右括号向前跨行扫描，按嵌套关系配对。
+    // 只扫描 lines 范围内的行，用于限制大文件中的扫描量。
+    // is_skipped(行, 字节索引) 为真的括号（例如字符串和注释中的）不参与配对。
+    // location 处不是括号或在范围内找不到配对时返回 None
+    pub fn find_matching_bracket(
+        &self,
+        location: Location,
+        lines: Range<LineIdx>,
+        mut is_skipped: impl FnMut(LineIdx, ByteIdx) -> bool,
+    ) -> Option<Location> {
+        let line = self.lines.get(location.line_idx)?;
+        let start = line
+            .byte_range(location.grapheme_idx..location.grapheme_idx.saturating_add(1))
+            .start;
+        let (target, nested, forward) = match line[start..].chars().next()? {
+            '(' => (')', '(', true),
+            '[' => (']', '[', true),
+            '{' => ('}', '{', true),
+            ')' => ('(', ')', false),
+            ']' => ('[', ']', false),
+            '}' => ('{', '}', false),
+            _ => return None,
+        };
+        let chars: Box<dyn Iterator<Item = (LineIdx, ByteIdx, char)>> = if forward {
+            Box::new(
+                self.lines
+                    .iter()
+                    .enumerate()
+                    .take(lines.end)
+                    .skip(location.line_idx)
+                    .flat_map(|(line_idx, line)| {
+                        line.char_indices()
+                            .map(move |(byte_idx, character)| (line_idx, byte_idx, character))
+                    })
+                    .skip_while(|&(line_idx, byte_idx, _)| {
+                        line_idx == location.line_idx && byte_idx <= start
+                    }),
+            )
+        } else {
+            Box::new(
+                self.lines
+                    .iter()
+                    .enumerate()
+                    .take(location.line_idx.saturating_add(1))
+                    .skip(lines.start)
+                    .rev()
+                    .flat_map(|(line_idx, line)| {
+                        line.char_indices()
+                            .rev()
+                            .map(move |(byte_idx, character)| (line_idx, byte_idx, character))
+                    })
+                    .skip_while(|&(line_idx, byte_idx, _)| {
+                        line_idx == location.line_idx && byte_idx >= start
+                    }),
+            )
+        };
+        let mut depth = 0usize;
+        for (line_idx, byte_idx, character) in chars {
+            if is_skipped(line_idx, byte_idx) {
+                continue;
+            }
+            if character == nested {
+                depth = depth.saturating_add(1);
+            } else if character == target {
+                if depth == 0 {
+                    return Some(Location {
+                        line_idx,
+                        grapheme_idx: self.lines.get(line_idx)?.byte_idx_to_grapheme_idx(byte_idx)?,
+                    });
+                }
+                depth = depth.saturating_sub(1);
+            }
+        }
+        None
+    }
+
+    // 返回所有缩进中混用制表符和空格的行
+    pub fn mixed_indentation_lines(&self) -> Vec<LineIdx> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.has_mixed_indentation())
+            .map(|(line_idx, _)| line_idx)
+            .collect()
+    }
+
+    // 返回超长行的数量
+    pub fn very_long_line_count(&self) -> usize {
+        self.lines.iter().filter(|line| line.is_very_long()).count()
+    }
+
+    // 转换 lines 范围内各行的行首缩进（超出文档的部分被忽略），返回发生改动的行数
+    pub fn convert_indentation(&mut self, style: IndentStyle, lines: Range<LineIdx>) -> usize {
+        let end = lines.end.min(self.lines.len());
+        let start = lines.start.min(end);
+        self.record(start, end.saturating_sub(start), end.saturating_sub(start));
+        let changed = self.lines[start..end]
+            .iter_mut()
+            .filter_map(|line| line.convert_indentation(style).then_some(()))
+            .count();
+        if changed > 0 {
+            self.mark_edited(start);
+        } else {
+            self.changes.pop();
+        }
+        changed
+    }
+
+    // 返回指定行的缩进参考线所在列，空白行沿用其后第一个非空白行的参考线
+    pub fn indent_guide_cols(&self, idx: LineIdx) -> Vec<ColIdx> {
+        self.lines
+            .iter()
+            .skip(idx)
+            .find_map(Line::indent_guide_cols)
+            .unwrap_or_default()
+    }
+
+    // 查找包围指定位置的花括号块，返回 '{' 和与之匹配的 '}' 所在的行。
+    // 若所在行以 '{' 结尾，则使用该行开启的块。不识别字符串和注释中的花括号。
+    pub fn enclosing_brace_block(&self, at: Location) -> Option<(LineIdx, LineIdx)> {
+        let (open_line_idx, from) = self.enclosing_open_brace(at)?;
+        let mut depth: usize = 0;
+        for (line_idx, line) in self.lines.iter().enumerate().skip(open_line_idx) {
+            let start = if line_idx == open_line_idx { from } else { 0 };
+            for character in line[start..].chars() {
+                match character {
+                    '{' => depth = depth.saturating_add(1),
+                    '}' if depth == 0 => return Some((open_line_idx, line_idx)),
+                    '}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    // 查找包围指定位置、在其之前尚未闭合的 '{'，返回所在行和 '{' 之后的字节位置，
+    // 不要求之后存在匹配的 '}'。若所在行以 '{' 结尾，则使用该行的 '{'。
+    pub fn enclosing_open_brace(&self, at: Location) -> Option<(LineIdx, ByteIdx)> {
+        let mut depth: usize = 0;
+        for line_idx in (0..=at.line_idx).rev() {
+            let Some(line) = self.lines.get(line_idx) else {
+                continue;
+            };
+            let end = if line_idx != at.line_idx || line.trim_end().ends_with('{') {
+                line.len()
+            } else {
+                line.byte_range(at.grapheme_idx..at.grapheme_idx).start
+            };
+            for (byte_idx, character) in line[..end].char_indices().rev() {
+                match character {
+                    '}' => depth = depth.saturating_add(1),
+                    '{' if depth == 0 => return Some((line_idx, byte_idx.saturating_add(1))),
+                    '{' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    // 查找指定行所在的缩进块：若其后的行缩进更深，则以该行为摘要行，
+    // 否则以其上方第一个缩进更浅的行为摘要行。返回摘要行和块的最后一个非空白行。
+    pub fn indentation_block(&self, line_idx: LineIdx) -> Option<(LineIdx, LineIdx)> {
+        let is_blank = |line: &Line| line.trim().is_empty();
+        let current = self.lines.get(line_idx)?;
+        let next_is_deeper = self
+            .lines
+            .iter()
+            .skip(line_idx.saturating_add(1))
+            .find(|line| !is_blank(line))
+            .is_some_and(|next| next.indent_width() > current.indent_width());
+        let header = if next_is_deeper && !is_blank(current) {
+            line_idx
+        } else {
+            let indent = self
+                .lines
+                .iter()
+                .skip(line_idx)
+                .find(|line| !is_blank(line))
+                .map_or(0, Line::indent_width);
+            (0..line_idx).rev().find(|&idx| {
+                !is_blank(&self.lines[idx]) && self.lines[idx].indent_width() < indent
+            })?
+        };
+        let header_indent = self.lines[header].indent_width();
+        let end = self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(header.saturating_add(1))
+            .filter(|(_, line)| !is_blank(line))
+            .take_while(|(_, line)| line.indent_width() > header_indent)
+            .last()
+            .map(|(idx, _)| idx)?;
+        Some((header, end))
+    }
+
+    // 返回包围指定行的各层块的摘要行（由外到内）。优先使用之前未闭合的 '{' 所在行
+    // （单独一行的 '{' 使用其上一行），没有时依次向上查找缩进更浅的非空白行。
+    pub fn enclosing_headers(&self, line_idx: LineIdx) -> Vec<LineIdx> {
+        let is_blank = |line: &Line| line.trim().is_empty();
+        let mut headers = Vec::new();
+        let mut depth: usize = 0;
+        for idx in (0..line_idx.min(self.lines.len())).rev() {
+            let line = &self.lines[idx];
+            for character in line.chars().rev() {
+                match character {
+                    '}' => depth = depth.saturating_add(1),
+                    '{' if depth == 0 => {
+                        let header = if line.trim() == "{" {
+                            idx.saturating_sub(1)
+                        } else {
+                            idx
+                        };
+                        if headers.last() != Some(&header) {
+                            headers.push(header);
+                        }
+                    }
+                    '{' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+        }
+        if headers.is_empty() {
+            let mut indent = self
+                .lines
+                .iter()
+                .skip(line_idx)
+                .find(|line| !is_blank(line))
+                .map_or(0, Line::indent_width);
+            for idx in (0..line_idx.min(self.lines.len())).rev() {
+                if indent == 0 {
+                    break;
+                }
+                let line = &self.lines[idx];
+                if !is_blank(line) && line.indent_width() < indent {
+                    headers.push(idx);
+                    indent = line.indent_width();
+                }
+            }
+        }
+        headers.reverse();
+        headers
+    }
+
+    pub fn get_line(&self, idx: LineIdx) -> Option<&Line> {
+        self.lines.get(idx)
+    }
+    // 按顺序遍历各行（不含换行符），只提供只读引用，修改须通过缓冲区的编辑方法
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter()
+    }
+
+    // 按保存到文件时的格式输出文档内容
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), Error> {
+        if self.has_bom {
+            write!(writer, "{BOM}")?;
+        }
+        let line_ending = if self.crlf { "\r\n" } else { "\n" };
+        let last_idx = self.lines.len().saturating_sub(1);
+        for (line_idx, line) in self.lines.iter().enumerate() {
+            if line_idx == last_idx && self.missing_final_newline {
+                write!(writer, "{line}")?;
+            } else {
+                write!(writer, "{line}{line_ending}")?;
+            }
+        }
+        writer.flush()
+    }
+
     fn save_to_file(&self, file_info: &FileInfo) -> Result<(), Error> {
         if let Some(file_path) = &file_info.get_path() {
-            let mut file = File::create(file_path)?;
-            for line in &self.lines {
-                writeln!(file, "{line}")?;
+            self.write_to(&mut File::create(file_path)?)?;
+            // 文件可能在编辑期间被删除或替换，重新应用原有权限
+            if let Some(permissions) = file_info.get_permissions() {
+                set_permissions(file_path, permissions.clone())?;
             }
         } else {
             #[cfg(debug_assertions)]
@@ -144,13 +657,13 @@ impl Buffer {
         let file_info = FileInfo::from(file_name);
         self.save_to_file(&file_info)?;
         self.file_info = file_info;
-        self.dirty = false;
+        self.mark_saved();
         Ok(())
     }
 
     pub fn save(&mut self) -> Result<(), Error> {
         self.save_to_file(&self.file_info)?;
-        self.dirty = false;
+        self.mark_saved();
         Ok(())
     }
 
@@ -160,41 +673,350 @@ impl Buffer {
     pub const fn is_file_loaded(&self) -> bool {
         self.file_info.has_path()
     }
+    pub const fn has_bom(&self) -> bool {
+        self.has_bom
+    }
+    pub const fn is_crlf(&self) -> bool {
+        self.crlf
+    }
     pub fn height(&self) -> LineIdx {
         self.lines.len()
     }
+    pub const fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+    // 改变制表符宽度并重新计算各行的列宽，宽度为 0 时按 1 处理；不算作修改文档
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width.max(1);
+        for line in &mut self.lines {
+            line.set_tab_width(self.tab_width);
+        }
+    }
     pub fn insert_char(&mut self, character: char, at: Location) {
         debug_assert!(at.line_idx <= self.height());
         if at.line_idx == self.height() {
-            self.lines.push(Line::from(&character.to_string()));
-            self.dirty = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_idx) {
-            line.insert_char(character, at.grapheme_idx);
-            self.dirty = true;
+            self.record(at.line_idx, 0, 1);
+            self.lines.push(Line::with_tab_width(&character.to_string(), self.tab_width));
+            self.origins.push(None);
+            self.mark_edited(at.line_idx);
+        } else if at.line_idx < self.height() {
+            self.record(at.line_idx, 1, 1);
+            self.lines[at.line_idx].insert_char(character, at.grapheme_idx);
+            self.mark_edited(at.line_idx);
         }
     }
+    // 插入一段可能包含换行的文本，返回插入的文本之后的位置。
+    // 每个受影响的行只重建一次，比逐个字素插入高效
+    pub fn insert_str(&mut self, text: &str, at: Location) -> Location {
+        if text.is_empty() || at.line_idx > self.height() {
+            return at;
+        }
+        let new_count = text.matches('\n').count().saturating_add(1);
+        if at.line_idx == self.height() {
+            self.record(at.line_idx, 0, new_count);
+            self.lines.push(Line::with_tab_width("", self.tab_width));
+            self.origins.push(None);
+        } else {
+            self.record(at.line_idx, 1, new_count);
+        }
+        let Some(line) = self.lines.get_mut(at.line_idx) else {
+            return at;
+        };
+        let old_count = line.grapheme_count();
+        let Some((first, rest)) = text.split_once('\n') else {
+            line.insert_str(text, at.grapheme_idx);
+            // 从插入点之后剩余的字素数推算终点，插入的文本可能与前后的字素合并
+            let tail_count = old_count.saturating_sub(at.grapheme_idx);
+            let end = Location {
+                line_idx: at.line_idx,
+                grapheme_idx: line.grapheme_count().saturating_sub(tail_count),
+            };
+            self.mark_edited(at.line_idx);
+            return end;
+        };
+        let original = line.to_string();
+        let split_at = line.byte_range(at.grapheme_idx..at.grapheme_idx).start;
+        let (head, tail) = original.split_at(split_at);
+        *line = Line::with_tab_width(&format!("{head}{first}"), self.tab_width);
+        let mut new_lines: Vec<Line> = rest
+            .split('\n')
+            .map(|value| Line::with_tab_width(value, self.tab_width))
+            .collect();
+        let tail_count = Line::from(tail).grapheme_count();
+        let mut end = Location {
+            line_idx: at.line_idx.saturating_add(new_lines.len()),
+            grapheme_idx: 0,
+        };
+        if let Some(last) = new_lines.last_mut() {
+            last.insert_str(tail, last.grapheme_count());
+            end.grapheme_idx = last.grapheme_count().saturating_sub(tail_count);
+        }
+        let after = at.line_idx.saturating_add(1);
+        let count = new_lines.len();
+        self.lines.splice(after..after, new_lines);
+        self.origins.splice(after..after, std::iter::repeat_n(None, count));
+        self.mark_edited(at.line_idx);
+        end
+    }
+    // 交换两行的内容，任一行不存在时不做任何事
+    pub fn swap_lines(&mut self, a: LineIdx, b: LineIdx) {
+        let (top, bottom) = (a.min(b), a.max(b));
+        if a == b || bottom >= self.height() {
+            return;
+        }
+        let count = bottom.saturating_sub(top).saturating_add(1);
+        self.record(top, count, count);
+        self.lines.swap(a, b);
+        self.origins.swap(a, b);
+        self.mark_edited(top);
+    }
+    // 在指定行之前插入若干整行
+    pub fn insert_lines(&mut self, at: LineIdx, mut lines: Vec<Line>) {
+        let at = at.min(self.height());
+        for line in &mut lines {
+            line.set_tab_width(self.tab_width);
+        }
+        let count = lines.len();
+        self.record(at, 0, count);
+        self.lines.splice(at..at, lines);
+        self.origins.splice(at..at, std::iter::repeat_n(None, count));
+        self.mark_edited(at);
+    }
     pub fn delete(&mut self, at: Location) {
         if let Some(line) = self.lines.get(at.line_idx) {
             if at.grapheme_idx >= line.grapheme_count()
                 && self.height() > at.line_idx.saturating_add(1)
             {
+                self.record(at.line_idx, 2, 1);
                 let next_line = self.lines.remove(at.line_idx.saturating_add(1));
+                self.origins.remove(at.line_idx.saturating_add(1));
                 self.lines[at.line_idx].append(&next_line);
-                self.dirty = true;
+                self.mark_edited(at.line_idx);
             } else if at.grapheme_idx < line.grapheme_count() {
+                self.record(at.line_idx, 1, 1);
                 self.lines[at.line_idx].delete(at.grapheme_idx);
-                self.dirty = true;
+                self.mark_edited(at.line_idx);
+            }
+        }
+    }
+    // 交换一行内指定位置的字素与其前一个字素
+    pub fn transpose(&mut self, at: Location) {
+        let count = self.grapheme_count(at.line_idx);
+        if at.grapheme_idx > 0 && at.grapheme_idx < count {
+            self.record(at.line_idx, 1, 1);
+            self.lines[at.line_idx].transpose(at.grapheme_idx);
+            self.mark_edited(at.line_idx);
+        }
+    }
+    // 删除一行内指定字素范围的内容，不会合并相邻行
+    pub fn delete_in_line(&mut self, line_idx: LineIdx, range: Range<GraphemeIdx>) {
+        let count = self.grapheme_count(line_idx);
+        if line_idx < self.height() && range.start < range.end && range.start < count {
+            self.record(line_idx, 1, 1);
+            self.lines[line_idx].delete_range(range);
+            self.mark_edited(line_idx);
+        }
+    }
+    // 一次删除从 from 到 to（不含）之间可能跨越多行的文本，首行剩余的开头与末行剩余的结尾合并为一行。
+    // 删除后 from 即为原先 to 处的文本所在的位置
+    pub fn delete_range(&mut self, from: Location, to: Location) {
+        let Some(last_line_idx) = self.height().checked_sub(1) else {
+            return;
+        };
+        let to = if to.line_idx > last_line_idx {
+            Location {
+                line_idx: last_line_idx,
+                grapheme_idx: self.grapheme_count(last_line_idx),
             }
+        } else {
+            to
+        };
+        if (to.line_idx, to.grapheme_idx) <= (from.line_idx, from.grapheme_idx) {
+            return;
+        }
+        if from.line_idx == to.line_idx {
+            self.delete_in_line(from.line_idx, from.grapheme_idx..to.grapheme_idx);
+            return;
         }
+        self.record(from.line_idx, to.line_idx.saturating_sub(from.line_idx).saturating_add(1), 1);
+        let last = &self.lines[to.line_idx];
+        let tail = last[last.byte_range(to.grapheme_idx..last.grapheme_count())].to_string();
+        let first = &mut self.lines[from.line_idx];
+        let head_end = first.byte_range(from.grapheme_idx..from.grapheme_idx).start;
+        *first = Line::with_tab_width(&format!("{}{tail}", &first[..head_end]), self.tab_width);
+        let removed = from.line_idx.saturating_add(1)..=to.line_idx;
+        self.lines.drain(removed.clone());
+        self.origins.drain(removed);
+        self.mark_edited(from.line_idx);
     }
     pub fn insert_newline(&mut self, at: Location) {
         if at.line_idx == self.height() {
-            self.lines.push(Line::default());
-            self.dirty = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_idx) {
-            let new = line.split(at.grapheme_idx);
+            self.record(at.line_idx, 0, 1);
+            self.lines.push(Line::with_tab_width("", self.tab_width));
+            self.origins.push(None);
+            self.mark_edited(at.line_idx);
+        } else if at.line_idx < self.height() {
+            self.record(at.line_idx, 1, 2);
+            let new = self.lines[at.line_idx].split(at.grapheme_idx);
             self.lines.insert(at.line_idx.saturating_add(1), new);
-            self.dirty = true;
+            self.origins.insert(at.line_idx.saturating_add(1), None);
+            self.mark_edited(at.line_idx);
+        }
+    }
+}
+
+// 文档的文本内容：各行以 \n 分隔，最后一行的换行符与文件中一致，不含 BOM。
+// 按保存时的格式（CRLF、BOM）输出请使用 write_to
+impl Display for Buffer {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let last_idx = self.lines.len().saturating_sub(1);
+        for (line_idx, line) in self.lines.iter().enumerate() {
+            if line_idx == last_idx && self.missing_final_newline {
+                write!(formatter, "{line}")?;
+            } else {
+                writeln!(formatter, "{line}")?;
+            }
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Buffer;
+    use crate::prelude::*;
+    use std::{env, fs, path::PathBuf, process};
+
+    // 各测试使用不同的文件名，可以并行运行
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("tzt-buffer-{}-{name}", process::id()))
+    }
+
+    // 写入文件、加载、在第一行开头插入再删除一个字符，保存后读回
+    fn load_edit_save(name: &str, contents: &[u8]) -> Vec<u8> {
+        let path = temp_path(name);
+        fs::write(&path, contents).unwrap();
+        let file_name = path.to_str().unwrap();
+        let mut buffer = Buffer::load(file_name, DEFAULT_TAB_WIDTH).unwrap();
+        buffer.insert_char('x', Location::default());
+        buffer.delete(Location::default());
+        buffer.save().unwrap();
+        let saved = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        saved
+    }
+
+    #[test]
+    fn save_keeps_line_endings_and_final_newline() {
+        for (name, contents) in [
+            ("lf", "a\nb\n"),
+            ("crlf", "a\r\nb\r\n"),
+            ("no-final-newline", "a\nb"),
+            ("crlf-no-final-newline", "a\r\nb"),
+            ("bom", "\u{feff}a\nb\n"),
+        ] {
+            let saved = load_edit_save(name, contents.as_bytes());
+            assert_eq!(String::from_utf8(saved).unwrap(), contents, "{name}");
+        }
+    }
+
+    #[test]
+    fn crlf_applies_to_new_lines() {
+        let mut buffer =
+            Buffer::load_from_reader("a\r\nb\r\n".as_bytes(), DEFAULT_TAB_WIDTH).unwrap();
+        buffer.insert_newline(Location {
+            grapheme_idx: 1,
+            line_idx: 0,
+        });
+        let mut saved = Vec::new();
+        buffer.write_to(&mut saved).unwrap();
+        assert_eq!(saved, b"a\r\n\r\nb\r\n");
+        // 文本内容不含回车符和 BOM
+        assert_eq!(buffer.to_string(), "a\n\nb\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_keeps_bom_and_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = temp_path("mode");
+        fs::write(&path, b"\xEF\xBB\xBF#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        let mut buffer = Buffer::load(path.to_str().unwrap(), DEFAULT_TAB_WIDTH).unwrap();
+        buffer.insert_newline(Location {
+            grapheme_idx: 0,
+            line_idx: 1,
+        });
+        // 保存前文件被删除，重新创建的文件仍使用加载时的权限
+        fs::remove_file(&path).unwrap();
+        buffer.save().unwrap();
+        let saved = fs::read(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved, b"\xEF\xBB\xBF#!/bin/sh\n\n");
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    // 查找 (line_idx, grapheme_idx) 处括号的配对括号，扫描整个文档
+    fn find(
+        text: &str,
+        (line_idx, grapheme_idx): (LineIdx, GraphemeIdx),
+        is_skipped: impl FnMut(LineIdx, ByteIdx) -> bool,
+    ) -> Option<(LineIdx, GraphemeIdx)> {
+        let buffer = Buffer::from_text(text);
+        let location = Location {
+            line_idx,
+            grapheme_idx,
+        };
+        buffer
+            .find_matching_bracket(location, 0..buffer.height(), is_skipped)
+            .map(|location| (location.line_idx, location.grapheme_idx))
+    }
+
+    #[test]
+    fn finds_matching_bracket_across_nesting_and_lines() {
+        let no_skip = |_, _| false;
+        assert_eq!(find("a(b(c)d)e", (0, 1), no_skip), Some((0, 7)));
+        assert_eq!(find("a(b(c)d)e", (0, 3), no_skip), Some((0, 5)));
+        assert_eq!(find("a(b(c)d)e", (0, 7), no_skip), Some((0, 1)));
+        // 不同种类的括号互不影响
+        assert_eq!(find("{[}]}", (0, 0), no_skip), Some((0, 2)));
+        let text = "fn f() {\n    [1,\n中 2]\n}\n";
+        assert_eq!(find(text, (0, 7), no_skip), Some((3, 0)));
+        assert_eq!(find(text, (3, 0), no_skip), Some((0, 7)));
+        // 返回字素索引而不是字节索引
+        assert_eq!(find(text, (1, 4), no_skip), Some((2, 3)));
+        assert_eq!(find(text, (2, 3), no_skip), Some((1, 4)));
+        // 光标处不是括号
+        assert_eq!(find(text, (0, 0), no_skip), None);
+    }
+
+    #[test]
+    fn skips_brackets_inside_strings_and_comments() {
+        // 第 0 行字节 2..5 是字符串 "}"，8..12 是注释 // }
+        let text = "{ \"}\" x // }\n}\n";
+        let mut skipped = Vec::new();
+        let is_skipped = |line_idx, byte_idx| {
+            skipped.push((line_idx, byte_idx));
+            line_idx == 0 && ((2..5).contains(&byte_idx) || (8..12).contains(&byte_idx))
+        };
+        assert_eq!(find(text, (0, 0), is_skipped), Some((1, 0)));
+        // 光标之后的每个字符都询问过一次（换行符不算），且止于配对括号
+        assert_eq!(skipped.len(), 12);
+        assert_eq!(skipped.last(), Some(&(1, 0)));
+        assert_eq!(find(text, (0, 0), |_, _| false), Some((0, 3)));
+    }
+
+    #[test]
+    fn unbalanced_brackets_have_no_match() {
+        let no_skip = |_, _| false;
+        assert_eq!(find("(a", (0, 0), no_skip), None);
+        assert_eq!(find("a)", (0, 1), no_skip), None);
+        assert_eq!(find("((a)", (0, 0), no_skip), None);
+        assert_eq!(find("((a)", (0, 1), no_skip), Some((0, 3)));
+        assert_eq!(find("a\n)\n", (1, 0), no_skip), None);
+        // 配对括号在扫描范围之外
+        let buffer = Buffer::from_text("(\n\n)\n");
+        assert_eq!(buffer.find_matching_bracket(Location::default(), 0..2, no_skip), None);
     }
 }
@@ -1,40 +1,57 @@
 use super::super::super::FileType;
+use super::highlighter::file_type_for_extension;
 use std::{
     fmt::{self, Display},
+    fs::{self, Permissions},
     path::{Path, PathBuf},
 };
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct FileInfo {
     path: Option<PathBuf>,
+    absolute_path: Option<PathBuf>, // 加载或保存时解析一次，状态栏不必每次重新解析
     file_type: FileType,
+    permissions: Option<Permissions>, // 文件已存在时的权限（Unix 下包括模式位），保存后恢复
 }
 
 impl FileInfo {
     pub fn from(file_name: &str) -> Self {
         let path = PathBuf::from(file_name);
-        let file_type = if path
+        let file_type = path
             .extension()
-            .map_or(false, |ext| ext.eq_ignore_ascii_case("rs"))
-        {
-            FileType::Rust
-        } else {
-            FileType::Text
-        };
+            .and_then(|ext| ext.to_str())
+            .and_then(file_type_for_extension)
+            .unwrap_or_default();
+        let permissions = fs::metadata(&path)
+            .ok()
+            .map(|metadata| metadata.permissions());
+        let absolute_path = std::path::absolute(&path).unwrap_or_else(|_| path.clone());
         Self {
             path: Some(path),
+            absolute_path: Some(absolute_path),
             file_type,
+            permissions,
         }
     }
     pub fn get_path(&self) -> Option<&Path> {
         self.path.as_deref()
     }
+    // 文件的绝对路径；无法解析时为原路径
+    pub fn get_absolute_path(&self) -> Option<&Path> {
+        self.absolute_path.as_deref()
+    }
     pub const fn has_path(&self) -> bool {
         self.path.is_some()
     }
     pub const fn get_file_type(&self) -> FileType {
         self.file_type
     }
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        self.file_type = file_type;
+    }
+    pub const fn get_permissions(&self) -> Option<&Permissions> {
+        self.permissions.as_ref()
+    }
 }
 
 impl Display for FileInfo {
@@ -0,0 +1,18 @@
+use crate::prelude::*;
+
+// 折叠区域：start 行作为摘要行保留显示，start+1..=end 行被隐藏，但仍保留在缓冲区中
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Fold {
+    pub start: LineIdx,
+    pub end: LineIdx,
+}
+
+impl Fold {
+    pub const fn hides(&self, line_idx: LineIdx) -> bool {
+        line_idx > self.start && line_idx <= self.end
+    }
+
+    pub const fn hidden_count(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+}
@@ -0,0 +1,99 @@
+use super::LineChange;
+use crate::prelude::*;
+use std::{
+    ops::Range,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+// git diff 中的一个变更块，行号为已保存文件中从 0 开始的行索引
+struct Hunk {
+    lines: Range<LineIdx>,
+    change: LineChange,
+}
+
+// 已保存的文件相对 git 索引的变更，加载和保存时更新；不在仓库中或 git 不可用时为空
+#[derive(Default)]
+pub struct GitHunks {
+    hunks: Vec<Hunk>,
+}
+
+impl GitHunks {
+    pub fn load(path: &Path) -> Self {
+        let dir = path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        let Some(file_name) = path.file_name() else {
+            return Self::default();
+        };
+        let output = Command::new("git")
+            .args(["diff", "--no-color", "--no-ext-diff", "-U0", "--"])
+            .arg(file_name)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                Self::parse(&String::from_utf8_lossy(&output.stdout))
+            }
+            Ok(output) => {
+                log::debug!("git diff 失败（可能不在仓库中）: {}", output.status);
+                Self::default()
+            }
+            Err(err) => {
+                log::debug!("无法运行 git: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    // 解析 -U0 输出中的块头 "@@ -a,b +c,d @@"，省略的行数为 1
+    fn parse(diff: &str) -> Self {
+        let hunks = diff
+            .lines()
+            .filter_map(|line| line.strip_prefix("@@ -"))
+            .filter_map(|header| {
+                let (old, rest) = header.split_once(" +")?;
+                let (new, _) = rest.split_once(" @@")?;
+                let (_, old_count) = Self::parse_range(old)?;
+                let (new_start, new_count) = Self::parse_range(new)?;
+                let hunk = if new_count == 0 {
+                    // 删除的行位于新文件第 new_start 行之后，标记在下一行上
+                    Hunk {
+                        lines: new_start..new_start.saturating_add(1),
+                        change: LineChange::RemovedAbove,
+                    }
+                } else {
+                    let start = new_start.saturating_sub(1);
+                    Hunk {
+                        lines: start..start.saturating_add(new_count),
+                        change: if old_count == 0 {
+                            LineChange::Added
+                        } else {
+                            LineChange::Modified
+                        },
+                    }
+                };
+                Some(hunk)
+            })
+            .collect();
+        Self { hunks }
+    }
+
+    fn parse_range(range: &str) -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    }
+
+    // 已保存文件中的某一行所在变更块的类型
+    pub fn change_at(&self, saved_line_idx: LineIdx) -> Option<LineChange> {
+        self.hunks
+            .iter()
+            .find(|hunk| hunk.lines.contains(&saved_line_idx))
+            .map(|hunk| hunk.change)
+    }
+}
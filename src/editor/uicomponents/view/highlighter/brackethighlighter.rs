@@ -0,0 +1,28 @@
+use std::collections::HashMap;
+
+use super::{Annotation, AnnotationType};
+use crate::prelude::*;
+
+// 标记光标处的括号及其配对括号。括号的位置由视图在语法高亮之后算出（需要跳过字符串和注释），
+// 因此直接以（行, 字节索引）给出，不再逐行查找
+pub struct BracketHighlighter {
+    highlights: HashMap<LineIdx, Vec<Annotation>>,
+}
+
+impl BracketHighlighter {
+    pub fn new(brackets: &[(LineIdx, ByteIdx)], annotation_type: AnnotationType) -> Self {
+        let mut highlights: HashMap<LineIdx, Vec<Annotation>> = HashMap::new();
+        for &(line_idx, start) in brackets {
+            highlights.entry(line_idx).or_default().push(Annotation {
+                annotation_type,
+                start,
+                end: start.saturating_add(1), // 括号都是单字节字符
+            });
+        }
+        Self { highlights }
+    }
+
+    pub fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.highlights.get(&idx)
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use super::{syntaxhighlighter::SyntaxHighlighter, Annotation, AnnotationType, Line};
+use crate::prelude::*;
+
+// 终端只能显示一个真实光标，其余光标以注解的形式标记其所在的字素
+pub struct CaretHighlighter<'a> {
+    carets: &'a [Location],
+    highlights: HashMap<LineIdx, Vec<Annotation>>,
+}
+
+impl<'a> CaretHighlighter<'a> {
+    pub fn new(carets: &'a [Location]) -> Self {
+        Self {
+            carets,
+            highlights: HashMap::new(),
+        }
+    }
+}
+
+impl SyntaxHighlighter for CaretHighlighter<'_> {
+    fn highlight(&mut self, idx: LineIdx, line: &Line) {
+        let result = self
+            .carets
+            .iter()
+            .filter(|caret| caret.line_idx == idx && caret.grapheme_idx < line.grapheme_count())
+            .map(|caret| {
+                let byte_range =
+                    line.byte_range(caret.grapheme_idx..caret.grapheme_idx.saturating_add(1));
+                Annotation {
+                    annotation_type: AnnotationType::SecondaryCaret,
+                    start: byte_range.start,
+                    end: byte_range.end,
+                }
+            })
+            .collect();
+        self.highlights.insert(idx, result);
+    }
+    fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.highlights.get(&idx)
+    }
+
+    fn invalidate_from(&mut self, idx: LineIdx) {
+        self.highlights.retain(|&line_idx, _| line_idx < idx);
+    }
+}
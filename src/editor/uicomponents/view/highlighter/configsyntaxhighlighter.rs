@@ -0,0 +1,195 @@
+use super::rustsyntaxhighlighter::is_valid_number;
+use super::{Annotation, AnnotationType, Line, SyntaxHighlighter};
+use crate::prelude::*;
+use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+// 从 TOML 文件读取的简易语法定义，例如：
+//
+// name = "Python"
+// extensions = ["py"]
+// keywords = ["def", "class", "return"]
+// types = ["int", "str"]
+// known_values = ["True", "False", "None"]
+// line_comments = ["#"]
+// block_comment = ["\"\"\"", "\"\"\""]
+// string_delimiters = ["\"", "'"]
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Grammar {
+    pub name: String,
+    pub extensions: Vec<String>,
+    keywords: Vec<String>,
+    types: Vec<String>,
+    known_values: Vec<String>,
+    line_comments: Vec<String>,
+    block_comment: Option<(String, String)>, // 起始和结束标记，不支持嵌套
+    string_delimiters: Vec<String>,          // 字符串的起止标记，字符串不跨行
+    escape: Option<char>,                    // 字符串内的转义字符
+    numbers: bool,                           // 是否高亮数字字面量
+}
+
+impl Default for Grammar {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            extensions: Vec::new(),
+            keywords: Vec::new(),
+            types: Vec::new(),
+            known_values: Vec::new(),
+            line_comments: Vec::new(),
+            block_comment: None,
+            string_delimiters: Vec::new(),
+            escape: Some('\\'),
+            numbers: true,
+        }
+    }
+}
+
+// 根据语法定义进行近似高亮的通用语法高亮器
+pub struct ConfigSyntaxHighlighter {
+    grammar: Grammar,
+    highlights: Vec<Vec<Annotation>>,
+    entering_in_block_comment: Vec<bool>, // 每行开始时是否位于块注释中，与 highlights 一一对应
+    in_block_comment: bool,
+}
+
+impl ConfigSyntaxHighlighter {
+    pub const fn new(grammar: Grammar) -> Self {
+        Self {
+            grammar,
+            highlights: Vec::new(),
+            entering_in_block_comment: Vec::new(),
+            in_block_comment: false,
+        }
+    }
+
+    // 从块注释内部开始（跳过 skip 字节的起始标记）查找结束标记，未找到时注释持续到行尾
+    fn annotate_block_comment_end(&mut self, string: &str, skip: ByteIdx) -> Annotation {
+        let end = self.grammar.block_comment.as_ref().and_then(|(_, end)| {
+            string
+                .get(skip..)
+                .and_then(|rest| rest.find(end.as_str()))
+                .map(|idx| skip.saturating_add(idx).saturating_add(end.len()))
+        });
+        self.in_block_comment = end.is_none();
+        Annotation {
+            annotation_type: AnnotationType::Comment,
+            start: 0,
+            end: end.unwrap_or(string.len()),
+        }
+    }
+
+    fn annotate_block_comment(&mut self, string: &str) -> Option<Annotation> {
+        let start = self
+            .grammar
+            .block_comment
+            .as_ref()
+            .map(|(start, _)| start.as_str())
+            .filter(|start| !start.is_empty() && string.starts_with(start))?;
+        Some(self.annotate_block_comment_end(string, start.len()))
+    }
+
+    fn annotate_line_comment(&self, string: &str) -> Option<Annotation> {
+        self.grammar
+            .line_comments
+            .iter()
+            .any(|token| !token.is_empty() && string.starts_with(token.as_str()))
+            .then_some(Annotation {
+                annotation_type: AnnotationType::Comment,
+                start: 0,
+                end: string.len(),
+            })
+    }
+
+    fn annotate_string(&self, string: &str) -> Option<Annotation> {
+        let delimiter = self
+            .grammar
+            .string_delimiters
+            .iter()
+            .find(|delimiter| !delimiter.is_empty() && string.starts_with(delimiter.as_str()))?;
+        let mut end = string.len();
+        let mut chars = string.char_indices().skip(delimiter.chars().count());
+        while let Some((idx, char)) = chars.next() {
+            if Some(char) == self.grammar.escape {
+                chars.next(); // 跳过被转义的字符
+            } else if string[idx..].starts_with(delimiter.as_str()) {
+                end = idx.saturating_add(delimiter.len());
+                break;
+            }
+        }
+        Some(Annotation {
+            annotation_type: AnnotationType::String,
+            start: 0,
+            end,
+        })
+    }
+
+    fn annotate_word(&self, string: &str) -> Option<Annotation> {
+        let word = string.split_word_bounds().next()?;
+        let contains = |words: &[String]| words.iter().any(|candidate| candidate == word);
+        let annotation_type = if self.grammar.numbers && is_valid_number(word) {
+            AnnotationType::Number
+        } else if contains(&self.grammar.keywords) {
+            AnnotationType::Keyword
+        } else if contains(&self.grammar.types) {
+            AnnotationType::Type
+        } else if contains(&self.grammar.known_values) {
+            AnnotationType::KnownValue
+        } else {
+            return None;
+        };
+        Some(Annotation {
+            annotation_type,
+            start: 0,
+            end: word.len(),
+        })
+    }
+
+    fn annotate_remainder(&mut self, remainder: &str) -> Option<Annotation> {
+        self.annotate_block_comment(remainder)
+            .or_else(|| self.annotate_line_comment(remainder))
+            .or_else(|| self.annotate_string(remainder))
+            .or_else(|| self.annotate_word(remainder))
+    }
+}
+
+impl SyntaxHighlighter for ConfigSyntaxHighlighter {
+    fn highlight(&mut self, idx: LineIdx, line: &Line) {
+        debug_assert_eq!(idx, self.highlights.len());
+        self.entering_in_block_comment.push(self.in_block_comment);
+        let mut result = Vec::new();
+        let mut annotated_until = 0;
+        if self.in_block_comment {
+            // 处理悬挂的块注释
+            let annotation = self.annotate_block_comment_end(line, 0);
+            annotated_until = annotation.end;
+            result.push(annotation);
+        }
+        for (start_idx, _) in line.split_word_bound_indices() {
+            // 跳过已经注释过的单词，字符串等标记可能在单词内部结束
+            if start_idx < annotated_until {
+                continue;
+            }
+            let remainder = &line[start_idx..];
+            if let Some(mut annotation) = self.annotate_remainder(remainder) {
+                annotation.shift(start_idx);
+                annotated_until = annotation.end;
+                result.push(annotation);
+            }
+        }
+        self.highlights.push(result);
+    }
+
+    fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.highlights.get(idx)
+    }
+
+    fn invalidate_from(&mut self, idx: LineIdx) {
+        if let Some(&in_block_comment) = self.entering_in_block_comment.get(idx) {
+            self.in_block_comment = in_block_comment;
+            self.entering_in_block_comment.truncate(idx);
+            self.highlights.truncate(idx);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use super::{Annotation, AnnotationType, Line, SyntaxHighlighter};
+use crate::prelude::*;
+
+const KNOWN_VALUES: [&str; 3] = ["true", "false", "null"];
+
+// JSON 的字符串不能跨行，唯一的跨行状态是尚未闭合的括号。
+// 括号按嵌套关系匹配，多余或不匹配的右括号标记为错误
+#[derive(Default)]
+pub struct JsonSyntaxHighlighter {
+    highlights: Vec<Vec<Annotation>>,
+    entering_brackets: Vec<Vec<char>>, // 每行开始时尚未闭合的左括号，与 highlights 一一对应
+    open_brackets: Vec<char>,
+}
+
+impl JsonSyntaxHighlighter {
+    // 右括号与最近的左括号匹配时弹出左括号
+    fn close_bracket(&mut self, close: char) -> AnnotationType {
+        let open = if close == '}' { '{' } else { '[' };
+        if self.open_brackets.last() == Some(&open) {
+            self.open_brackets.pop();
+            AnnotationType::Punctuation
+        } else {
+            AnnotationType::Error
+        }
+    }
+}
+
+impl SyntaxHighlighter for JsonSyntaxHighlighter {
+    fn highlight(&mut self, idx: LineIdx, line: &Line) {
+        debug_assert_eq!(idx, self.highlights.len());
+        self.entering_brackets.push(self.open_brackets.clone());
+        let mut result = Vec::new();
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, character)) = chars.next() {
+            let annotation_type = match character {
+                '"' => {
+                    let Some(end) = string_end(&line[start..]).map(|len| start.saturating_add(len))
+                    else {
+                        // 未闭合的字符串：到行尾为止标记为错误，不影响之后的行
+                        result.push(Annotation {
+                            annotation_type: AnnotationType::Error,
+                            start,
+                            end: line.len(),
+                        });
+                        break;
+                    };
+                    while chars.next_if(|&(idx, _)| idx < end).is_some() {}
+                    // 紧跟冒号的字符串是对象的键
+                    let annotation_type = if line[end..].trim_start().starts_with(':') {
+                        AnnotationType::Key
+                    } else {
+                        AnnotationType::String
+                    };
+                    result.push(Annotation {
+                        annotation_type,
+                        start,
+                        end,
+                    });
+                    continue;
+                }
+                '{' | '[' => {
+                    self.open_brackets.push(character);
+                    AnnotationType::Punctuation
+                }
+                '}' | ']' => self.close_bracket(character),
+                ':' | ',' => AnnotationType::Punctuation,
+                _ if character == '-' || character.is_ascii_alphanumeric() => {
+                    let mut end = start.saturating_add(character.len_utf8());
+                    while let Some((idx, next)) = chars.next_if(|&(_, next)| {
+                        next.is_ascii_alphanumeric() || matches!(next, '.' | '+' | '-')
+                    }) {
+                        end = idx.saturating_add(next.len_utf8());
+                    }
+                    let word = &line[start..end];
+                    let annotation_type = if KNOWN_VALUES.contains(&word) {
+                        AnnotationType::KnownValue
+                    } else if is_json_number(word) {
+                        AnnotationType::Number
+                    } else {
+                        continue;
+                    };
+                    result.push(Annotation {
+                        annotation_type,
+                        start,
+                        end,
+                    });
+                    continue;
+                }
+                _ => continue,
+            };
+            result.push(Annotation {
+                annotation_type,
+                start,
+                end: start.saturating_add(character.len_utf8()),
+            });
+        }
+        self.highlights.push(result);
+    }
+
+    fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.highlights.get(idx)
+    }
+
+    fn invalidate_from(&mut self, idx: LineIdx) {
+        if let Some(brackets) = self.entering_brackets.get_mut(idx) {
+            self.open_brackets = std::mem::take(brackets);
+            self.entering_brackets.truncate(idx);
+            self.highlights.truncate(idx);
+        }
+    }
+}
+
+// 以引号开头的字符串的字节长度（包括两端的引号），未闭合时返回 None
+fn string_end(string: &str) -> Option<ByteIdx> {
+    let mut chars = string.char_indices().skip(1);
+    while let Some((idx, character)) = chars.next() {
+        match character {
+            '\\' => {
+                chars.next(); // 跳过转义字符
+            }
+            '"' => return Some(idx.saturating_add(1)),
+            _ => {}
+        }
+    }
+    None
+}
+
+// JSON 数字：可选的负号，没有多余前导零的整数部分，可选的小数部分和指数部分
+fn is_json_number(word: &str) -> bool {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    let (mantissa, exponent) = match digits.find(['e', 'E']) {
+        Some(idx) => (&digits[..idx], Some(&digits[idx.saturating_add(1)..])),
+        None => (digits, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit());
+    is_digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(is_digits)
+        && exponent.is_none_or(|exponent| {
+            is_digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnnotationType, JsonSyntaxHighlighter, Line, SyntaxHighlighter};
+
+    // 逐行高亮，返回每行的 (注解类型, 注解文本)，省略标点
+    fn highlight(text: &str) -> Vec<Vec<(AnnotationType, String)>> {
+        let mut highlighter = JsonSyntaxHighlighter::default();
+        let lines: Vec<Line> = text.lines().map(Line::from).collect();
+        for (idx, line) in lines.iter().enumerate() {
+            highlighter.highlight(idx, line);
+        }
+        lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                highlighter
+                    .get_annotations(idx)
+                    .into_iter()
+                    .flatten()
+                    .filter(|annotation| annotation.annotation_type != AnnotationType::Punctuation)
+                    .map(|annotation| {
+                        let text = line[annotation.start..annotation.end].to_string();
+                        (annotation.annotation_type, text)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn annotation(annotation_type: AnnotationType, text: &str) -> (AnnotationType, String) {
+        (annotation_type, text.to_string())
+    }
+
+    #[test]
+    fn distinguishes_keys_from_string_values() {
+        let lines = highlight("{\"a\": \"b\", \"c\" : [\"d\", \"e:\"], \"f\\\"\":\"g\"}");
+        assert_eq!(
+            lines[0],
+            [
+                annotation(AnnotationType::Key, "\"a\""),
+                annotation(AnnotationType::String, "\"b\""),
+                annotation(AnnotationType::Key, "\"c\""),
+                annotation(AnnotationType::String, "\"d\""),
+                annotation(AnnotationType::String, "\"e:\""),
+                annotation(AnnotationType::Key, "\"f\\\"\""),
+                annotation(AnnotationType::String, "\"g\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn marks_known_values_and_numbers() {
+        let lines = highlight("[true, false, null, truthy, 0, -1.5, 2e10, 3.0E-2, 01, 1., -, 0x1]");
+        assert_eq!(
+            lines[0],
+            [
+                annotation(AnnotationType::KnownValue, "true"),
+                annotation(AnnotationType::KnownValue, "false"),
+                annotation(AnnotationType::KnownValue, "null"),
+                annotation(AnnotationType::Number, "0"),
+                annotation(AnnotationType::Number, "-1.5"),
+                annotation(AnnotationType::Number, "2e10"),
+                annotation(AnnotationType::Number, "3.0E-2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_string_is_an_error_only_on_its_line() {
+        let lines = highlight("{\"a\": \"b,\n\"c\": 1}");
+        assert_eq!(
+            lines[0],
+            [
+                annotation(AnnotationType::Key, "\"a\""),
+                annotation(AnnotationType::Error, "\"b,"),
+            ]
+        );
+        assert_eq!(
+            lines[1],
+            [
+                annotation(AnnotationType::Key, "\"c\""),
+                annotation(AnnotationType::Number, "1"),
+            ]
+        );
+        // 以反斜杠结尾的字符串同样没有闭合
+        assert_eq!(highlight("\"a\\\"")[0], [annotation(AnnotationType::Error, "\"a\\\"")]);
+    }
+
+    #[test]
+    fn marks_mismatched_brackets_across_lines() {
+        let lines = highlight("{\n  [1,\n  }\n]\n]");
+        // 第 2 行的 '}' 与未闭合的 '[' 不匹配，'[' 仍留待第 3 行闭合
+        assert_eq!(lines[2], [annotation(AnnotationType::Error, "}")]);
+        assert!(lines[3].is_empty());
+        // 最外层的 '{' 未闭合，']' 多余
+        assert_eq!(lines[4], [annotation(AnnotationType::Error, "]")]);
+    }
+}
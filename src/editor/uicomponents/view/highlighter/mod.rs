@@ -1,5 +1,23 @@
 use crate::editor::{Annotation, AnnotationType, FileType, Line};
 use crate::prelude::*;
+use super::Selection;
+use std::{fs, path::Path};
+
+mod brackethighlighter;
+use brackethighlighter::BracketHighlighter;
+
+mod carethighlighter;
+use carethighlighter::CaretHighlighter;
+
+mod configsyntaxhighlighter;
+use configsyntaxhighlighter::{ConfigSyntaxHighlighter, Grammar};
+
+mod jsonsyntaxhighlighter;
+use jsonsyntaxhighlighter::JsonSyntaxHighlighter;
+
+mod registry;
+use registry::{create_syntax_highlighter, HighlighterFactory};
+pub use registry::{file_type_for_extension, file_type_for_name, registered_file_types};
 
 mod rustsyntaxhighlighter;
 use rustsyntaxhighlighter::RustSyntaxHighlighter;
@@ -7,56 +25,285 @@ use rustsyntaxhighlighter::RustSyntaxHighlighter;
 mod searchresulthighlighter;
 use searchresulthighlighter::SearchResultHighlighter;
 
+#[cfg(feature = "spellcheck")]
+mod spellcheckhighlighter;
+#[cfg(feature = "spellcheck")]
+use spellcheckhighlighter::SpellCheckHighlighter;
+#[cfg(feature = "spellcheck")]
+pub use spellcheckhighlighter::load_dictionary;
+
+mod selectionhighlighter;
+use selectionhighlighter::SelectionHighlighter;
+
 mod syntaxhighlighter;
 use syntaxhighlighter::SyntaxHighlighter;
 
-fn create_syntax_highlighter(file_type: FileType) -> Option<Box<dyn SyntaxHighlighter>> {
-    match file_type {
-        FileType::Rust => Some(Box::<RustSyntaxHighlighter>::default()),
-        FileType::Text => None,
+mod tomlsyntaxhighlighter;
+use tomlsyntaxhighlighter::TomlSyntaxHighlighter;
+
+// 注册内置的语法高亮器，应在加载文件之前调用
+pub fn register_builtin_highlighters() {
+    Highlighter::register(
+        "rs",
+        FileType::Syntax("Rust"),
+        Box::new(|| Box::<RustSyntaxHighlighter>::default()),
+    );
+    Highlighter::register(
+        "json",
+        FileType::Syntax("JSON"),
+        Box::new(|| Box::<JsonSyntaxHighlighter>::default()),
+    );
+    Highlighter::register(
+        "toml",
+        FileType::Syntax("TOML"),
+        Box::new(|| Box::<TomlSyntaxHighlighter>::default()),
+    );
+}
+
+// 加载目录中所有 .toml 语法定义文件，并为其中列出的扩展名注册高亮器。
+// 返回无法读取或解析的文件及原因。
+pub fn register_grammar_files(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new(); // 目录不存在时视为没有自定义语法
+    };
+    let mut errors = Vec::new();
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        if !path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+        {
+            continue;
+        }
+        let file_name = path.file_name().map_or_else(String::new, |name| {
+            name.to_string_lossy().into_owned()
+        });
+        let grammar = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                toml::from_str::<Grammar>(&contents).map_err(|err| err.message().to_string())
+            });
+        match grammar {
+            Ok(grammar) => {
+                // 文件类型需要 'static 的名称，语法定义只在启动时加载一次，因此可以泄漏
+                let name: &'static str = Box::leak(grammar.name.clone().into_boxed_str());
+                for extension in &grammar.extensions {
+                    let grammar = grammar.clone();
+                    Highlighter::register(
+                        extension,
+                        FileType::Syntax(name),
+                        Box::new(move || Box::new(ConfigSyntaxHighlighter::new(grammar.clone()))),
+                    );
+                }
+            }
+            Err(err) => errors.push(format!("{file_name}: {err}")),
+        }
+    }
+    errors
+}
+
+// 在多次绘制之间保留的语法高亮结果。文档被编辑后从被修改的行开始失效，
+// 之后只需重新高亮该行及其后的行，而不是每次绘制都从文档开头重新计算。
+#[derive(Default)]
+pub struct SyntaxCache {
+    file_type: FileType,
+    highlighter: Option<Box<dyn SyntaxHighlighter>>,
+    highlighted_lines: LineIdx, // 结果仍然有效的行数，即 0..highlighted_lines
+}
+
+impl SyntaxCache {
+    fn new(file_type: FileType) -> Self {
+        Self {
+            file_type,
+            highlighter: create_syntax_highlighter(file_type),
+            highlighted_lines: 0,
+        }
+    }
+    // 文件类型改变（手动设置或另存为其他扩展名）时重新创建高亮器
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        if self.file_type != file_type {
+            *self = Self::new(file_type);
+        }
+    }
+    pub fn invalidate_from(&mut self, idx: LineIdx) {
+        if idx < self.highlighted_lines {
+            if let Some(highlighter) = &mut self.highlighter {
+                highlighter.invalidate_from(idx);
+            }
+            self.highlighted_lines = idx;
+        }
+    }
+    // 已有有效结果的行直接跳过
+    fn highlight(&mut self, idx: LineIdx, line: &Line) {
+        debug_assert!(idx <= self.highlighted_lines);
+        if idx == self.highlighted_lines {
+            if let Some(highlighter) = &mut self.highlighter {
+                highlighter.highlight(idx, line);
+            }
+            self.highlighted_lines = self.highlighted_lines.saturating_add(1);
+        }
+    }
+    fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.highlighter
+            .as_ref()
+            .and_then(|highlighter| highlighter.get_annotations(idx))
     }
 }
 
 #[derive(Default)]
 pub struct Highlighter<'a> {
-    syntax_highlighter: Option<Box<dyn SyntaxHighlighter>>,
+    syntax_cache: SyntaxCache,
     search_result_highlighter: Option<SearchResultHighlighter<'a>>,
+    selection_highlighter: Option<SelectionHighlighter>,
+    caret_highlighter: Option<CaretHighlighter<'a>>,
+    bracket_highlighter: Option<BracketHighlighter>,
+    #[cfg(feature = "spellcheck")]
+    spell_check_highlighter: Option<SpellCheckHighlighter>,
 }
 
 impl<'a> Highlighter<'a> {
+    // 为扩展名（不含点，忽略大小写）注册文件类型及其语法高亮器，之后打开该扩展名的文件时
+    // 由 factory 为每个文档创建新的高亮器。内置语言和语法定义文件都通过这里注册，
+    // 添加新语言不需要修改其他代码。重复注册同一扩展名会覆盖之前的注册。
+    pub fn register(extension: &str, file_type: FileType, factory: HighlighterFactory) {
+        registry::register(extension, file_type, factory);
+    }
+    // 语法高亮使用调用者保留的 syntax_cache，绘制结束后通过 into_syntax_cache 交还
     pub fn new(
         matched_word: Option<&'a str>,
         selected_match: Option<Location>,
-        file_type: FileType,
+        syntax_cache: SyntaxCache,
+        selections: Vec<Selection>,
+        secondary_carets: &'a [Location],
     ) -> Self {
         let search_result_highlighter = matched_word
             .map(|matched_word| SearchResultHighlighter::new(matched_word, selected_match));
+        #[cfg(feature = "spellcheck")]
+        let file_type = syntax_cache.file_type;
         Self {
-            syntax_highlighter: create_syntax_highlighter(file_type),
+            syntax_cache,
             search_result_highlighter,
+            selection_highlighter: (!selections.is_empty())
+                .then(|| SelectionHighlighter::new(selections)),
+            caret_highlighter: (!secondary_carets.is_empty())
+                .then(|| CaretHighlighter::new(secondary_carets)),
+            bracket_highlighter: None,
+            #[cfg(feature = "spellcheck")]
+            spell_check_highlighter: SpellCheckHighlighter::for_file_type(file_type),
         }
     }
+    // 仅包含语法高亮的高亮器，用于在绘制之外查询行的语法注解
+    pub fn for_syntax(file_type: FileType) -> Self {
+        Self {
+            syntax_cache: SyntaxCache::new(file_type),
+            ..Self::default()
+        }
+    }
+    // 只做语法高亮，沿用调用者保留的 syntax_cache，用于在绘制之外按需高亮
+    pub fn from_syntax_cache(syntax_cache: SyntaxCache) -> Self {
+        Self {
+            syntax_cache,
+            ..Self::default()
+        }
+    }
+    pub fn into_syntax_cache(self) -> SyntaxCache {
+        self.syntax_cache
+    }
+    // 已有有效语法高亮结果的行数，即 0..syntax_highlighted_lines
+    pub const fn syntax_highlighted_lines(&self) -> LineIdx {
+        self.syntax_cache.highlighted_lines
+    }
+    // 已高亮的行的语法注解，不含搜索、选区等其他注解
+    pub fn syntax_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.syntax_cache.get_annotations(idx)
+    }
+    // 标记光标处的括号及其配对括号（以行和字节索引给出）
+    pub fn set_brackets(
+        &mut self,
+        brackets: &[(LineIdx, ByteIdx)],
+        annotation_type: AnnotationType,
+    ) {
+        self.bracket_highlighter = Some(BracketHighlighter::new(brackets, annotation_type));
+    }
+    // 仅包含拼写检查的高亮器，用于查找拼写错误
+    #[cfg(feature = "spellcheck")]
+    pub fn for_spell_check(file_type: FileType) -> Self {
+        Self {
+            spell_check_highlighter: SpellCheckHighlighter::for_file_type(file_type),
+            ..Self::default()
+        }
+    }
+    // 返回指定行的注解。调用前需已按顺序对 0..=idx 的各行调用过 highlight，
+    // 否则多行注释等跨行状态不完整，未高亮的行返回空列表。
     pub fn get_annotations(&self, idx: LineIdx) -> Vec<Annotation> {
         let mut result = Vec::new();
 
-        if let Some(syntax_highlighter) = &self.syntax_highlighter {
-            if let Some(annotations) = syntax_highlighter.get_annotations(idx) {
+        // 光标和选区注解优先，覆盖语法高亮
+        if let Some(caret_highlighter) = &self.caret_highlighter {
+            if let Some(annotations) = caret_highlighter.get_annotations(idx) {
+                result.extend(annotations.iter().copied());
+            }
+        }
+        if let Some(selection_highlighter) = &self.selection_highlighter {
+            if let Some(annotations) = selection_highlighter.get_annotations(idx) {
+                result.extend(annotations.iter().copied());
+            }
+        }
+        if let Some(bracket_highlighter) = &self.bracket_highlighter {
+            if let Some(annotations) = bracket_highlighter.get_annotations(idx) {
                 result.extend(annotations.iter().copied());
             }
         }
+        if let Some(annotations) = self.syntax_cache.get_annotations(idx) {
+            result.extend(annotations.iter().copied());
+        }
         if let Some(search_result_highlighter) = &self.search_result_highlighter {
             if let Some(annotations) = search_result_highlighter.get_annotations(idx) {
                 result.extend(annotations.iter().copied());
             }
         }
+        #[cfg(feature = "spellcheck")]
+        if let Some(spell_check_highlighter) = &self.spell_check_highlighter {
+            if let Some(annotations) = spell_check_highlighter.get_annotations(idx) {
+                result.extend(annotations.iter().copied());
+            }
+        }
         result
     }
-    pub fn highlight(&mut self, idx: LineIdx, line: &Line) {
-        if let Some(syntax_highlighter) = &mut self.syntax_highlighter {
-            syntax_highlighter.highlight(idx, line);
+    // 只做语法高亮，例如查找配对括号时按需高亮可见区域之外的行；超长行按空行处理
+    pub fn advance_syntax(&mut self, idx: LineIdx, line: &Line) {
+        if line.is_very_long() {
+            self.syntax_cache.highlight(idx, &Line::default());
+        } else {
+            self.syntax_cache.highlight(idx, line);
         }
+    }
+    pub fn highlight(&mut self, idx: LineIdx, line: &Line) {
+        self.advance(idx, line);
         if let Some(search_result_highlighter) = &mut self.search_result_highlighter {
             search_result_highlighter.highlight(idx, line);
         }
+        if let Some(selection_highlighter) = &mut self.selection_highlighter {
+            selection_highlighter.highlight(idx, line);
+        }
+        if let Some(caret_highlighter) = &mut self.caret_highlighter {
+            caret_highlighter.highlight(idx, line);
+        }
+    }
+    // 只高亮依赖前面各行状态的语法高亮和拼写检查，用于不显示的行：
+    // 搜索结果、选区和光标注解只与本行有关，不显示时无需计算
+    pub fn advance(&mut self, idx: LineIdx, line: &Line) {
+        // 拼写检查每次重绘都从文档开头重新计算，超长行的语法高亮和拼写检查按空行处理以保持响应
+        let empty_line;
+        let content_line = if line.is_very_long() {
+            empty_line = Line::default();
+            &empty_line
+        } else {
+            line
+        };
+        self.advance_syntax(idx, content_line);
+        #[cfg(feature = "spellcheck")]
+        if let Some(spell_check_highlighter) = &mut self.spell_check_highlighter {
+            spell_check_highlighter.highlight(idx, content_line);
+        }
     }
 }
@@ -0,0 +1,86 @@
+use std::sync::RwLock;
+
+use super::SyntaxHighlighter;
+use crate::editor::FileType;
+
+// 创建语法高亮器的工厂。注册表是全局的 static，因此工厂必须是 'static 且 Send + Sync：
+// 闭包只能捕获自身拥有的数据（例如 move 进来的语法定义），不能借用局部变量。
+// 返回的 Box<dyn SyntaxHighlighter> 同样隐含 'static，高亮器的状态必须自己持有，
+// 不能引用文档或其他高亮器的数据；需要的行内容都通过 highlight 的参数传入。
+pub type HighlighterFactory = Box<dyn Fn() -> Box<dyn SyntaxHighlighter> + Send + Sync>;
+
+struct Registration {
+    extension: String,
+    file_type: FileType,
+    factory: HighlighterFactory,
+}
+
+// 文件扩展名到文件类型和语法高亮器工厂的注册表
+static REGISTRY: RwLock<Vec<Registration>> = RwLock::new(Vec::new());
+
+// 为指定扩展名（不含点，忽略大小写）注册文件类型及其语法高亮器。
+// 对同一扩展名的重复注册会覆盖之前的注册。
+pub fn register(extension: &str, file_type: FileType, factory: HighlighterFactory) {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.retain(|registration| !registration.extension.eq_ignore_ascii_case(extension));
+        registry.push(Registration {
+            extension: extension.to_string(),
+            file_type,
+            factory,
+        });
+    }
+}
+
+// 根据扩展名查找已注册的文件类型
+pub fn file_type_for_extension(extension: &str) -> Option<FileType> {
+    REGISTRY.read().ok().and_then(|registry| {
+        registry
+            .iter()
+            .find(|registration| registration.extension.eq_ignore_ascii_case(extension))
+            .map(|registration| registration.file_type)
+    })
+}
+
+// 根据名称查找文件类型：可以是 "text"、已注册语言的显示名称或扩展名，均忽略大小写
+pub fn file_type_for_name(name: &str) -> Option<FileType> {
+    if name.eq_ignore_ascii_case("text") {
+        return Some(FileType::Text);
+    }
+    REGISTRY.read().ok().and_then(|registry| {
+        registry
+            .iter()
+            .find(|registration| match registration.file_type {
+                FileType::Syntax(language) => language.eq_ignore_ascii_case(name),
+                FileType::Text => false,
+            })
+            .or_else(|| {
+                registry
+                    .iter()
+                    .find(|registration| registration.extension.eq_ignore_ascii_case(name))
+            })
+            .map(|registration| registration.file_type)
+    })
+}
+
+// 返回所有可用的文件类型（按注册顺序，不重复）
+pub fn registered_file_types() -> Vec<FileType> {
+    let mut file_types = vec![FileType::Text];
+    if let Ok(registry) = REGISTRY.read() {
+        for registration in registry.iter() {
+            if !file_types.contains(&registration.file_type) {
+                file_types.push(registration.file_type);
+            }
+        }
+    }
+    file_types
+}
+
+// 为文件类型创建已注册的语法高亮器
+pub fn create_syntax_highlighter(file_type: FileType) -> Option<Box<dyn SyntaxHighlighter>> {
+    REGISTRY.read().ok().and_then(|registry| {
+        registry
+            .iter()
+            .find(|registration| registration.file_type == file_type)
+            .map(|registration| (registration.factory)())
+    })
+}
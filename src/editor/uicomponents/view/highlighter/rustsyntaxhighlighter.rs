@@ -63,13 +63,22 @@ const TYPES: [&str; 22] = [
 
 const KNOWN_VALUES: [&str; 6] = ["Some", "None", "true", "false", "Ok", "Err"];
 
+// 跨行的高亮状态：多行注释的嵌套层数，以及是否位于跨行字符串中
+#[derive(Default, Clone, Copy)]
+struct CrossLineState {
+    ml_comment_balance: usize,
+    in_ml_string: bool,
+}
+
 #[derive(Default)]
 pub struct RustSyntaxHighlighter {
     highlights: Vec<Vec<Annotation>>,
+    entering_states: Vec<CrossLineState>, // 每行开始时的跨行状态，与 highlights 一一对应
     ml_comment_balance: usize,
     in_ml_string: bool,
 }
 impl RustSyntaxHighlighter {
+
     fn annotate_ml_comment(&mut self, string: &str) -> Option<Annotation> {
         let mut chars = string.char_indices().peekable();
 
@@ -157,6 +166,10 @@ impl RustSyntaxHighlighter {
 impl SyntaxHighlighter for RustSyntaxHighlighter {
     fn highlight(&mut self, idx: LineIdx, line: &Line) {
         debug_assert_eq!(idx, self.highlights.len());
+        self.entering_states.push(CrossLineState {
+            ml_comment_balance: self.ml_comment_balance,
+            in_ml_string: self.in_ml_string,
+        });
         let mut result = Vec::new();
         let mut iterator = line.split_word_bound_indices().peekable();
         if let Some(annotation) = self.initial_annotation(line) {
@@ -191,6 +204,15 @@ impl SyntaxHighlighter for RustSyntaxHighlighter {
     fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
         self.highlights.get(idx)
     }
+
+    fn invalidate_from(&mut self, idx: LineIdx) {
+        if let Some(&state) = self.entering_states.get(idx) {
+            self.ml_comment_balance = state.ml_comment_balance;
+            self.in_ml_string = state.in_ml_string;
+            self.entering_states.truncate(idx);
+            self.highlights.truncate(idx);
+        }
+    }
 }
 
 fn annotate_next_word<F>(
@@ -272,7 +294,7 @@ fn annotate_single_line_comment(string: &str) -> Option<Annotation> {
     None
 }
 
-fn is_valid_number(word: &str) -> bool {
+pub fn is_valid_number(word: &str) -> bool {
     if word.is_empty() {
         return false;
     }
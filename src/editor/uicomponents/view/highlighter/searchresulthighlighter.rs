@@ -33,12 +33,15 @@ impl<'a> SearchResultHighlighter<'a> {
                 });
             });
     }
-    fn highlight_selected_match(&self, result: &mut Vec<Annotation>) {
+    fn highlight_selected_match(&self, line: &Line, result: &mut Vec<Annotation>) {
         if let Some(selected_match) = self.selected_match {
             if self.matched_word.is_empty() {
                 return;
             }
-            let start = selected_match.grapheme_idx;
+            // 注解使用字节索引，需要把匹配的字素索引转换过来
+            let start = line
+                .byte_range(selected_match.grapheme_idx..selected_match.grapheme_idx)
+                .start;
             result.push(Annotation {
                 annotation_type: AnnotationType::SelectedMatch,
                 start,
@@ -54,7 +57,7 @@ impl<'a> SyntaxHighlighter for SearchResultHighlighter<'a> {
         self.highlight_matched_words(line, &mut result);
         if let Some(selected_match) = self.selected_match {
             if selected_match.line_idx == idx {
-                self.highlight_selected_match(&mut result);
+                self.highlight_selected_match(line, &mut result);
             }
         }
         self.highlights.insert(idx, result);
@@ -62,4 +65,8 @@ impl<'a> SyntaxHighlighter for SearchResultHighlighter<'a> {
     fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
         self.highlights.get(&idx)
     }
+
+    fn invalidate_from(&mut self, idx: LineIdx) {
+        self.highlights.retain(|&line_idx, _| line_idx < idx);
+    }
 }
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use super::{syntaxhighlighter::SyntaxHighlighter, Annotation, AnnotationType, Line, Selection};
+use crate::prelude::*;
+
+pub struct SelectionHighlighter {
+    selections: Vec<Selection>, // 主选区，以及其他光标处的选区
+    highlights: HashMap<LineIdx, Vec<Annotation>>,
+}
+
+impl SelectionHighlighter {
+    pub fn new(selections: Vec<Selection>) -> Self {
+        Self {
+            selections,
+            highlights: HashMap::new(),
+        }
+    }
+}
+
+impl SyntaxHighlighter for SelectionHighlighter {
+    fn highlight(&mut self, idx: LineIdx, line: &Line) {
+        let mut result = Vec::new();
+        for selection in &self.selections {
+            let Some(range) = selection.grapheme_range(idx, line) else {
+                continue;
+            };
+            let byte_range = line.byte_range(range);
+            if byte_range.start < byte_range.end {
+                result.push(Annotation {
+                    annotation_type: AnnotationType::Selection,
+                    start: byte_range.start,
+                    end: byte_range.end,
+                });
+            }
+        }
+        self.highlights.insert(idx, result);
+    }
+    fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.highlights.get(&idx)
+    }
+
+    fn invalidate_from(&mut self, idx: LineIdx) {
+        self.highlights.retain(|&line_idx, _| line_idx < idx);
+    }
+}
@@ -0,0 +1,101 @@
+use std::{collections::HashSet, fs, io::ErrorKind, path::Path, sync::OnceLock};
+
+use super::{Annotation, AnnotationType, FileType, Line, SyntaxHighlighter};
+use crate::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+// 拼写检查使用的单词表，启动时加载一次，全部转换为小写
+static DICTIONARY: OnceLock<HashSet<String>> = OnceLock::new();
+
+// 从文件加载单词表（每行一个单词，忽略空行和以 # 开头的行）。
+// 文件不存在时不进行拼写检查，也不视为错误。
+pub fn load_dictionary(path: &Path) -> Result<(), String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.to_string()),
+    };
+    let words: HashSet<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty() && !word.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect();
+    let _ = DICTIONARY.set(words);
+    Ok(())
+}
+
+// 为纯文本和 Markdown 中不在单词表里的英文单词添加 Misspelled 注解。
+// Markdown 中跳过行内代码（反引号之间）和围栏代码块。
+pub struct SpellCheckHighlighter {
+    dictionary: &'static HashSet<String>,
+    is_markdown: bool,
+    in_code_block: bool,
+    entering_in_code_block: Vec<bool>, // 每行开始时是否位于围栏代码块中，与 highlights 一一对应
+    highlights: Vec<Vec<Annotation>>,
+}
+
+impl SpellCheckHighlighter {
+    // 没有加载单词表或文件类型不是文本时返回 None
+    pub fn for_file_type(file_type: FileType) -> Option<Self> {
+        let is_markdown = match file_type {
+            FileType::Text => false,
+            FileType::Syntax(name) if name.eq_ignore_ascii_case("Markdown") => true,
+            FileType::Syntax(_) => return None,
+        };
+        let dictionary = DICTIONARY.get().filter(|dictionary| !dictionary.is_empty())?;
+        Some(Self {
+            dictionary,
+            is_markdown,
+            in_code_block: false,
+            entering_in_code_block: Vec::new(),
+            highlights: Vec::new(),
+        })
+    }
+
+    fn is_misspelled(&self, word: &str) -> bool {
+        // 只检查由字母（可含撇号）组成的单词，数字、标点和 CJK 文本均跳过
+        word.starts_with(|char: char| char.is_ascii_alphabetic())
+            && word
+                .chars()
+                .all(|char| char.is_ascii_alphabetic() || char == '\'')
+            && !self.dictionary.contains(&word.to_lowercase())
+    }
+}
+
+impl SyntaxHighlighter for SpellCheckHighlighter {
+    fn highlight(&mut self, idx: LineIdx, line: &Line) {
+        debug_assert_eq!(idx, self.highlights.len());
+        self.entering_in_code_block.push(self.in_code_block);
+        let mut result = Vec::new();
+        if self.is_markdown && line.trim_start().starts_with("```") {
+            self.in_code_block = !self.in_code_block; // 围栏行本身也不检查
+        } else if !self.in_code_block {
+            let mut in_code_span = false;
+            for (start, word) in line.split_word_bound_indices() {
+                if self.is_markdown && word.contains('`') {
+                    in_code_span = !in_code_span;
+                } else if !in_code_span && self.is_misspelled(word) {
+                    result.push(Annotation {
+                        annotation_type: AnnotationType::Misspelled,
+                        start,
+                        end: start.saturating_add(word.len()),
+                    });
+                }
+            }
+        }
+        self.highlights.push(result);
+    }
+
+    fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.highlights.get(idx)
+    }
+
+    fn invalidate_from(&mut self, idx: LineIdx) {
+        if let Some(&in_code_block) = self.entering_in_code_block.get(idx) {
+            self.in_code_block = in_code_block;
+            self.entering_in_code_block.truncate(idx);
+            self.highlights.truncate(idx);
+        }
+    }
+}
@@ -1,7 +1,12 @@
 use super::Annotation;
 use super::Line;
 use crate::prelude::*;
+// 逐行高亮的约定：highlight 必须按行号顺序调用（跨行状态依赖前面的行），
+// 即每次调用的 idx 等于已高亮的行数；invalidate_from 之后从 idx 行起重新按顺序调用。
 pub trait SyntaxHighlighter {
     fn highlight(&mut self, idx: LineIdx, line: &Line);
     fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>>;
+    // 丢弃 idx 行及之后的结果，并把跨行状态恢复为进入 idx 行时的状态。
+    // idx 不小于已高亮的行数时不做任何事。
+    fn invalidate_from(&mut self, idx: LineIdx);
 }
@@ -0,0 +1,320 @@
+use super::{Annotation, AnnotationType, Line, SyntaxHighlighter};
+use crate::prelude::*;
+
+const ML_BASIC_STRING: &str = "\"\"\"";
+const ML_LITERAL_STRING: &str = "'''";
+
+// 跨行的高亮状态：所在多行字符串的结束标记，以及尚未闭合的括号（数组可以跨行）
+#[derive(Default, Clone)]
+struct CrossLineState {
+    ml_string: Option<&'static str>,
+    open_brackets: Vec<char>,
+}
+
+#[derive(Default)]
+pub struct TomlSyntaxHighlighter {
+    highlights: Vec<Vec<Annotation>>,
+    entering_states: Vec<CrossLineState>, // 每行开始时的跨行状态，与 highlights 一一对应
+    state: CrossLineState,
+}
+
+impl TomlSyntaxHighlighter {
+    // 顶层和内联表中，行首和逗号之后是键；数组中是值
+    fn expects_key(&self) -> bool {
+        self.state.open_brackets.last().is_none_or(|&bracket| bracket == '{')
+    }
+
+    // 处理从上一行延续下来的多行字符串，返回字符串之后的位置；字符串未结束时返回 None
+    fn annotate_ml_string_end(
+        &mut self,
+        line: &Line,
+        result: &mut Vec<Annotation>,
+    ) -> Option<ByteIdx> {
+        let delimiter = self.state.ml_string?;
+        let end = closing_delimiter_end(line, delimiter);
+        result.push(Annotation {
+            annotation_type: AnnotationType::String,
+            start: 0,
+            end: end.unwrap_or(line.len()),
+        });
+        if end.is_some() {
+            self.state.ml_string = None;
+        }
+        end
+    }
+}
+
+impl SyntaxHighlighter for TomlSyntaxHighlighter {
+    fn highlight(&mut self, idx: LineIdx, line: &Line) {
+        debug_assert_eq!(idx, self.highlights.len());
+        self.entering_states.push(self.state.clone());
+        let mut result = Vec::new();
+        let mut idx = 0;
+        if self.state.ml_string.is_some() {
+            match self.annotate_ml_string_end(line, &mut result) {
+                Some(end) => idx = end,
+                None => {
+                    self.highlights.push(result);
+                    return;
+                }
+            }
+        }
+        let mut expecting_key = idx == 0 && self.expects_key();
+        // [table] 和 [[array.of.tables]] 表头整体高亮
+        let indent = line.len().saturating_sub(line.trim_start().len());
+        if idx == 0 && self.state.open_brackets.is_empty() && line[indent..].starts_with('[') {
+            let closing = if line[indent..].starts_with("[[") { "]]" } else { "]" };
+            idx = line[indent..]
+                .find(closing)
+                .map_or(line.len(), |end| indent.saturating_add(end).saturating_add(closing.len()));
+            result.push(Annotation {
+                annotation_type: AnnotationType::Type,
+                start: indent,
+                end: idx,
+            });
+            expecting_key = false;
+        }
+        while let Some(character) = line[idx..].chars().next() {
+            let rest = &line[idx..];
+            let (annotation_type, len) = match character {
+                '#' => (Some(AnnotationType::Comment), rest.len()),
+                '"' | '\'' => {
+                    let ml_delimiter = [ML_BASIC_STRING, ML_LITERAL_STRING]
+                        .into_iter()
+                        .find(|&delimiter| rest.starts_with(delimiter));
+                    if let Some(delimiter) = ml_delimiter {
+                        let len = closing_delimiter_end(&rest[delimiter.len()..], delimiter)
+                            .map(|end| end.saturating_add(delimiter.len()));
+                        if len.is_none() {
+                            self.state.ml_string = Some(delimiter);
+                        }
+                        (Some(AnnotationType::String), len.unwrap_or(rest.len()))
+                    } else {
+                        let delimiter = if character == '"' { "\"" } else { "'" };
+                        match closing_delimiter_end(&rest[1..], delimiter) {
+                            Some(end) => {
+                                let annotation_type = if expecting_key {
+                                    AnnotationType::Key
+                                } else {
+                                    AnnotationType::String
+                                };
+                                (Some(annotation_type), end.saturating_add(1))
+                            }
+                            // 单行字符串未闭合：到行尾为止标记为错误，不影响之后的行
+                            None => (Some(AnnotationType::Error), rest.len()),
+                        }
+                    }
+                }
+                '{' | '[' => {
+                    self.state.open_brackets.push(character);
+                    expecting_key = character == '{';
+                    (Some(AnnotationType::Punctuation), 1)
+                }
+                '}' | ']' => {
+                    self.state.open_brackets.pop();
+                    (Some(AnnotationType::Punctuation), 1)
+                }
+                ',' => {
+                    expecting_key = self.expects_key();
+                    (Some(AnnotationType::Punctuation), 1)
+                }
+                '=' => {
+                    expecting_key = false;
+                    (Some(AnnotationType::Punctuation), 1)
+                }
+                '.' if expecting_key => (Some(AnnotationType::Punctuation), 1), // 点分隔的键
+                // '+' 只能出现在值（带符号的数字）中，不是裸键的字符
+                _ if character.is_ascii_alphanumeric()
+                    || matches!(character, '_' | '-')
+                    || (character == '+' && !expecting_key) =>
+                {
+                    let len = word_len(rest, expecting_key);
+                    let word = &rest[..len];
+                    let annotation_type = if expecting_key {
+                        Some(AnnotationType::Key)
+                    } else if matches!(word, "true" | "false") {
+                        Some(AnnotationType::KnownValue)
+                    } else if is_toml_number(word) || is_toml_datetime(word) {
+                        Some(AnnotationType::Number)
+                    } else {
+                        None
+                    };
+                    (annotation_type, len)
+                }
+                _ => (None, character.len_utf8()),
+            };
+            let end = idx.saturating_add(len);
+            if let Some(annotation_type) = annotation_type {
+                result.push(Annotation {
+                    annotation_type,
+                    start: idx,
+                    end,
+                });
+            }
+            idx = end;
+        }
+        self.highlights.push(result);
+    }
+
+    fn get_annotations(&self, idx: LineIdx) -> Option<&Vec<Annotation>> {
+        self.highlights.get(idx)
+    }
+
+    fn invalidate_from(&mut self, idx: LineIdx) {
+        if let Some(state) = self.entering_states.get_mut(idx) {
+            self.state = std::mem::take(state);
+            self.entering_states.truncate(idx);
+            self.highlights.truncate(idx);
+        }
+    }
+}
+
+// 在字符串内部查找结束标记，返回结束标记之后的位置。基本字符串（双引号）支持反斜杠转义，
+// 字面量字符串（单引号）不支持。多行字符串的结束标记后最多还可以紧跟两个引号
+fn closing_delimiter_end(string: &str, delimiter: &str) -> Option<ByteIdx> {
+    let escapes = delimiter.starts_with('"');
+    let mut chars = string.char_indices();
+    while let Some((idx, character)) = chars.next() {
+        if escapes && character == '\\' {
+            chars.next(); // 跳过转义字符
+        } else if string[idx..].starts_with(delimiter) {
+            let end = idx.saturating_add(delimiter.len());
+            let extra_quotes = if delimiter.len() > 1 {
+                string[end..].chars().take(2).take_while(|&next| next == character).count()
+            } else {
+                0
+            };
+            return Some(end.saturating_add(extra_quotes));
+        }
+    }
+    None
+}
+
+// 键或值的字节长度。裸键只包含字母、数字、下划线和连字符；
+// 值还可以包含数字和日期时间中的 + . : 以及日期和时间之间的空格
+fn word_len(string: &str, key: bool) -> ByteIdx {
+    let is_word_char = |character: char| {
+        character.is_ascii_alphanumeric()
+            || matches!(character, '_' | '-')
+            || (!key && matches!(character, '+' | '.' | ':'))
+    };
+    let len = string.find(|character| !is_word_char(character)).unwrap_or(string.len());
+    if !key && is_toml_datetime(&string[..len]) {
+        if let Some(time) = string[len..]
+            .strip_prefix(' ')
+            .filter(|time| time.starts_with(|character: char| character.is_ascii_digit()))
+        {
+            return len.saturating_add(1).saturating_add(word_len(time, false));
+        }
+    }
+    len
+}
+
+// TOML 数字：可带符号的整数和浮点数（允许下划线分隔），0x/0o/0b 整数，以及 inf 和 nan
+fn is_toml_number(word: &str) -> bool {
+    let unsigned = word.strip_prefix(['+', '-']).unwrap_or(word);
+    if matches!(unsigned, "inf" | "nan") {
+        return true;
+    }
+    let radix = match unsigned.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        let digits = &unsigned[2..];
+        return unsigned.len() == word.len()
+            && !digits.is_empty()
+            && digits.chars().all(|character| character == '_' || character.is_digit(radix));
+    }
+    let digits = unsigned.replace('_', "");
+    digits.starts_with(|character: char| character.is_ascii_digit())
+        && digits.parse::<f64>().is_ok()
+}
+
+// 日期（1979-05-27）、时间（07:32:00）或日期时间（可以用 T 或空格分隔），只检查大致形状
+fn is_toml_datetime(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    let is_date = bytes.get(4) == Some(&b'-') && bytes.get(7) == Some(&b'-');
+    let is_time = bytes.get(2) == Some(&b':') && bytes.get(5) == Some(&b':');
+    (is_date || is_time)
+        && word.chars().all(|character| {
+            character.is_ascii_digit()
+                || matches!(character, '-' | ':' | '.' | '+' | ' ' | 'T' | 't' | 'Z' | 'z')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnnotationType, Line, SyntaxHighlighter, TomlSyntaxHighlighter};
+
+    // 逐行高亮，返回每行的 (注解类型, 注解文本)
+    fn highlight(text: &str) -> Vec<Vec<(AnnotationType, String)>> {
+        let mut highlighter = TomlSyntaxHighlighter::default();
+        let lines: Vec<Line> = text.lines().map(Line::from).collect();
+        for (idx, line) in lines.iter().enumerate() {
+            highlighter.highlight(idx, line);
+        }
+        lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                highlighter
+                    .get_annotations(idx)
+                    .into_iter()
+                    .flatten()
+                    .map(|annotation| {
+                        let text = line[annotation.start..annotation.end].to_string();
+                        (annotation.annotation_type, text)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn annotation(annotation_type: AnnotationType, text: &str) -> (AnnotationType, String) {
+        (annotation_type, text.to_string())
+    }
+
+    #[test]
+    fn plus_where_a_key_is_expected_advances() {
+        assert!(highlight("+")[0].is_empty());
+        let lines = highlight("a = { +, b = +1 }");
+        assert!(lines[0].contains(&annotation(AnnotationType::Key, "b")));
+        assert!(lines[0].contains(&annotation(AnnotationType::Number, "+1")));
+    }
+
+    #[test]
+    fn hash_inside_strings_is_not_a_comment() {
+        let lines = highlight(concat!(
+            "a = \"x # y\" # c\n",
+            "b = 'x # y'\n",
+            "c = \"\"\"\n",
+            "# not a comment\n",
+            "\"\"\" # c\n",
+            "d = '''x\n",
+            "# y'''\n",
+        ));
+        assert_eq!(
+            lines[0],
+            [
+                annotation(AnnotationType::Key, "a"),
+                annotation(AnnotationType::Punctuation, "="),
+                annotation(AnnotationType::String, "\"x # y\""),
+                annotation(AnnotationType::Comment, "# c"),
+            ]
+        );
+        assert!(lines[1].contains(&annotation(AnnotationType::String, "'x # y'")));
+        assert_eq!(lines[3], [annotation(AnnotationType::String, "# not a comment")]);
+        assert_eq!(
+            lines[4],
+            [
+                annotation(AnnotationType::String, "\"\"\""),
+                annotation(AnnotationType::Comment, "# c"),
+            ]
+        );
+        assert!(lines[5].contains(&annotation(AnnotationType::String, "'''x")));
+        assert_eq!(lines[6], [annotation(AnnotationType::String, "# y'''")]);
+    }
+}
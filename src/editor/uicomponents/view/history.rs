@@ -0,0 +1,103 @@
+use super::buffer::{Buffer, Change};
+use crate::prelude::*;
+use std::collections::VecDeque;
+
+// 一个撤销步骤：一条命令（或一串连续输入的字符）造成的全部修改，以及修改前后的光标位置
+struct Transaction {
+    changes: Vec<Change>,
+    caret_before: Location,
+    caret_after: Location,
+}
+
+impl Transaction {
+    // 按相反的顺序撤销各次修改，得到能够反过来恢复它的步骤
+    fn revert(self, buffer: &mut Buffer) -> Self {
+        let changes = self
+            .changes
+            .into_iter()
+            .rev()
+            .map(|change| buffer.revert(change))
+            .collect();
+        Self {
+            changes,
+            caret_before: self.caret_after,
+            caret_after: self.caret_before,
+        }
+    }
+}
+
+// 有上限的撤销 / 重做历史。每条命令执行完后由 commit 收集缓冲区的修改
+#[derive(Default)]
+pub struct History {
+    undo_stack: VecDeque<Transaction>,
+    redo_stack: Vec<Transaction>,
+    limit: usize,
+    caret: Location, // 上一条命令执行后的光标位置，即下一个步骤修改前的光标位置
+    can_merge: bool, // 上一个步骤是连续输入的字符，且之后没有其他命令
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    // 换成新的文档时清空历史，保留上限
+    pub fn clear(&mut self) {
+        *self = Self::new(self.limit);
+    }
+
+    // 收集缓冲区自上次调用以来的修改，作为一个步骤记入历史。is_typing 表示该命令是输入字符，
+    // 紧接在另一次输入之后时与之合并为同一个步骤
+    pub fn commit(&mut self, buffer: &mut Buffer, caret: Location, is_typing: bool) {
+        let changes = buffer.take_changes();
+        let caret_before = self.caret;
+        self.caret = caret;
+        if changes.is_empty() {
+            self.can_merge = false;
+            return;
+        }
+        self.redo_stack.clear();
+        if let Some(last) = self
+            .undo_stack
+            .back_mut()
+            .filter(|last| is_typing && self.can_merge && last.caret_after == caret_before)
+        {
+            last.changes.extend(changes);
+            last.caret_after = caret;
+            return;
+        }
+        self.can_merge = is_typing;
+        self.undo_stack.push_back(Transaction {
+            changes,
+            caret_before,
+            caret_after: caret,
+        });
+        while self.undo_stack.len() > self.limit {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    // 撤销最近的一个步骤，返回应当恢复的光标位置；没有可撤销的步骤时返回 None
+    pub fn undo(&mut self, buffer: &mut Buffer) -> Option<Location> {
+        let transaction = self.undo_stack.pop_back()?.revert(buffer);
+        self.finish(transaction.caret_after);
+        self.redo_stack.push(transaction);
+        Some(self.caret)
+    }
+
+    // 重做最近撤销的一个步骤，返回应当恢复的光标位置；没有可重做的步骤时返回 None
+    pub fn redo(&mut self, buffer: &mut Buffer) -> Option<Location> {
+        let transaction = self.redo_stack.pop()?.revert(buffer);
+        self.finish(transaction.caret_after);
+        self.undo_stack.push_back(transaction);
+        Some(self.caret)
+    }
+
+    fn finish(&mut self, caret: Location) {
+        self.caret = caret;
+        self.can_merge = false;
+    }
+}
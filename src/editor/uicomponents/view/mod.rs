@@ -1,19 +1,41 @@
-use std::{cmp::min, io::Error};
+use std::{
+    cmp::{max, min, Reverse},
+    fs::File,
+    io::{BufWriter, Error, Read, Write},
+    ops::Range,
+    path::Path,
+};
 
 use crate::editor::RowIdx;
 use crate::prelude::*;
 
 use crate::editor::{
     command::{Edit, Move},
-    DocumentStatus, Line, Terminal,
+    Annotation, AnnotatedString, AnnotationType, DocumentStatistics, DocumentStatus, FileType,
+    IndentStyle, Line, Terminal,
 };
 use super::UIComponent;
 
 mod highlighter;
-use highlighter::Highlighter;
+use highlighter::{Highlighter, SyntaxCache};
+pub use highlighter::{
+    file_type_for_name, register_builtin_highlighters, register_grammar_files,
+    registered_file_types,
+};
+#[cfg(feature = "spellcheck")]
+pub use highlighter::load_dictionary;
 
 mod buffer;
-use buffer::Buffer;
+pub use buffer::Buffer;
+use buffer::LineChange;
+
+#[cfg(feature = "git")]
+mod githunks;
+#[cfg(feature = "git")]
+use githunks::GitHunks;
+
+mod history;
+use history::History;
 
 mod fileinfo;
 use fileinfo::FileInfo;
@@ -24,14 +46,71 @@ use searchdirection::SearchDirection;
 mod searchinfo;
 use searchinfo::SearchInfo;
 
+mod selection;
+use selection::Selection;
+
+mod fold;
+use fold::Fold;
+
+const MAX_STICKY_LINES: usize = 3;
+// 绘制时配对括号不在可见区域内，向可见区域之外最多再扫描的行数
+const BRACKET_SCAN_LINES: usize = 1000;
+
+// 在缩进后追加一级：以制表符缩进且不在软缩进模式时追加制表符，否则追加一个制表符宽度的空格
+fn push_indent_level(indentation: &mut String, soft_indent: bool, tab_width: usize) {
+    if indentation.starts_with('\t') && !soft_indent {
+        indentation.push('\t');
+    } else {
+        indentation.push_str(&" ".repeat(tab_width));
+    }
+}
+
+// 从 from 查找到的匹配 location 是否越过了文档的一端：向后查找时位于 from 之前，
+// 向前查找时不在 from 之前（唯一的匹配就在光标处时也算作绕回了一圈）
+fn is_wrapped(from: Location, location: Location, direction: SearchDirection) -> bool {
+    let is_before = (location.line_idx, location.grapheme_idx) < (from.line_idx, from.grapheme_idx);
+    match direction {
+        SearchDirection::Forward => is_before,
+        SearchDirection::Backward => !is_before,
+    }
+}
+
 #[derive(Default)]
 pub struct View {
     buffer: Buffer,
     needs_redraw: bool,
-    size: Size,
+    size: Size,          // 文本区域的尺寸，不包括左侧的行号和行标记列
+    outer_width: ColIdx, // 包括左侧各列在内的总宽度
     text_location: Location,
     scroll_offset: Position,
     search_info: Option<SearchInfo>,
+    last_search_query: Option<Line>, // 最近一次确认的查询，供搜索提示之外的 F3 / Shift-F3 使用
+    last_search_direction: SearchDirection, // 确认该查询时的搜索方向
+    wrap_search: bool,    // 搜索到达文档一端后从另一端继续
+    search_wrapped: bool, // 最近一次查找是否从文档的另一端继续
+    block_anchor: Option<Position>, // 块选区的起点（文档中的行和列）
+    selection_anchor: Option<Location>, // 字符选区的起点，与 block_anchor 不会同时存在
+    secondary_carets: Vec<Location>, // 除 text_location（主光标）之外的其他光标
+    // Alt-D 按选中文本添加光标时，其他光标处选中的同一文本（起点, 终点），与主选区一起编辑
+    secondary_selections: Vec<(Location, Location)>,
+    show_indent_guides: bool,
+    show_sticky_lines: bool,
+    folds: Vec<Fold>, // 按起始行排序且互不重叠
+    overwrite: bool,  // 覆盖模式：输入的字符替换光标处的字素
+    read_only: bool,  // 只读模式：编辑命令不做任何事
+    drawn_caret_bracket: Option<Location>, // 最近一次绘制时标记的光标处括号
+    // 上次检查状态栏时的光标位置、行数、各项标志和光标数，以及文件信息，用于判断状态是否可能改变
+    last_status_key: Option<(Location, usize, bool, bool, bool, bool, usize)>,
+    last_status_file_info: Option<FileInfo>,
+    horizontal_scroll_step: usize,   // 为 0 时按 1 处理
+    horizontal_scroll_margin: usize, // 光标与左右边缘之间保留的列数
+    syntax_cache: SyntaxCache,       // 跨绘制保留的语法高亮结果
+    history: History,                // 撤销 / 重做历史
+    show_line_numbers: bool,         // 在文本左侧显示行号
+    sign_column: bool,               // 在文本左侧显示一列行标记
+    gutter_separator: Option<char>,  // 行标记与文本之间的分隔字符
+    #[cfg(feature = "git")]
+    git_hunks: GitHunks, // 已保存的文件相对 git 索引的变更
 }
 
 impl View {
@@ -40,35 +119,178 @@ impl View {
         DocumentStatus {
             total_lines: self.buffer.height(),
             current_line_idx: self.text_location.line_idx,
+            current_col: self.text_location_to_position().col,
             file_name: format!("{file_info}"),
+            path: file_info.get_absolute_path().map(Path::to_path_buf),
             is_modified: self.buffer.is_dirty(),
+            is_read_only: file_info
+                .get_permissions()
+                .is_some_and(|permissions| permissions.readonly()),
+            is_read_only_mode: self.read_only,
+            caret_count: self.secondary_carets.len().saturating_add(1),
             file_type: file_info.get_file_type(),
+            encoding: if self.buffer.has_bom() { "UTF-8 BOM" } else { "UTF-8" },
+            line_ending: if self.buffer.is_crlf() { "CRLF" } else { "LF" },
         }
     }
 
+    // 与上次调用时相比，状态栏显示的内容是否可能改变。get_status 需要格式化文件名、复制路径，
+    // 这里只比较光标位置、行数、各项标志和光标数，文件信息只在改变时复制；
+    // 文档被编辑时总会需要重绘，因此也视为改变
+    pub fn take_status_changed(&mut self) -> bool {
+        let key = (
+            self.text_location,
+            self.buffer.height(),
+            self.buffer.is_dirty(),
+            self.read_only,
+            self.buffer.has_bom(),
+            self.buffer.is_crlf(),
+            self.secondary_carets.len(),
+        );
+        let file_info = self.buffer.get_file_info();
+        let file_info_changed = self.last_status_file_info.as_ref() != Some(file_info);
+        if file_info_changed {
+            self.last_status_file_info = Some(file_info.clone());
+        }
+        let key_changed = self.last_status_key != Some(key);
+        self.last_status_key = Some(key);
+        file_info_changed || key_changed || self.needs_redraw
+    }
+
+    // 统计块选区内的文本，没有选区时统计全文（字节数包括保存时每行末尾的换行符）
+    pub fn get_statistics(&self) -> DocumentStatistics {
+        let selection = self.selection();
+        let mut statistics = DocumentStatistics {
+            is_selection: selection.is_some(),
+            ..DocumentStatistics::default()
+        };
+        for line_idx in 0..self.buffer.height() {
+            let Some(line) = self.buffer.get_line(line_idx) else {
+                continue;
+            };
+            match selection {
+                Some(selection) => {
+                    if let Some(range) = selection.grapheme_range(line_idx, line) {
+                        statistics.add_line(&line[line.byte_range(range)]);
+                    }
+                }
+                None => {
+                    statistics.add_line(line);
+                    statistics.bytes = statistics.bytes.saturating_add(1);
+                }
+            }
+        }
+        statistics
+    }
+
     pub const fn is_file_loaded(&self) -> bool {
         self.buffer.is_file_loaded()
     }
 
     // 搜索
-    pub fn enter_search(&mut self) {
+    // 进入搜索。选区只包含一行内的文本时，以选中的文本作为初始查询立即搜索，并返回该查询
+    pub fn enter_search(&mut self) -> Option<String> {
+        let selected = self.selected_text();
+        self.clear_selection();
+        self.collapse_carets();
         self.search_info = Some(SearchInfo {
             prev_location: self.text_location,
             prev_scroll_offset: self.scroll_offset,
             query: None,
+            direction: self.last_search_direction,
         });
+        let (location, query) = selected?;
+        self.text_location = location; // 从选中的文本开始搜索，使它成为当前匹配
+        self.search(&query);
+        Some(query)
+    }
+    // 选区只包含一行内的非空文本时，返回选中文本的起点和内容
+    fn selected_text(&self) -> Option<(Location, String)> {
+        let selection = self.selection()?;
+        let (top, bottom) = selection.lines();
+        let line = self.buffer.get_line(top)?;
+        let range = selection.grapheme_range(top, line)?;
+        if top != bottom || range.is_empty() {
+            return None;
+        }
+        let location = Location {
+            line_idx: top,
+            grapheme_idx: range.start,
+        };
+        Some((location, line[line.byte_range(range)].to_string()))
     }
+    // 确认搜索：光标停留在当前匹配上，并保留非空查询及其方向供 F3 / Shift-F3 重复
     pub fn exit_search(&mut self) {
-        self.search_info = None;
-        self.set_needs_redraw(true);
+        if let Some(SearchInfo {
+            query: Some(query),
+            direction,
+            ..
+        }) = self.search_info.take()
+        {
+            if !query.is_empty() {
+                self.last_search_query = Some(query);
+                self.last_search_direction = direction;
+            }
+        }
+        self.scroll_text_location_into_view(); // 搜索期间终端可能已调整大小，确保匹配仍然可见。
+        self.set_needs_redraw(true); // 清除匹配高亮
     }
+    // 取消搜索：恢复进入搜索前的位置和滚动，丢弃本次查询（之前确认的查询不受影响）
     pub fn dismiss_search(&mut self) {
-        if let Some(search_info) = &self.search_info {
+        if let Some(search_info) = self.search_info.take() {
             self.text_location = search_info.prev_location;
             self.scroll_offset = search_info.prev_scroll_offset;
             self.scroll_text_location_into_view(); // 确保即使在搜索期间终端已调整大小，之前的位置仍然可见。
         }
-        self.exit_search();
+        self.set_needs_redraw(true);
+    }
+
+    // 查找替换：光标位于当前查询的一个匹配上时，将它替换为 replacement，
+    // 光标移到替换后的文本之后，使后续查找不会匹配到替换进来的文本。返回是否进行了替换
+    pub fn replace_current_match(&mut self, replacement: &str) -> bool {
+        let Some(query) = self
+            .search_info
+            .as_ref()
+            .and_then(|search_info| search_info.query.clone())
+        else {
+            return false;
+        };
+        let location = self.text_location;
+        if query.is_empty()
+            || self.buffer.search_forward(&query, location, false) != Some(location)
+        {
+            return false;
+        }
+        let match_end = location.grapheme_idx.saturating_add(query.grapheme_count());
+        self.buffer
+            .delete_in_line(location.line_idx, location.grapheme_idx..match_end);
+        self.text_location = self.buffer.insert_str(replacement, location);
+        self.set_needs_redraw(true);
+        true
+    }
+    // 查找替换：从光标处（skip_current 为真时从光标之后）向文档末尾查找当前查询的下一个匹配，
+    // 不从文档开头绕回，以免重复处理已经替换过的位置。找到时光标移到匹配处并返回 true
+    pub fn find_replace_candidate(&mut self, skip_current: bool) -> bool {
+        let Some(query) = self
+            .search_info
+            .as_ref()
+            .and_then(|search_info| search_info.query.as_ref())
+        else {
+            return false;
+        };
+        let mut from = self.text_location;
+        if skip_current {
+            from.grapheme_idx = min(
+                from.grapheme_idx.saturating_add(1),
+                self.buffer.grapheme_count(from.line_idx),
+            );
+        }
+        let Some(location) = self.find_match(query, from, SearchDirection::Forward, false) else {
+            return false;
+        };
+        self.text_location = location;
+        self.center_text_location();
+        true
     }
 
     pub fn search(&mut self, query: &str) {
@@ -78,6 +300,169 @@ impl View {
         self.search_in_direction(self.text_location, SearchDirection::default());
     }
 
+    // 跳到光标处括号的配对括号并居中显示。光标处没有括号时返回 None，否则返回是否找到了配对括号
+    pub fn jump_to_matching_bracket(&mut self) -> Option<bool> {
+        let location = self.caret_bracket()?;
+        // 沿用跨绘制保留的语法高亮结果，只按需高亮扫描到的行
+        let mut highlighter = Highlighter::from_syntax_cache(self.take_syntax_cache());
+        let matching = self.matching_bracket(location, 0..self.buffer.height(), &mut highlighter);
+        self.syntax_cache = highlighter.into_syntax_cache();
+        let Some(matching) = matching else {
+            return Some(false);
+        };
+        self.clear_selection();
+        self.collapse_carets();
+        self.text_location = matching;
+        self.center_text_location();
+        self.set_needs_redraw(true);
+        Some(true)
+    }
+    // 光标处的括号；光标不在括号上时改用光标前的字符（例如行尾的 '{'）
+    fn caret_bracket(&self) -> Option<Location> {
+        let caret = self.text_location;
+        let before = Location {
+            grapheme_idx: caret.grapheme_idx.saturating_sub(1),
+            ..caret
+        };
+        [caret, before]
+            .into_iter()
+            .find(|&location| self.bracket_byte_idx(location).is_some())
+    }
+    // 在 lines 范围内查找 location 处括号的配对括号。字符串和注释中的括号不参与配对，
+    // 除非 location 处的括号本身就在其中。highlighter 只从已高亮的位置推进到扫描到的行，
+    // 因此向后查找时止于配对括号所在的行
+    fn matching_bracket(
+        &self,
+        location: Location,
+        lines: Range<LineIdx>,
+        highlighter: &mut Highlighter,
+    ) -> Option<Location> {
+        let is_literal = |highlighter: &mut Highlighter, line_idx: LineIdx, byte_idx: ByteIdx| {
+            for idx in highlighter.syntax_highlighted_lines()..=line_idx {
+                self.buffer.advance_syntax_highlighter(idx, highlighter);
+            }
+            highlighter.syntax_annotations(line_idx).is_some_and(|annotations| {
+                annotations.iter().any(|annotation| {
+                    matches!(
+                        annotation.annotation_type,
+                        AnnotationType::String | AnnotationType::Char | AnnotationType::Comment
+                    ) && (annotation.start..annotation.end).contains(&byte_idx)
+                })
+            })
+        };
+        let in_literal = self
+            .bracket_byte_idx(location)
+            .is_some_and(|byte_idx| is_literal(highlighter, location.line_idx, byte_idx));
+        self.buffer.find_matching_bracket(location, lines, |line_idx, byte_idx| {
+            !in_literal && is_literal(highlighter, line_idx, byte_idx)
+        })
+    }
+    // 绘制时光标处括号的标记：先在可见区域及其上下至多 BRACKET_SCAN_LINES 行内查找配对括号，
+    // 配对括号不在屏幕上时仍把光标处的括号标记为配对。只有扫描覆盖了整个文档仍找不到配对时
+    // 才标记为不匹配；扫描范围被截断时无法确定，不做标记
+    fn caret_bracket_marks(
+        &self,
+        visible_lines: Range<LineIdx>,
+        highlighter: &mut Highlighter,
+    ) -> Option<(Vec<(LineIdx, ByteIdx)>, AnnotationType)> {
+        let location = self.caret_bracket()?;
+        let height = self.buffer.height();
+        let lines = visible_lines.start.saturating_sub(BRACKET_SCAN_LINES)
+            ..min(visible_lines.end.saturating_add(BRACKET_SCAN_LINES), height);
+        let is_whole_document = lines.start == 0 && lines.end == height;
+        let matching = self.matching_bracket(location, lines, highlighter);
+        let annotation_type = if matching.is_some() {
+            AnnotationType::MatchingBracket
+        } else if is_whole_document {
+            AnnotationType::Error
+        } else {
+            return None;
+        };
+        let brackets = [Some(location), matching]
+            .into_iter()
+            .flatten()
+            .filter_map(|location| {
+                self.bracket_byte_idx(location).map(|byte_idx| (location.line_idx, byte_idx))
+            })
+            .collect();
+        Some((brackets, annotation_type))
+    }
+    // 取出跨绘制保留的语法高亮结果：文件类型改变时整体失效，被修改的行及其后的行需要重新高亮
+    fn take_syntax_cache(&mut self) -> SyntaxCache {
+        let mut syntax_cache = std::mem::take(&mut self.syntax_cache);
+        syntax_cache.set_file_type(self.buffer.get_file_info().get_file_type());
+        if let Some(line_idx) = self.buffer.take_edited_from() {
+            syntax_cache.invalidate_from(line_idx);
+        }
+        syntax_cache
+    }
+    // 建立绘制用的高亮器：从文档开始高亮到可见区域结束，并标记光标处的括号
+    fn highlight_for_draw(&self, syntax_cache: SyntaxCache) -> Highlighter<'_> {
+        let scroll_top = self.scroll_offset.row;
+        let query = self
+            .search_info
+            .as_ref()
+            .and_then(|search_info| search_info.query.as_deref());
+        let selected_match = query.is_some().then_some(self.text_location);
+        let selections = self
+            .selection()
+            .into_iter()
+            .chain(
+                self.active_secondary_selections()
+                    .into_iter()
+                    .map(|(start, end)| Selection::Stream { start, end }),
+            )
+            .collect();
+        let mut highlighter = Highlighter::new(
+            query,
+            selected_match,
+            syntax_cache,
+            selections,
+            &self.secondary_carets,
+        );
+
+        // 从文档开始高亮到可见区域结束，确保所有注释都是最新的。语法高亮的结果已缓存，
+        // 可见区域之上的行通常直接跳过；只有显示的行（包括固定在顶部的上下文行）才需要完整的注解
+        let first_visible_line_idx = self.row_to_line(scroll_top);
+        let last_visible_line_idx = self.row_to_line(scroll_top.saturating_add(self.size.height));
+        let sticky_lines = self.sticky_lines(scroll_top);
+        for line_idx in 0..last_visible_line_idx {
+            if line_idx >= first_visible_line_idx || sticky_lines.contains(&line_idx) {
+                self.buffer.highlight(line_idx, &mut highlighter);
+            } else {
+                self.buffer.advance_highlighter(line_idx, &mut highlighter);
+            }
+        }
+        let visible_lines = first_visible_line_idx..last_visible_line_idx;
+        if let Some((brackets, annotation_type)) =
+            self.caret_bracket_marks(visible_lines, &mut highlighter)
+        {
+            highlighter.set_brackets(&brackets, annotation_type);
+        }
+        highlighter
+    }
+    // 光标移到或离开括号时，括号的标记需要更新，因此需要重绘
+    pub fn track_caret_bracket(&mut self) {
+        let caret_bracket = self.caret_bracket();
+        if caret_bracket != self.drawn_caret_bracket {
+            self.drawn_caret_bracket = caret_bracket;
+            self.set_needs_redraw(true);
+        }
+    }
+    // location 处是括号时返回其字节索引
+    fn bracket_byte_idx(&self, location: Location) -> Option<ByteIdx> {
+        let line = self.buffer.get_line(location.line_idx)?;
+        let range =
+            line.byte_range(location.grapheme_idx..location.grapheme_idx.saturating_add(1));
+        matches!(line.get(range.clone())?, "(" | ")" | "[" | "]" | "{" | "}").then_some(range.start)
+    }
+
+    // 当前查询的匹配总数，以及光标所在的匹配是第几个；还没有输入查询时返回 None
+    pub fn search_match_count(&self) -> Option<(usize, Option<usize>)> {
+        let query = self.search_info.as_ref()?.query.as_ref()?;
+        (!query.is_empty()).then(|| self.buffer.count_matches(query, self.text_location))
+    }
+
     // 尝试获取当前搜索查询 - 对于搜索查询必须存在的场景。
     // 如果在调试中不存在，则会触发恐慌，或者如果在调试中搜索信息不存在
     // 在发布版本中返回 None。
@@ -95,22 +480,113 @@ impl View {
     }
 
     fn search_in_direction(&mut self, from: Location, direction: SearchDirection) {
-        if let Some(location) = self.get_search_query().and_then(|query| {
-            if query.is_empty() {
-                None
-            } else if direction == SearchDirection::Forward {
-                self.buffer.search_forward(query, from)
-            } else {
-                self.buffer.search_backward(query, from)
-            }
-        }) {
+        self.search_wrapped = false;
+        if let Some(location) = self
+            .get_search_query()
+            .and_then(|query| self.find_match(query, from, direction, self.wrap_search))
+        {
+            self.search_wrapped = is_wrapped(from, location, direction);
             self.text_location = location;
             self.center_text_location();
         };
         self.set_needs_redraw(true);
     }
 
+    fn find_match(
+        &self,
+        query: &Line,
+        from: Location,
+        direction: SearchDirection,
+        wrap: bool,
+    ) -> Option<Location> {
+        if query.is_empty() {
+            None
+        } else if direction == SearchDirection::Forward {
+            self.buffer.search_forward(query, from, wrap)
+        } else {
+            self.buffer.search_backward(query, from, wrap)
+        }
+    }
+
+    // 搜索到达文档末尾（或开头）时是否从另一端继续，关闭后停在最后（或第一个）匹配处
+    pub fn set_wrap_search(&mut self, wrap: bool) {
+        self.wrap_search = wrap;
+    }
+    // 最近一次查找下一个 / 上一个匹配时是否越过了文档末尾（或开头）
+    pub const fn search_wrapped(&self) -> bool {
+        self.search_wrapped
+    }
+
+    // 在搜索提示之外，用最近一次确认的查询沿确认时的方向（next）或其反方向（prev）跳到下一个匹配；
+    // 选区只包含一行内的文本时，先以选中的文本代替最近的查询。
+    // 没有可重复的查询时返回 None，否则返回是否找到了匹配。
+    pub fn repeat_search_next(&mut self) -> Option<bool> {
+        self.repeat_search(self.last_search_direction)
+    }
+    pub fn repeat_search_prev(&mut self) -> Option<bool> {
+        self.repeat_search(self.last_search_direction.reversed())
+    }
+    fn repeat_search(&mut self, direction: SearchDirection) -> Option<bool> {
+        if let Some((location, query)) = self.selected_text() {
+            self.text_location = location;
+            self.last_search_query = Some(Line::from(&query));
+        }
+        self.search_wrapped = false;
+        let query = self.last_search_query.as_ref()?;
+        let from = match direction {
+            SearchDirection::Forward => Location {
+                line_idx: self.text_location.line_idx,
+                grapheme_idx: self.text_location.grapheme_idx.saturating_add(1), //从当前匹配后面开始
+            },
+            SearchDirection::Backward => self.text_location,
+        };
+        let Some(location) = self.find_match(query, from, direction, self.wrap_search) else {
+            return Some(false);
+        };
+        self.search_wrapped = is_wrapped(from, location, direction);
+        self.clear_selection();
+        self.collapse_carets();
+        self.text_location = location;
+        self.center_text_location();
+        self.set_needs_redraw(true);
+        Some(true)
+    }
+
+    // 明确指定方向的查找（搜索提示中的箭头键），同时记住该方向
     pub fn search_next(&mut self) {
+        self.set_search_direction(SearchDirection::Forward);
+        self.search_forward_from_caret();
+    }
+    pub fn search_prev(&mut self) {
+        self.set_search_direction(SearchDirection::Backward);
+        self.search_in_direction(self.text_location, SearchDirection::Backward);
+    }
+    // 沿最近一次明确选择的方向（或其反方向）继续查找，不改变记住的方向
+    pub fn search_again(&mut self) {
+        self.search_in_remembered_direction(false);
+    }
+    pub fn search_again_reversed(&mut self) {
+        self.search_in_remembered_direction(true);
+    }
+    fn search_in_remembered_direction(&mut self, reverse: bool) {
+        let direction = self
+            .search_info
+            .as_ref()
+            .map_or(SearchDirection::default(), |search_info| search_info.direction);
+        let direction = if reverse { direction.reversed() } else { direction };
+        match direction {
+            SearchDirection::Forward => self.search_forward_from_caret(),
+            SearchDirection::Backward => {
+                self.search_in_direction(self.text_location, SearchDirection::Backward);
+            }
+        }
+    }
+    fn set_search_direction(&mut self, direction: SearchDirection) {
+        if let Some(search_info) = &mut self.search_info {
+            search_info.direction = direction;
+        }
+    }
+    fn search_forward_from_caret(&mut self) {
         let step_right = self
             .get_search_query()
             .map_or(1, |query| min(query.grapheme_count(), 1));
@@ -121,87 +597,1579 @@ impl View {
         };
         self.search_in_direction(location, SearchDirection::Forward);
     }
-    pub fn search_prev(&mut self) {
-        self.search_in_direction(self.text_location, SearchDirection::Backward);
+
+    // 查找全部匹配项，并附带所在行的内容用于预览
+    // 列出所有匹配及其所在行的预览，位于注释或字符串中的匹配会附加标注
+    pub fn find_all(&self, query: &str) -> Vec<(Location, String)> {
+        let locations = self.buffer.find_all(query);
+        let end = locations
+            .last()
+            .map_or(0, |location| location.line_idx.saturating_add(1));
+        let annotations = self.syntax_annotations(0..end);
+        locations
+            .into_iter()
+            .map(|location| {
+                let Some(line) = self.buffer.get_line(location.line_idx) else {
+                    return (location, String::new());
+                };
+                let byte_idx = line
+                    .byte_range(location.grapheme_idx..location.grapheme_idx)
+                    .start;
+                let context = annotations
+                    .get(location.line_idx)
+                    .and_then(|annotations| {
+                        annotations.iter().find(|annotation| {
+                            annotation.start <= byte_idx && byte_idx < annotation.end
+                        })
+                    })
+                    .and_then(|annotation| match annotation.annotation_type {
+                        AnnotationType::Comment => Some("[注释] "),
+                        AnnotationType::String | AnnotationType::Char => Some("[字符串] "),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                (location, format!("{context}{}", line.trim()))
+            })
+            .collect()
+    }
+
+    // 返回指定范围内每一行的语法注解（不含搜索、选区和光标注解），结果按行顺序排列。
+    // 注解的 start..end 是该行文本内左闭右开的字节索引（而非字素或显示列），
+    // 总是落在字符边界上，可直接用于切片该行的字符串；同一行的注解互不重叠。
+    // 由于多行注释等状态需要从文档开头推导，总会从第 0 行开始高亮到范围结束。
+    pub fn syntax_annotations(&self, lines: Range<LineIdx>) -> Vec<Vec<Annotation>> {
+        let mut highlighter =
+            Highlighter::for_syntax(self.buffer.get_file_info().get_file_type());
+        for line_idx in 0..lines.end.min(self.buffer.height()) {
+            self.buffer.highlight(line_idx, &mut highlighter);
+        }
+        lines
+            .map(|line_idx| highlighter.get_annotations(line_idx))
+            .collect()
+    }
+
+    pub fn mixed_indentation_lines(&self) -> Vec<LineIdx> {
+        self.buffer.mixed_indentation_lines()
+    }
+
+    pub fn very_long_line_count(&self) -> usize {
+        self.buffer.very_long_line_count()
+    }
+
+    // 转换选区所在各行的缩进，没有选区时转换全文
+    pub fn convert_indentation(&mut self, style: IndentStyle) -> usize {
+        let lines = self
+            .selected_lines()
+            .map_or(0..self.buffer.height(), |(first, last)| first..last.saturating_add(1));
+        let changed = self.buffer.convert_indentation(style, lines);
+        if changed > 0 {
+            self.clear_selection(); // 缩进宽度改变后块选区的列不再对应原来的文本
+            self.collapse_carets();
+            self.snap_to_valid_grapheme();
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+        changed
+    }
+
+    // 跳转到指定位置并将其居中显示
+    pub fn jump_to(&mut self, location: Location) {
+        self.clear_selection();
+        self.collapse_carets();
+        self.text_location = location;
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.center_text_location();
     }
 
     // 文件输入输出
     pub fn load(&mut self, file_name: &str) -> Result<(), Error> {
-        let buffer = Buffer::load(file_name)?;
+        let buffer = Buffer::load(file_name, self.buffer.tab_width())?;
         self.buffer = buffer;
+        self.syntax_cache = SyntaxCache::default();
+        self.folds.clear();
+        self.history.clear();
+        self.refresh_git_hunks();
         self.set_needs_redraw(true);
         Ok(())
     }
 
-    pub fn save(&mut self) -> Result<(), Error> {
-        self.buffer.save()?;
-        self.set_needs_redraw(true);
-        Ok(())
+    pub fn load_from_reader(&mut self, reader: impl Read) -> Result<(), Error> {
+        self.buffer = Buffer::load_from_reader(reader, self.buffer.tab_width())?;
+        self.syntax_cache = SyntaxCache::default();
+        self.folds.clear();
+        self.history.clear();
+        self.refresh_git_hunks();
+        self.set_needs_redraw(true);
+        Ok(())
+    }
+
+    // 丢弃当前文档，换成未命名的空白文档；显示设置和最近的查询保留
+    pub fn new_buffer(&mut self) {
+        let tab_width = self.buffer.tab_width();
+        self.buffer = Buffer::default();
+        self.buffer.set_tab_width(tab_width);
+        self.syntax_cache = SyntaxCache::default();
+        self.folds.clear();
+        self.history.clear();
+        self.refresh_git_hunks();
+        self.text_location = Location::default();
+        self.scroll_offset = Position::default();
+        self.search_info = None;
+        self.block_anchor = None;
+        self.selection_anchor = None;
+        self.secondary_carets.clear();
+        self.secondary_selections.clear();
+        self.set_needs_redraw(true);
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), Error> {
+        self.buffer.write_to(writer)
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.buffer.save()?;
+        self.refresh_git_hunks();
+        self.set_needs_redraw(true);
+        Ok(())
+    }
+    // 手动指定文件类型，绕过按扩展名的检测；高亮在下次绘制时按新类型重建
+    pub fn set_file_type(&mut self, file_type: FileType) {
+        self.buffer.set_file_type(file_type);
+        self.set_needs_redraw(true);
+    }
+    pub fn get_file_path(&self) -> Option<&Path> {
+        self.buffer.get_file_info().get_path()
+    }
+    pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
+        self.buffer.save_as(file_name)?;
+        self.refresh_git_hunks();
+        self.set_needs_redraw(true);
+        Ok(())
+    }
+
+    // 制表符宽度随文档保存，加载其他文件或新建文档时沿用
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.buffer.set_tab_width(tab_width);
+        self.set_needs_redraw(true);
+    }
+
+    // 撤销历史
+    pub fn set_undo_limit(&mut self, limit: usize) {
+        self.history = History::new(limit);
+    }
+    // 每条命令执行完后调用，把这条命令造成的修改记为一个撤销步骤；
+    // is_typing 表示该命令是输入字符，连续的输入合并为一个步骤
+    pub fn commit_history(&mut self, is_typing: bool) {
+        self.history.commit(&mut self.buffer, self.text_location, is_typing);
+    }
+    // 撤销最近的一个步骤，光标回到修改之前的位置；没有可撤销的步骤时返回 false
+    pub fn undo(&mut self) -> bool {
+        let old_height = self.buffer.height();
+        let Some(caret) = self.history.undo(&mut self.buffer) else {
+            return false;
+        };
+        self.restore_caret_after_history(caret, old_height);
+        true
+    }
+    // 重做最近撤销的一个步骤，光标回到修改之后的位置；没有可重做的步骤时返回 false
+    pub fn redo(&mut self) -> bool {
+        let old_height = self.buffer.height();
+        let Some(caret) = self.history.redo(&mut self.buffer) else {
+            return false;
+        };
+        self.restore_caret_after_history(caret, old_height);
+        true
+    }
+    fn restore_caret_after_history(&mut self, caret: Location, old_height: usize) {
+        self.clear_selection();
+        self.collapse_carets();
+        if self.buffer.height() != old_height {
+            self.folds.clear();
+        }
+        self.text_location = caret;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    // 命令处理
+    // 执行键入的编辑命令。soft_indent 为真时 Tab 和行首缩进中的退格按缩进层级处理，
+    // 自动缩进追加的一级缩进也只使用空格。
+    // 返回是否有任何效果：只读模式下，以及所有光标都位于文档开头时的退格、
+    // 都位于文档末尾时的删除不做任何事
+    pub fn handle_edit_command(&mut self, command: Edit, soft_indent: bool) -> bool {
+        if self.read_only {
+            return false;
+        }
+        match command {
+            Edit::Insert('\t') if soft_indent => self.insert_soft_tab(),
+            Edit::DeleteBackward if soft_indent => self.delete_indent_backward(),
+            _ => self.edit(command, Some(soft_indent)),
+        }
+    }
+    // 执行编辑命令。auto_indent 不为 None 时按文件类型调整换行后和输入 '}' 后的缩进，
+    // 其中的值表示是否处于软缩进模式；只在单个光标时进行。粘贴等整段插入的文本保持原有缩进
+    fn edit(&mut self, command: Edit, auto_indent: Option<bool>) -> bool {
+        // 选区中的文本先被删除，输入和换行随后在原处进行；交换字符不改变行长，保留选区
+        if !matches!(command, Edit::Dedent | Edit::Transpose)
+            && self.delete_selections()
+            && matches!(command, Edit::Delete | Edit::DeleteBackward | Edit::DeleteWordBackward)
+        {
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+            return true;
+        }
+        if self.is_edit_at_boundary(command) {
+            return false;
+        }
+        let old_height = self.buffer.height();
+        let edited_line_idx = self.text_location.line_idx;
+        let is_single_caret = self.block_anchor.is_none() && self.secondary_carets.is_empty();
+        if self.block_anchor.is_some() {
+            self.handle_block_edit_command(command);
+        } else if self.secondary_carets.is_empty() || matches!(command, Edit::Dedent) {
+            self.apply_edit(command); // 减少缩进按行处理，一次作用于所有光标所在的行
+        } else {
+            self.apply_edit_at_all_carets(command);
+        }
+        if let Some(soft_indent) = auto_indent.filter(|_| is_single_caret) {
+            self.apply_auto_indent(command, soft_indent);
+        }
+        if self.buffer.height() != old_height {
+            if is_single_caret {
+                self.shift_folds(edited_line_idx, old_height);
+            } else {
+                self.folds.clear(); // 多处编辑可能改变任意位置的行数
+            }
+        }
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+    // 没有选区时，判断退格或删除是否在每个光标处都越过了文档的开头或末尾，
+    // 或者交换字符是否在每个光标处都无事可做
+    fn is_edit_at_boundary(&self, command: Edit) -> bool {
+        if self.block_anchor.is_some() {
+            return false;
+        }
+        let last_line_idx = self.buffer.height().saturating_sub(1);
+        let is_at_boundary = |caret: &Location| match command {
+            Edit::DeleteBackward | Edit::DeleteWordBackward => {
+                caret.line_idx == 0 && caret.grapheme_idx == 0
+            }
+            Edit::Delete => {
+                caret.line_idx >= last_line_idx
+                    && caret.grapheme_idx >= self.buffer.grapheme_count(caret.line_idx)
+            }
+            // 行首或不足两个字素的行中没有可以交换的字素
+            Edit::Transpose => {
+                caret.grapheme_idx == 0 || self.buffer.grapheme_count(caret.line_idx) < 2
+            }
+            Edit::Insert(_) | Edit::InsertNewline | Edit::Dedent => false,
+        };
+        std::iter::once(&self.text_location)
+            .chain(&self.secondary_carets)
+            .all(is_at_boundary)
+    }
+    // 软缩进模式下的 Tab：在每个光标处插入空格，直到该光标所在列之后的下一个制表位。
+    // 块选区的各行从同一列开始，按主光标所在的列计算
+    fn insert_soft_tab(&mut self) -> bool {
+        let tab_width = self.buffer.tab_width();
+        let padding = |col: ColIdx| next_tab_stop(col, tab_width).saturating_sub(col);
+        if self.block_anchor.is_some() || self.secondary_carets.is_empty() {
+            for _ in 0..padding(self.text_location_to_position().col) {
+                self.edit(Edit::Insert(' '), None);
+            }
+            return true;
+        }
+        self.delete_selections();
+        self.repeat_edit_at_all_carets(Edit::Insert(' '), |view, caret| {
+            padding(view.buffer.width_until(caret.line_idx, caret.grapheme_idx))
+        });
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+    // 软缩进模式下的退格：单个光标前只有空格（即位于行首的缩进中）时，删除到上一个制表位，
+    // 一次删除一级缩进；其他情况与普通的退格相同
+    fn delete_indent_backward(&mut self) -> bool {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let is_in_indentation = self.buffer.get_line(line_idx).is_some_and(|line| {
+            grapheme_idx > 0
+                && line[line.byte_range(0..grapheme_idx)]
+                    .chars()
+                    .all(|character| character == ' ')
+        });
+        if self.has_selection() || !self.secondary_carets.is_empty() || !is_in_indentation {
+            return self.edit(Edit::DeleteBackward, Some(true));
+        }
+        // 光标前都是单列的空格，列数与字素数相同
+        let tab_width = self.buffer.tab_width();
+        let previous_stop = grapheme_idx.saturating_sub(1) / tab_width * tab_width;
+        self.buffer.delete_in_line(line_idx, previous_stop..grapheme_idx);
+        self.text_location.grapheme_idx = previous_stop;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+    // 在所有光标处插入一段文本。只有一个光标、没有选区且不在覆盖模式时整段插入缓冲区，
+    // 否则逐个字符经由普通的编辑命令处理。只读模式下不做任何事
+    pub fn insert_text(&mut self, text: &str) {
+        if self.read_only {
+            return;
+        }
+        self.delete_selections();
+        if self.block_anchor.is_some() || !self.secondary_carets.is_empty() || self.overwrite {
+            for character in text.chars() {
+                if character == '\n' {
+                    self.edit(Edit::InsertNewline, None);
+                } else {
+                    self.edit(Edit::Insert(character), None);
+                }
+            }
+            return;
+        }
+        let old_height = self.buffer.height();
+        let edited_line_idx = self.text_location.line_idx;
+        self.text_location = self.buffer.insert_str(text, self.text_location);
+        if self.buffer.height() != old_height {
+            self.shift_folds(edited_line_idx, old_height);
+        }
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    // 粘贴文本，终端送来的 \r\n 和 \r 按换行处理。indent_into_block 为真且光标位于
+    // 新开启的花括号块中时，按块内的缩进重新对齐粘贴的各行，保留它们之间的相对缩进。
+    // 只读模式下不做任何事
+    pub fn paste(&mut self, text: &str, indent_into_block: bool) {
+        if self.read_only {
+            return;
+        }
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        match indent_into_block
+            .then(|| self.new_block_indentation())
+            .flatten()
+        {
+            Some(indentation) => self.paste_indented(&text, &indentation),
+            None => self.insert_text(&text),
+        }
+    }
+    // 光标所在行为空白行，上方最近的未闭合 '{' 位于行尾，且两者之间只有空白行时，
+    // 返回块内一级的缩进：沿用 '{' 所在行的缩进再加一级。有选区或多个光标时不处理
+    fn new_block_indentation(&self) -> Option<String> {
+        if self.has_selection() || !self.secondary_carets.is_empty() {
+            return None;
+        }
+        let line_idx = self.text_location.line_idx;
+        let is_blank = |line_idx| {
+            self.buffer
+                .get_line(line_idx)
+                .is_none_or(|line| line.trim().is_empty())
+        };
+        if !is_blank(line_idx) {
+            return None;
+        }
+        let (open_line_idx, _) = self.buffer.enclosing_open_brace(self.text_location)?;
+        let open_line = self.buffer.get_line(open_line_idx)?;
+        if open_line_idx >= line_idx
+            || !open_line.trim_end().ends_with('{')
+            || !(open_line_idx.saturating_add(1)..line_idx).all(is_blank)
+        {
+            return None;
+        }
+        let mut indentation = open_line.leading_whitespace().to_string();
+        push_indent_level(&mut indentation, false, self.buffer.tab_width());
+        Some(indentation)
+    }
+    // 换行后沿用上一行的缩进；按花括号缩进的语言在 '{' 之后多缩进一级（新行以 '}' 开头时除外），
+    // 输入单独一行的 '}' 时与对应的 '{' 所在行对齐
+    fn apply_auto_indent(&mut self, command: Edit, soft_indent: bool) {
+        let indents_by_braces = self.buffer.get_file_info().get_file_type().indents_by_braces();
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let Some(line) = self.buffer.get_line(line_idx) else {
+            return;
+        };
+        let (indentation, old_len) = match command {
+            Edit::InsertNewline => {
+                let Some(previous) = line_idx
+                    .checked_sub(1)
+                    .and_then(|line_idx| self.buffer.get_line(line_idx))
+                else {
+                    return;
+                };
+                // 新行中从断开处带过来的空白换成计算出的缩进。在行首缩进中换行时，
+                // 上一行只剩空白，新行保留原来的全部缩进
+                let old_indentation = line.leading_whitespace();
+                let mut indentation = previous.leading_whitespace().to_string();
+                if previous.trim().is_empty() {
+                    indentation.push_str(old_indentation);
+                } else if indents_by_braces
+                    && previous.trim_end().ends_with('{')
+                    && !line.trim_start().starts_with('}')
+                {
+                    push_indent_level(&mut indentation, soft_indent, self.buffer.tab_width());
+                }
+                if indentation == old_indentation {
+                    return;
+                }
+                (indentation, old_indentation.len())
+            }
+            Edit::Insert('}') if indents_by_braces && line.trim() == "}" => {
+                let brace = Location {
+                    line_idx,
+                    grapheme_idx: grapheme_idx.saturating_sub(1),
+                };
+                let Some(open_line) = self
+                    .buffer
+                    .enclosing_open_brace(brace)
+                    .and_then(|(open_line_idx, _)| self.buffer.get_line(open_line_idx))
+                else {
+                    return;
+                };
+                let indentation = open_line.leading_whitespace().to_string();
+                if indentation == line.leading_whitespace() {
+                    return;
+                }
+                (indentation, line.leading_whitespace().len())
+            }
+            _ => return,
+        };
+        if indentation.is_empty() && old_len == 0 {
+            return;
+        }
+        // 缩进只包含制表符和空格，每个字符都是一个字素
+        self.buffer.delete_in_line(line_idx, 0..old_len);
+        let line_start = Location {
+            line_idx,
+            grapheme_idx: 0,
+        };
+        let indent_end = self.buffer.insert_str(&indentation, line_start);
+        self.text_location.grapheme_idx = grapheme_idx
+            .saturating_sub(old_len)
+            .saturating_add(indent_end.grapheme_idx);
+    }
+    // 去掉粘贴文本各非空白行共同的行首空白，换成 indentation；光标所在的空白行先清空
+    fn paste_indented(&mut self, text: &str, indentation: &str) {
+        let common = text
+            .split('\n')
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let end = line
+                    .find(|character: char| character != ' ' && character != '\t')
+                    .unwrap_or(line.len());
+                &line[..end]
+            })
+            .reduce(|common, leading| {
+                let len = common
+                    .bytes()
+                    .zip(leading.bytes())
+                    .take_while(|(left, right)| left == right)
+                    .count();
+                &common[..len]
+            })
+            .unwrap_or_default();
+        let reindented: Vec<String> = text
+            .split('\n')
+            .map(|line| {
+                if line.trim().is_empty() {
+                    String::new()
+                } else {
+                    format!("{indentation}{}", &line[common.len()..])
+                }
+            })
+            .collect();
+        self.text_location.grapheme_idx = 0;
+        let line_end = Location {
+            line_idx: self.text_location.line_idx,
+            grapheme_idx: self.buffer.grapheme_count(self.text_location.line_idx),
+        };
+        self.buffer.delete_range(self.text_location, line_end);
+        self.insert_text(&reindented.join("\n"));
+    }
+    fn apply_edit(&mut self, command: Edit) {
+        match command {
+            Edit::Insert(character) => self.insert_char(character),
+            Edit::Delete => self.delete(),
+            Edit::DeleteBackward => self.delete_backward(),
+            Edit::DeleteWordBackward => self.delete_word_backward(),
+            Edit::Transpose => self.transpose(),
+            Edit::InsertNewline => self.insert_newline(),
+            Edit::Dedent => self.dedent(),
+        }
+    }
+    // 将选区跨越的各行（没有选区时为各光标所在的行）减少一级缩进，光标和选区起点随之左移
+    fn dedent(&mut self) {
+        let mut line_idxs: Vec<LineIdx> = match self.selected_lines() {
+            Some((first, last)) => (first..=last).collect(),
+            None => std::iter::once(self.text_location)
+                .chain(self.secondary_carets.iter().copied())
+                .map(|caret| caret.line_idx)
+                .collect(),
+        };
+        line_idxs.sort_unstable();
+        line_idxs.dedup();
+        for line_idx in line_idxs {
+            let removed = self.buffer.get_line(line_idx).map_or(0, Line::dedent_len);
+            if removed == 0 {
+                continue;
+            }
+            self.buffer.delete_in_line(line_idx, 0..removed);
+            // 删除的制表符和空格都只占一列，因此列数与字素数的变化相同
+            for caret in std::iter::once(&mut self.text_location).chain(&mut self.secondary_carets) {
+                if caret.line_idx == line_idx {
+                    caret.grapheme_idx = caret.grapheme_idx.saturating_sub(removed);
+                }
+            }
+            if let Some(anchor) = &mut self.block_anchor {
+                if anchor.row == line_idx {
+                    anchor.col = anchor.col.saturating_sub(removed);
+                }
+            }
+            if let Some(anchor) = &mut self.selection_anchor {
+                if anchor.line_idx == line_idx {
+                    anchor.grapheme_idx = anchor.grapheme_idx.saturating_sub(removed);
+                }
+            }
+        }
+    }
+    // 切换选区跨越的各行（没有选区时为各光标所在的行）的行注释：各非空白行都已注释时去掉注释，
+    // 否则在每行的第一个非空白字符前加上注释标记和一个空格。光标和选区起点随文本移动。
+    // 文件类型没有行注释标记时返回 false
+    pub fn toggle_line_comment(&mut self) -> bool {
+        let Some(token) = self.buffer.get_file_info().get_file_type().comment_token() else {
+            return false;
+        };
+        let mut line_idxs: Vec<LineIdx> = match self.selected_lines() {
+            Some((first, last)) => (first..=last).collect(),
+            None => std::iter::once(self.text_location)
+                .chain(self.secondary_carets.iter().copied())
+                .map(|caret| caret.line_idx)
+                .collect(),
+        };
+        line_idxs.sort_unstable();
+        line_idxs.dedup();
+        // 空白行不加注释，除非所有行都是空白行
+        let is_blank = |line_idx| {
+            self.buffer
+                .get_line(line_idx)
+                .is_none_or(|line| line.trim().is_empty())
+        };
+        if !line_idxs.iter().all(|&line_idx| is_blank(line_idx)) {
+            line_idxs.retain(|&line_idx| !is_blank(line_idx));
+        }
+        // 注释标记紧跟在行首的空格和制表符之后，与下面计算插入位置时的判断一致
+        let is_commented = line_idxs.iter().all(|&line_idx| {
+            self.buffer.get_line(line_idx).is_some_and(|line| {
+                line[line.leading_whitespace().len()..].starts_with(token)
+            })
+        });
+        for line_idx in line_idxs {
+            let Some(line) = self.buffer.get_line(line_idx) else {
+                continue;
+            };
+            // 缩进和注释标记中的字符都是单独的字素
+            let start = line.leading_whitespace().len();
+            let (removed, inserted) = if is_commented {
+                let rest = &line[start.saturating_add(token.len())..];
+                let len = token.len().saturating_add(usize::from(rest.starts_with(' ')));
+                self.buffer.delete_in_line(line_idx, start..start.saturating_add(len));
+                (len, 0)
+            } else {
+                let at = Location {
+                    line_idx,
+                    grapheme_idx: start,
+                };
+                self.buffer.insert_str(&format!("{token} "), at);
+                (0, token.len().saturating_add(1))
+            };
+            let shift = |grapheme_idx: GraphemeIdx| {
+                if grapheme_idx < start {
+                    grapheme_idx
+                } else {
+                    grapheme_idx
+                        .saturating_sub(removed)
+                        .max(start)
+                        .saturating_add(inserted)
+                }
+            };
+            let carets = std::iter::once(&mut self.text_location)
+                .chain(&mut self.secondary_carets)
+                .chain(&mut self.selection_anchor);
+            for caret in carets {
+                if caret.line_idx == line_idx {
+                    caret.grapheme_idx = shift(caret.grapheme_idx);
+                }
+            }
+            if let Some(anchor) = self.block_anchor.filter(|anchor| anchor.row == line_idx) {
+                let col = self.buffer.width_until(line_idx, start);
+                if anchor.col >= col {
+                    let anchor_col = anchor.col.saturating_sub(removed).max(col);
+                    self.block_anchor = Some(Position {
+                        col: anchor_col.saturating_add(inserted),
+                        ..anchor
+                    });
+                }
+            }
+        }
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+    // 返回是否有任何效果：光标移动或清除了选区
+    pub fn handle_move_command(&mut self, command: Move) -> bool {
+        let had_selection = self.has_selection();
+        self.clear_selection();
+        let old_carets = (self.text_location, self.secondary_carets.clone());
+        let primary = self.text_location;
+        for idx in 0..self.secondary_carets.len() {
+            self.text_location = self.secondary_carets[idx];
+            self.move_text_location(command);
+            self.secondary_carets[idx] = self.text_location;
+        }
+        self.text_location = primary;
+        self.move_text_location(command);
+        self.merge_carets();
+        self.scroll_text_location_into_view();
+        had_selection || old_carets != (self.text_location, self.secondary_carets.clone())
+    }
+    // 移动主光标，不触发滚动
+    fn move_text_location(&mut self, command: Move) {
+        let Size { height, .. } = self.size;
+        // 此匹配移动位置，但不检查所有边界。
+        // 最终的边界检查发生在匹配语句之后。
+        match command {
+            Move::Up => self.move_up(1),
+            Move::Down => self.move_down(1),
+            Move::Left => self.move_left(),
+            Move::Right => self.move_right(),
+            Move::PageUp => self.move_up(height.saturating_sub(1)),
+            Move::PageDown => self.move_down(height.saturating_sub(1)),
+            Move::StartOfLine => self.move_to_start_of_line(),
+            Move::EndOfLine => self.move_to_end_of_line(),
+            Move::StartOfDocument => self.move_to_start_of_document(),
+            Move::EndOfDocument => self.move_to_end_of_document(),
+        }
+    }
+
+    // 块选区
+    pub fn handle_block_select_command(&mut self, command: Move) {
+        if self.block_anchor.is_none() {
+            self.selection_anchor = None;
+            self.collapse_carets();
+            self.unfold_all(); // 块选区按文档行编辑，不能包含隐藏的行
+            self.block_anchor = Some(self.text_location_to_position());
+        }
+        self.move_text_location(command);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    // 字符选区：从光标处开始，随移动扩展，选中起点与光标之间的文本
+    pub fn handle_select_command(&mut self, command: Move) {
+        if self.selection_anchor.is_none() {
+            self.block_anchor = None;
+            self.collapse_carets();
+            self.unfold_all(); // 选区中的文本可能被整段删除，不能包含隐藏的行
+            self.selection_anchor = Some(self.text_location);
+        }
+        self.move_text_location(command);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    pub const fn has_selection(&self) -> bool {
+        self.block_anchor.is_some() || self.selection_anchor.is_some()
+    }
+    // 删除字符选区中的文本，光标移到选区的起点，返回是否删除了文本。
+    // 字符选区总是被清除，没有字符选区时不做任何事
+    fn delete_stream_selection(&mut self) -> bool {
+        let Some(Selection::Stream { start, end }) = self.selection() else {
+            return false;
+        };
+        self.clear_selection();
+        if start == end {
+            return false;
+        }
+        let old_height = self.buffer.height();
+        self.buffer.delete_range(start, end);
+        if self.buffer.height() != old_height {
+            self.folds.clear();
+        }
+        self.text_location = start;
+        true
+    }
+    // 删除所有光标处选中的文本：主光标的字符选区，以及 Alt-D 添加的光标处选中的同一文本。
+    // 各光标移到被删除文本的起点，返回是否删除了文本。选区总是被清除
+    fn delete_selections(&mut self) -> bool {
+        let secondary = self.active_secondary_selections();
+        let Some(Selection::Stream { start, end }) =
+            self.selection().filter(|_| !secondary.is_empty())
+        else {
+            return self.delete_stream_selection();
+        };
+        self.clear_selection();
+        let mut ranges = secondary;
+        ranges.push((start, end));
+        ranges.sort_by_key(|(start, _)| Reverse((start.line_idx, start.grapheme_idx)));
+        // 这些选区都在一行之内，从文档末尾向开头删除，前面的选区位置不受影响
+        for (start, end) in ranges {
+            let removed = end.grapheme_idx.saturating_sub(start.grapheme_idx);
+            self.buffer
+                .delete_in_line(start.line_idx, start.grapheme_idx..end.grapheme_idx);
+            let carets = std::iter::once(&mut self.text_location).chain(&mut self.secondary_carets);
+            for caret in carets {
+                if caret.line_idx == start.line_idx && caret.grapheme_idx >= start.grapheme_idx {
+                    caret.grapheme_idx = caret
+                        .grapheme_idx
+                        .saturating_sub(removed)
+                        .max(start.grapheme_idx);
+                }
+            }
+        }
+        self.merge_carets();
+        true
+    }
+    // 其他光标处仍然选中与主选区相同文本的选区。主选区不是一行之内的非空文本，
+    // 或者文档被其他命令修改后不再匹配时，这些选区被忽略
+    fn active_secondary_selections(&self) -> Vec<(Location, Location)> {
+        let Some((_, text)) = self.selected_text() else {
+            return Vec::new();
+        };
+        self.secondary_selections
+            .iter()
+            .copied()
+            .filter(|(start, end)| {
+                start.line_idx == end.line_idx
+                    && self.buffer.get_line(start.line_idx).is_some_and(|line| {
+                        line.get(line.byte_range(start.grapheme_idx..end.grapheme_idx))
+                            == Some(text.as_str())
+                    })
+            })
+            .collect()
+    }
+    // 将选区内的文本写入新文件，每行末尾加换行符；零宽度的选区导出其跨越的完整行。
+    // 当前缓冲区的内容、文件名和修改状态都不受影响。返回导出的行数。
+    pub fn export_selection(&self, file_name: &str) -> Result<usize, Error> {
+        let Some(selection) = self.selection() else {
+            return Ok(0);
+        };
+        let mut writer = BufWriter::new(File::create(file_name)?);
+        let (top, bottom) = selection.lines();
+        for line_idx in top..=bottom {
+            let Some(line) = self.buffer.get_line(line_idx) else {
+                continue;
+            };
+            match selection.grapheme_range(line_idx, line) {
+                Some(range) if !selection.is_zero_width() => {
+                    writeln!(writer, "{}", &line[line.byte_range(range)])?;
+                }
+                _ => writeln!(writer, "{line}")?,
+            }
+        }
+        writer.flush()?;
+        Ok(bottom.saturating_sub(top).saturating_add(1))
+    }
+    // 在块选区每行的选中文本之后插入它的副本，字符选区的副本作为一段文本插入在选区之后，
+    // 选区留在原文本上；零宽度的选区复制其跨越的完整行，副本插入在最后一行之下。
+    // 没有选区时返回 false
+    pub fn duplicate_selection(&mut self) -> bool {
+        let Some(selection) = self.selection() else {
+            return false;
+        };
+        let (top, bottom) = selection.lines();
+        let stream_end = match selection {
+            Selection::Stream { end, .. } if !selection.is_zero_width() => Some(end),
+            _ => None,
+        };
+        if let Some(end) = stream_end {
+            let text = self.copy_text();
+            self.buffer.insert_str(&text, end);
+        } else if selection.is_zero_width() {
+            let lines: Vec<Line> = (top..=bottom)
+                .filter_map(|line_idx| self.buffer.get_line(line_idx).cloned())
+                .collect();
+            self.buffer.insert_lines(bottom.saturating_add(1), lines);
+        } else {
+            for line_idx in top..=bottom {
+                let Some(line) = self.buffer.get_line(line_idx) else {
+                    continue;
+                };
+                let Some(range) = selection.grapheme_range(line_idx, line) else {
+                    continue;
+                };
+                let text = line[line.byte_range(range.clone())].to_string();
+                let at = Location {
+                    line_idx,
+                    grapheme_idx: range.end,
+                };
+                self.buffer.insert_str(&text, at);
+            }
+        }
+        self.set_needs_redraw(true);
+        true
+    }
+    // 在光标所在行之下插入它的副本，光标移到副本上的同一位置。光标位于文档末尾之后的空行时
+    // 插入一个空行
+    pub fn duplicate_line(&mut self) {
+        self.clear_selection();
+        self.collapse_carets();
+        let line_idx = self.text_location.line_idx;
+        let old_height = self.buffer.height();
+        let line = self.buffer.get_line(line_idx).cloned().unwrap_or_default();
+        self.buffer.insert_lines(line_idx.saturating_add(1), vec![line]);
+        self.shift_folds(line_idx, old_height);
+        self.text_location.line_idx = line_idx.saturating_add(1);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    // 将光标所在行与上一行（或下一行）交换，光标随之移动。已在文档一端时返回 false
+    pub fn move_line(&mut self, up: bool) -> bool {
+        let line_idx = self.text_location.line_idx;
+        let target = if up {
+            line_idx.checked_sub(1)
+        } else {
+            Some(line_idx.saturating_add(1))
+        };
+        let Some(target) = target.filter(|&target| max(target, line_idx) < self.buffer.height())
+        else {
+            return false;
+        };
+        self.clear_selection();
+        self.collapse_carets();
+        self.buffer.swap_lines(line_idx, target);
+        self.shift_folds(min(line_idx, target), self.buffer.height());
+        self.text_location = Location {
+            line_idx: target,
+            grapheme_idx: min(self.text_location.grapheme_idx, self.buffer.grapheme_count(target)),
+        };
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+    // 复制的文本：块选区为各行选中的文本，以换行分隔；零宽度的选区或没有选区时
+    // 为所跨越的整行（没有选区时为光标所在的行），以换行结尾表示按整行粘贴
+    pub fn copy_text(&self) -> String {
+        let selection = self.selection();
+        let caret_line_idx = self.text_location.line_idx;
+        let (top, bottom) = self.selected_lines().unwrap_or((caret_line_idx, caret_line_idx));
+        let lines = (top..=bottom).filter_map(|line_idx| self.buffer.get_line(line_idx));
+        match selection.filter(|selection| !selection.is_zero_width()) {
+            Some(selection) => (top..=bottom)
+                .zip(lines)
+                .map(|(line_idx, line)| {
+                    selection
+                        .grapheme_range(line_idx, line)
+                        .map_or("", |range| &line[line.byte_range(range)])
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => lines.map(|line| format!("{line}\n")).collect(),
+        }
+    }
+    // 剪切：返回与 copy_text 相同的文本，并删除块选区内的文本或所跨越的整行
+    pub fn cut_text(&mut self) -> String {
+        let text = self.copy_text();
+        let selection = self.selection();
+        if selection.is_some_and(|selection| !selection.is_zero_width()) {
+            self.edit(Edit::Delete, None);
+            return text;
+        }
+        let caret_line_idx = self.text_location.line_idx;
+        let (top, bottom) = self.selected_lines().unwrap_or((caret_line_idx, caret_line_idx));
+        let last_line_idx = self.buffer.height().saturating_sub(1);
+        let end_of = |line_idx| Location {
+            line_idx,
+            grapheme_idx: self.buffer.grapheme_count(line_idx),
+        };
+        // 删除到下一行的开头；剪切到文档末尾时改为从上一行的末尾删起，不留下多余的空行
+        let (from, to) = if bottom < last_line_idx {
+            let next_line_start = Location {
+                line_idx: bottom.saturating_add(1),
+                grapheme_idx: 0,
+            };
+            (Location { line_idx: top, grapheme_idx: 0 }, next_line_start)
+        } else if top > 0 {
+            (end_of(top.saturating_sub(1)), end_of(bottom))
+        } else {
+            (Location::default(), end_of(bottom))
+        };
+        let old_height = self.buffer.height();
+        self.clear_selection();
+        self.collapse_carets();
+        self.buffer.delete_range(from, to);
+        if self.buffer.height() != old_height {
+            self.folds.clear();
+        }
+        self.text_location = Location {
+            line_idx: top.min(self.buffer.height().saturating_sub(1)),
+            grapheme_idx: 0,
+        };
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        text
+    }
+    // 粘贴复制或剪切的文本，光标停在粘贴的内容之后。以换行结尾的整行文本粘贴在当前行之上，
+    // 有字符选区时替换选中的文本。只读模式下不做任何事
+    pub fn paste_text(&mut self, text: &str) {
+        if self.read_only {
+            return;
+        }
+        let replaced = self.delete_stream_selection();
+        self.clear_selection();
+        self.collapse_carets();
+        if text.ends_with('\n') && !replaced {
+            self.text_location.grapheme_idx = 0;
+        }
+        self.insert_text(text);
+    }
+    // 在块选区每行的选中文本（或字符选区的整段文本）前后分别插入 open 和 close，
+    // 选区随之右移，仍然选中原文本。行太短、未到达块选区左边界的行不受影响。没有选区时返回 false
+    pub fn surround_selection(&mut self, open: char, close: char) -> bool {
+        if let Some(Selection::Stream { start, end }) = self.selection() {
+            self.buffer.insert_char(close, end);
+            self.buffer.insert_char(open, start);
+            let new_start = Location {
+                grapheme_idx: start.grapheme_idx.saturating_add(1),
+                ..start
+            };
+            let new_end = Location {
+                grapheme_idx: if end.line_idx == start.line_idx {
+                    end.grapheme_idx.saturating_add(1)
+                } else {
+                    end.grapheme_idx
+                },
+                ..end
+            };
+            let (anchor, caret) = if self.text_location == end {
+                (new_start, new_end)
+            } else {
+                (new_end, new_start)
+            };
+            self.selection_anchor = Some(anchor);
+            self.text_location = caret;
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+            return true;
+        }
+        let Some(Selection::Block {
+            top,
+            bottom,
+            left,
+            right,
+        }) = self.selection()
+        else {
+            return false;
+        };
+        for line_idx in top..=bottom {
+            if self.buffer.width(line_idx) < left {
+                continue;
+            }
+            let Some(line) = self.buffer.get_line(line_idx) else {
+                continue;
+            };
+            let start = line.grapheme_idx_at_col(left);
+            let end = line.grapheme_idx_at_col(right);
+            self.buffer.insert_char(
+                close,
+                Location {
+                    line_idx,
+                    grapheme_idx: end,
+                },
+            );
+            self.buffer.insert_char(
+                open,
+                Location {
+                    line_idx,
+                    grapheme_idx: start,
+                },
+            );
+        }
+        let shift = Line::from(&open.to_string()).width();
+        let caret_col = self.text_location_to_position().col;
+        if let Some(anchor) = &mut self.block_anchor {
+            anchor.col = anchor.col.saturating_add(shift);
+        }
+        let caret_col = caret_col.saturating_add(shift);
+        self.text_location.grapheme_idx = self
+            .buffer
+            .get_line(self.text_location.line_idx)
+            .map_or(0, |line| line.grapheme_idx_at_col(caret_col));
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+    pub fn clear_selection(&mut self) {
+        self.secondary_selections.clear();
+        if self.block_anchor.take().is_some() || self.selection_anchor.take().is_some() {
+            self.set_needs_redraw(true);
+        }
+    }
+    // 按行操作的命令使用：选区（即使起止于行中间）扩展为其跨越的完整行，返回第一行和最后一行
+    fn selected_lines(&self) -> Option<(LineIdx, LineIdx)> {
+        self.selection().map(|selection| selection.lines())
+    }
+    fn selection(&self) -> Option<Selection> {
+        if let Some(anchor) = self.selection_anchor {
+            let caret = self.text_location;
+            let key = |location: Location| (location.line_idx, location.grapheme_idx);
+            let (start, end) = if key(anchor) <= key(caret) {
+                (anchor, caret)
+            } else {
+                (caret, anchor)
+            };
+            return Some(Selection::Stream { start, end });
+        }
+        self.block_anchor.map(|anchor| {
+            let caret = self.text_location_to_position();
+            Selection::Block {
+                top: min(anchor.row, caret.row),
+                bottom: max(anchor.row, caret.row),
+                left: min(anchor.col, caret.col),
+                right: max(anchor.col, caret.col),
+            }
+        })
+    }
+
+    // 在块选区的每一行上执行编辑：输入替换块内的内容，删除清空块内的内容；
+    // 块宽度为零时，删除作用于每行光标所在列前后的字素。编辑后块收缩为零宽度的列光标。
+    fn handle_block_edit_command(&mut self, command: Edit) {
+        let Some(Selection::Block {
+            top,
+            bottom,
+            left,
+            right,
+        }) = self.selection()
+        else {
+            return;
+        };
+        let new_col = match command {
+            Edit::InsertNewline => {
+                self.clear_selection();
+                self.insert_newline();
+                return;
+            }
+            Edit::Transpose => {
+                self.clear_selection();
+                self.transpose();
+                return;
+            }
+            Edit::Dedent => {
+                self.dedent();
+                return;
+            }
+            Edit::Insert(character) => {
+                left.saturating_add(Line::from(&character.to_string()).width())
+            }
+            Edit::Delete => left,
+            // 块选区中按单词删除与退格相同，每行删除一列
+            Edit::DeleteBackward | Edit::DeleteWordBackward if left < right => left,
+            Edit::DeleteBackward | Edit::DeleteWordBackward => {
+                let line_idx = self.text_location.line_idx;
+                let grapheme_idx = self
+                    .buffer
+                    .get_line(line_idx)
+                    .map_or(0, |line| line.grapheme_idx_at_col(left));
+                self.buffer
+                    .width_until(line_idx, grapheme_idx.saturating_sub(1))
+            }
+        };
+        for line_idx in top..=bottom {
+            if self.buffer.width(line_idx) < left {
+                continue; // 行太短，未到达块的左边界
+            }
+            let Some(line) = self.buffer.get_line(line_idx) else {
+                continue;
+            };
+            let start = line.grapheme_idx_at_col(left);
+            let end = line.grapheme_idx_at_col(right);
+            match command {
+                Edit::Insert(character) => {
+                    // 覆盖模式下，零宽度的块选区替换每行光标处的字素
+                    let end = if self.overwrite && start == end {
+                        end.saturating_add(1)
+                    } else {
+                        end
+                    };
+                    self.buffer.delete_in_line(line_idx, start..end);
+                    self.buffer.insert_char(
+                        character,
+                        Location {
+                            line_idx,
+                            grapheme_idx: start,
+                        },
+                    );
+                }
+                Edit::Delete if start == end => {
+                    self.buffer
+                        .delete_in_line(line_idx, start..start.saturating_add(1));
+                }
+                Edit::DeleteBackward | Edit::DeleteWordBackward if start == end => {
+                    self.buffer
+                        .delete_in_line(line_idx, start.saturating_sub(1)..start);
+                }
+                Edit::Delete | Edit::DeleteBackward | Edit::DeleteWordBackward => {
+                    self.buffer.delete_in_line(line_idx, start..end);
+                }
+                Edit::InsertNewline | Edit::Transpose | Edit::Dedent => {}
+            }
+        }
+        if let Some(anchor) = &mut self.block_anchor {
+            anchor.col = new_col;
+        }
+        self.text_location.grapheme_idx = self
+            .buffer
+            .get_line(self.text_location.line_idx)
+            .map_or(0, |line| line.grapheme_idx_at_col(new_col));
+    }
+
+    // 折叠
+    // 折叠包围光标的花括号块，找不到时折叠光标所在的缩进块。光标移动到摘要行。
+    pub fn fold_at_caret(&mut self) -> bool {
+        let Some((start, end)) = self
+            .buffer
+            .enclosing_brace_block(self.text_location)
+            .filter(|(start, end)| start < end)
+            .or_else(|| self.buffer.indentation_block(self.text_location.line_idx))
+        else {
+            return false;
+        };
+        let fold = Fold { start, end };
+        if self.folds.contains(&fold) {
+            return false;
+        }
+        // 新折叠会包含与之重叠的旧折叠
+        self.folds.retain(|other| other.end < start || other.start > end);
+        let idx = self.folds.partition_point(|other| other.start < start);
+        self.folds.insert(idx, fold);
+        self.collapse_carets();
+        self.text_location.line_idx = start;
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+    // 展开以光标所在行为摘要行的折叠
+    pub fn unfold_at_caret(&mut self) -> bool {
+        let line_idx = self.text_location.line_idx;
+        let count = self.folds.len();
+        self.folds.retain(|fold| fold.start != line_idx);
+        self.set_needs_redraw(true);
+        self.folds.len() != count
+    }
+    fn unfold_all(&mut self) {
+        if !self.folds.is_empty() {
+            self.folds.clear();
+            self.set_needs_redraw(true);
+        }
+    }
+    // 指定行是否被某个折叠隐藏
+    fn is_hidden(&self, line_idx: LineIdx) -> bool {
+        self.folds.iter().any(|fold| fold.hides(line_idx))
+    }
+    // 展开隐藏了指定行的折叠
+    fn reveal_line(&mut self, line_idx: LineIdx) {
+        let count = self.folds.len();
+        self.folds.retain(|fold| !fold.hides(line_idx));
+        if self.folds.len() != count {
+            self.set_needs_redraw(true);
+        }
+    }
+    // 单光标编辑改变行数后更新折叠：移除涉及被编辑行及其相邻行的折叠，平移其后的折叠
+    fn shift_folds(&mut self, edited_line_idx: LineIdx, old_height: usize) {
+        let new_height = self.buffer.height();
+        let first = edited_line_idx.saturating_sub(1);
+        let last = edited_line_idx.saturating_add(1);
+        self.folds.retain(|fold| fold.end < first || fold.start > last);
+        for fold in self.folds.iter_mut().filter(|fold| fold.start > edited_line_idx) {
+            if new_height > old_height {
+                let delta = new_height.saturating_sub(old_height);
+                fold.start = fold.start.saturating_add(delta);
+                fold.end = fold.end.saturating_add(delta);
+            } else {
+                let delta = old_height.saturating_sub(new_height);
+                fold.start = fold.start.saturating_sub(delta);
+                fold.end = fold.end.saturating_sub(delta);
+            }
+        }
+    }
+    fn fold_starting_at(&self, line_idx: LineIdx) -> Option<&Fold> {
+        self.folds.iter().find(|fold| fold.start == line_idx)
+    }
+    // 文档行对应的显示行（未计滚动），被隐藏的行映射到其折叠的摘要行
+    fn line_to_row(&self, line_idx: LineIdx) -> RowIdx {
+        let hidden: usize = self
+            .folds
+            .iter()
+            .filter(|fold| fold.start < line_idx)
+            .map(|fold| min(fold.end, line_idx).saturating_sub(fold.start))
+            .sum();
+        line_idx.saturating_sub(hidden)
+    }
+    // 显示行对应的文档行
+    fn row_to_line(&self, row: RowIdx) -> LineIdx {
+        let mut line_idx = row;
+        for fold in &self.folds {
+            if fold.start >= line_idx {
+                break;
+            }
+            line_idx = line_idx.saturating_add(fold.hidden_count());
+        }
+        line_idx
+    }
+
+    // 多光标
+    pub fn add_caret_above(&mut self) {
+        let topmost = self
+            .secondary_carets
+            .iter()
+            .copied()
+            .fold(self.text_location, |top, caret| {
+                if (caret.line_idx, caret.grapheme_idx) < (top.line_idx, top.grapheme_idx) {
+                    caret
+                } else {
+                    top
+                }
+            });
+        if let Some(row) = self.line_to_row(topmost.line_idx).checked_sub(1) {
+            self.add_caret_at_col_of(topmost, self.row_to_line(row));
+        }
+    }
+    pub fn add_caret_below(&mut self) {
+        let bottommost = self
+            .secondary_carets
+            .iter()
+            .copied()
+            .fold(self.text_location, |bottom, caret| {
+                if (caret.line_idx, caret.grapheme_idx) > (bottom.line_idx, bottom.grapheme_idx) {
+                    caret
+                } else {
+                    bottom
+                }
+            });
+        let line_idx = self.row_to_line(self.line_to_row(bottommost.line_idx).saturating_add(1));
+        if line_idx < self.buffer.height() {
+            self.add_caret_at_col_of(bottommost, line_idx);
+        }
+    }
+    // 在主光标所在单词的下一个完整匹配处添加光标（到达末尾后从头开始），
+    // 新光标在匹配中的相对位置与主光标相同。有选区时改为查找选中的文本，见
+    // add_selection_at_next_occurrence。没有可添加的匹配时返回 false。
+    pub fn add_caret_at_next_occurrence(&mut self) -> bool {
+        if let Some((start, text)) = self.selected_text() {
+            return self.add_selection_at_next_occurrence(start, &text);
+        }
+        let primary = self.text_location;
+        let Some(line) = self.buffer.get_line(primary.line_idx) else {
+            return false;
+        };
+        let Some(word_range) = line.word_at(primary.grapheme_idx) else {
+            return false;
+        };
+        let word_len = word_range.len();
+        let offset = primary.grapheme_idx.saturating_sub(word_range.start);
+        let query = line
+            .get(line.byte_range(word_range.clone()))
+            .unwrap_or_default()
+            .to_string();
+        let current = (primary.line_idx, word_range.start);
+
+        let occurrences: Vec<Location> = self
+            .buffer
+            .find_all(&query)
+            .into_iter()
+            .filter(|location| {
+                let word_end = location.grapheme_idx.saturating_add(word_len);
+                self.buffer.get_line(location.line_idx).is_some_and(|line| {
+                    line.word_at(location.grapheme_idx) == Some(location.grapheme_idx..word_end)
+                })
+            })
+            .collect();
+        let next = occurrences
+            .iter()
+            .filter(|location| (location.line_idx, location.grapheme_idx) > current)
+            .chain(occurrences.iter())
+            .map(|location| Location {
+                line_idx: location.line_idx,
+                grapheme_idx: location.grapheme_idx.saturating_add(offset),
+            })
+            .find(|caret| *caret != primary && !self.secondary_carets.contains(caret));
+        let Some(next) = next else {
+            return false;
+        };
+        self.clear_selection();
+        self.secondary_carets.push(primary);
+        self.text_location = next;
+        self.center_text_location();
+        self.set_needs_redraw(true);
+        true
+    }
+    // 在选中文本（从 start 开始的 text）的下一处出现位置（到达末尾后从头开始）添加光标并选中
+    // 该处的文本，新光标成为主光标，已有的选区都保留。所有出现位置都已选中时返回 false
+    fn add_selection_at_next_occurrence(&mut self, start: Location, text: &str) -> bool {
+        let len = Line::from(text).grapheme_count();
+        let mut selections = self.active_secondary_selections();
+        selections.push((
+            start,
+            Location {
+                line_idx: start.line_idx,
+                grapheme_idx: start.grapheme_idx.saturating_add(len),
+            },
+        ));
+        let occurrences = self.buffer.find_all(text);
+        let current = (start.line_idx, start.grapheme_idx);
+        let Some(next) = occurrences
+            .iter()
+            .filter(|location| (location.line_idx, location.grapheme_idx) > current)
+            .chain(occurrences.iter())
+            .copied()
+            .find(|location| selections.iter().all(|(start, _)| start != location))
+        else {
+            return false;
+        };
+        self.secondary_carets.push(self.text_location);
+        self.secondary_selections = selections;
+        self.selection_anchor = Some(next);
+        self.text_location = Location {
+            line_idx: next.line_idx,
+            grapheme_idx: next.grapheme_idx.saturating_add(len),
+        };
+        self.center_text_location();
+        self.set_needs_redraw(true);
+        true
+    }
+    // 移除最近添加的光标（即当前主光标），上一个光标成为主光标，它处的选区成为主选区
+    pub fn remove_last_caret(&mut self) {
+        if let Some(caret) = self.secondary_carets.pop() {
+            self.text_location = caret;
+            self.selection_anchor = None;
+            if let Some(&(start, end)) = self.secondary_selections.last() {
+                if caret == start || caret == end {
+                    self.secondary_selections.pop();
+                    self.selection_anchor = Some(if caret == end { start } else { end });
+                }
+            }
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        }
+    }
+    // 在指定行上与 source 相同的显示列处添加新光标，新光标成为主光标
+    fn add_caret_at_col_of(&mut self, source: Location, line_idx: LineIdx) {
+        self.clear_selection();
+        let col = self.buffer.width_until(source.line_idx, source.grapheme_idx);
+        let grapheme_idx = self
+            .buffer
+            .get_line(line_idx)
+            .map_or(0, |line| line.grapheme_idx_at_col(col));
+        self.secondary_carets.push(self.text_location);
+        self.text_location = Location {
+            grapheme_idx,
+            line_idx,
+        };
+        self.merge_carets();
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+    pub fn collapse_carets(&mut self) {
+        self.secondary_selections.clear();
+        if !self.secondary_carets.is_empty() {
+            self.secondary_carets.clear();
+            self.set_needs_redraw(true);
+        }
+    }
+    // 合并重叠的光标
+    fn merge_carets(&mut self) {
+        let primary = self.text_location;
+        let mut merged: Vec<Location> = Vec::with_capacity(self.secondary_carets.len());
+        for &caret in &self.secondary_carets {
+            if caret != primary && !merged.contains(&caret) {
+                merged.push(caret);
+            }
+        }
+        self.secondary_carets = merged;
+    }
+
+    // 在所有光标处执行编辑。从文档末尾向开头依次处理，
+    // 每次编辑后修正已处理（位于其后方）的光标位置，最后合并重叠的光标。
+    fn apply_edit_at_all_carets(&mut self, command: Edit) {
+        self.repeat_edit_at_all_carets(command, |_, _| 1);
     }
-    pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
-        self.buffer.save_as(file_name)?;
-        self.set_needs_redraw(true);
-        Ok(())
+    // 在每个光标处重复执行编辑命令，次数由 times 按该光标编辑前的位置给出
+    fn repeat_edit_at_all_carets(
+        &mut self,
+        command: Edit,
+        times: impl Fn(&Self, Location) -> usize,
+    ) {
+        let mut carets: Vec<(Location, bool)> = std::iter::once((self.text_location, true))
+            .chain(self.secondary_carets.iter().map(|&caret| (caret, false)))
+            .collect();
+        carets.sort_by_key(|(caret, _)| Reverse((caret.line_idx, caret.grapheme_idx)));
+        for idx in 0..carets.len() {
+            let before = carets[idx].0;
+            // 按单词删除可能越过尚未处理的光标，这样的光标并入上一处删除后的位置，不再重复删除
+            if let Some(&(previous, _)) = idx.checked_sub(1).and_then(|prev| carets.get(prev)) {
+                if matches!(command, Edit::DeleteWordBackward)
+                    && (before.line_idx, before.grapheme_idx)
+                        >= (previous.line_idx, previous.grapheme_idx)
+                {
+                    carets[idx].0 = previous;
+                    continue;
+                }
+            }
+            for _ in 0..times(self, before) {
+                self.apply_edit_at_caret(command, &mut carets, idx);
+            }
+        }
+        self.secondary_carets.clear();
+        for (caret, is_primary) in carets {
+            if is_primary {
+                self.text_location = caret;
+            } else {
+                self.secondary_carets.push(caret);
+            }
+        }
+        self.merge_carets();
     }
-
-    // 命令处理
-    pub fn handle_edit_command(&mut self, command: Edit) {
-        match command {
-            Edit::Insert(character) => self.insert_char(character),
-            Edit::Delete => self.delete(),
-            Edit::DeleteBackward => self.delete_backward(),
-            Edit::InsertNewline => self.insert_newline(),
+    // 在第 idx 个光标处执行一次编辑，并平移已经处理过的（位于其后的）光标
+    fn apply_edit_at_caret(&mut self, command: Edit, carets: &mut [(Location, bool)], idx: usize) {
+        let before = carets[idx].0;
+        // 被替换区域的结束位置：向前删除或覆盖输入时为被替换字素之后的位置，
+        // 其余情况为编辑前的光标位置
+        let replaces_next = match command {
+            Edit::Delete => true,
+            // 交换光标前后的字素；位于行尾时交换最后两个字素，光标不动
+            Edit::Transpose => before.grapheme_idx < self.buffer.grapheme_count(before.line_idx),
+            Edit::Insert(_) => self.overwrites_at(before),
+            Edit::InsertNewline
+            | Edit::DeleteBackward
+            | Edit::DeleteWordBackward
+            | Edit::Dedent => false,
+        };
+        let old_end = if replaces_next {
+            self.location_after(before)
+        } else {
+            before
+        };
+        self.text_location = before;
+        self.apply_edit(command);
+        let new_end = self.text_location;
+        carets[idx].0 = new_end;
+        for (caret, _) in &mut carets[..idx] {
+            *caret = Self::shift_location(*caret, old_end, new_end);
         }
     }
-    pub fn handle_move_command(&mut self, command: Move) {
-        let Size { height, .. } = self.size;
-        // 此匹配移动位置，但不检查所有边界。
-        // 最终的边界检查发生在匹配语句之后。
-        match command {
-            Move::Up => self.move_up(1),
-            Move::Down => self.move_down(1),
-            Move::Left => self.move_left(),
-            Move::Right => self.move_right(),
-            Move::PageUp => self.move_up(height.saturating_sub(1)),
-            Move::PageDown => self.move_down(height.saturating_sub(1)),
-            Move::StartOfLine => self.move_to_start_of_line(),
-            Move::EndOfLine => self.move_to_end_of_line(),
+    // 区域被替换后，将原本位于区域结束位置 old_end 之后的位置映射到新结束位置 new_end 之后
+    fn shift_location(location: Location, old_end: Location, new_end: Location) -> Location {
+        if (location.line_idx, location.grapheme_idx) < (old_end.line_idx, old_end.grapheme_idx) {
+            new_end
+        } else if location.line_idx == old_end.line_idx {
+            Location {
+                line_idx: new_end.line_idx,
+                grapheme_idx: new_end
+                    .grapheme_idx
+                    .saturating_add(location.grapheme_idx.saturating_sub(old_end.grapheme_idx)),
+            }
+        } else {
+            Location {
+                line_idx: location
+                    .line_idx
+                    .saturating_sub(old_end.line_idx)
+                    .saturating_add(new_end.line_idx),
+                grapheme_idx: location.grapheme_idx,
+            }
+        }
+    }
+    fn location_after(&self, location: Location) -> Location {
+        if location.grapheme_idx < self.buffer.grapheme_count(location.line_idx) {
+            Location {
+                line_idx: location.line_idx,
+                grapheme_idx: location.grapheme_idx.saturating_add(1),
+            }
+        } else {
+            Location {
+                line_idx: location.line_idx.saturating_add(1),
+                grapheme_idx: 0,
+            }
         }
-        self.scroll_text_location_into_view();
     }
 
     // 文本编辑
     fn insert_newline(&mut self) {
         self.buffer.insert_newline(self.text_location);
-        self.handle_move_command(Move::Right);
+        self.move_text_location(Move::Right);
         self.set_needs_redraw(true);
     }
     fn delete_backward(&mut self) {
         if self.text_location.line_idx != 0 || self.text_location.grapheme_idx != 0 {
-            self.handle_move_command(Move::Left);
+            self.move_text_location(Move::Left);
             self.delete();
         }
     }
+    // 删除光标之前的空白和一个单词（或一串标点），边界见 Line::previous_word_start；
+    // 位于行首时与退格相同，与上一行合并
+    fn delete_word_backward(&mut self) {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        if grapheme_idx == 0 {
+            self.delete_backward();
+            return;
+        }
+        let start = self
+            .buffer
+            .get_line(line_idx)
+            .map_or(0, |line| line.previous_word_start(grapheme_idx));
+        self.buffer.delete_in_line(line_idx, start..grapheme_idx);
+        self.text_location.grapheme_idx = start;
+        self.set_needs_redraw(true);
+    }
+    // 交换光标前后的两个字素，然后光标右移一个字素；位于行尾时交换最后两个字素，光标不动。
+    // 位于行首时不做任何事
+    fn transpose(&mut self) {
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let count = self.buffer.grapheme_count(line_idx);
+        if grapheme_idx == 0 || count < 2 {
+            return;
+        }
+        let at = min(grapheme_idx, count.saturating_sub(1));
+        self.buffer.transpose(Location {
+            line_idx,
+            grapheme_idx: at,
+        });
+        self.text_location.grapheme_idx = at.saturating_add(1);
+        self.set_needs_redraw(true);
+    }
     fn delete(&mut self) {
         self.buffer.delete(self.text_location);
         self.set_needs_redraw(true);
     }
+    // 覆盖模式下在行尾之前输入会替换光标处的字素
+    fn overwrites_at(&self, location: Location) -> bool {
+        self.overwrite && location.grapheme_idx < self.buffer.grapheme_count(location.line_idx)
+    }
     fn insert_char(&mut self, character: char) {
+        if self.overwrites_at(self.text_location) {
+            self.buffer.delete(self.text_location);
+        }
         let old_len = self.buffer.grapheme_count(self.text_location.line_idx);
         self.buffer.insert_char(character, self.text_location);
         let new_len = self.buffer.grapheme_count(self.text_location.line_idx);
         let grapheme_delta = new_len.saturating_sub(old_len);
         if grapheme_delta > 0 {
             // 为添加的字符向右移动（应该是常规情况）
-            self.handle_move_command(Move::Right);
+            self.move_text_location(Move::Right);
         }
         self.set_needs_redraw(true);
     }
 
     // 渲染
-    fn render_line(at: RowIdx, line_text: &str) -> Result<(), Error> {
-        Terminal::print_row(at, line_text)
-    }
     fn build_welcome_message(width: usize) -> String {
         if width == 0 {
             return String::new();
@@ -228,46 +2196,531 @@ impl View {
         } else {
             false
         };
-        if offset_changed {
+        let sticky_changed = self.scroll_below_sticky_lines(to);
+        if offset_changed || sticky_changed {
             self.set_needs_redraw(true);
         }
     }
+    // 视图变高后，如果文档末尾之后留有空白而上方仍有内容被滚出，则向上滚动以填满视图。
+    // 文档末尾之后保留一行，光标可以停在那里
+    fn clamp_scroll_offset_to_content(&mut self) {
+        let content_rows = self.line_to_row(self.buffer.height()).saturating_add(1);
+        let max_offset = content_rows.saturating_sub(self.size.height);
+        if self.scroll_offset.row > max_offset {
+            self.scroll_offset.row = max_offset;
+            self.set_needs_redraw(true);
+        }
+    }
+    // 固定的上下文行占用了文档区域顶部，继续向下滚动直到 to 行位于剩余区域内
+    fn scroll_below_sticky_lines(&mut self, to: RowIdx) -> bool {
+        let Size { height, .. } = self.size;
+        let mut offset_changed = false;
+        while to
+            >= self
+                .scroll_offset
+                .row
+                .saturating_add(height)
+                .saturating_sub(self.sticky_lines(self.scroll_offset.row).len())
+            && self.scroll_offset.row < to
+        {
+            self.scroll_offset.row = self.scroll_offset.row.saturating_add(1);
+            offset_changed = true;
+        }
+        offset_changed
+    }
+    // 光标进入左右边距时至少滚动 horizontal_scroll_step 列，滚动后的位置对齐到字素起点
     fn scroll_horizontally(&mut self, to: ColIdx) {
         let Size { width, .. } = self.size;
-        let offset_changed = if to < self.scroll_offset.col {
-            self.scroll_offset.col = to;
-            true
-        } else if to >= self.scroll_offset.col.saturating_add(width) {
-            self.scroll_offset.col = to.saturating_sub(width).saturating_add(1);
-            true
+        let left = self.scroll_offset.col;
+        // 边缘显示 "«" / ">" 标记时，标记占用的一列不算在边距内，光标与标记之间仍保留 margin 列。
+        // 水平滚动后左边缘总有标记；光标之后还有超过一列的文本时，光标到达右边缘前右侧会出现标记。
+        // 每侧的边距不超过可见宽度的一半，否则两侧的边距无法同时满足
+        let Location {
+            line_idx,
+            grapheme_idx,
+        } = self.text_location;
+        let line_width = self.buffer.width(line_idx);
+        // 光标所在字素的最后一列：双宽字素和制表符要整个可见，不能压在右侧的标记上
+        let caret_end = self
+            .buffer
+            .width_until(line_idx, grapheme_idx.saturating_add(1))
+            .max(to.saturating_add(1))
+            .saturating_sub(1);
+        let half = width.saturating_sub(1) / 2;
+        let left_margin = self.horizontal_scroll_margin.saturating_add(1).min(half);
+        let right_margin = self
+            .horizontal_scroll_margin
+            .saturating_add(usize::from(line_width > caret_end.saturating_add(1)))
+            .min(half);
+        let step = self.horizontal_scroll_step.max(1);
+        // 步长较大时也要让光标留在边距以内：min_col / max_col 分别使光标位于右侧 / 左侧边距处
+        let min_col = caret_end
+            .saturating_add(right_margin)
+            .saturating_add(1)
+            .saturating_sub(width);
+        let max_col = to.saturating_sub(left_margin);
+        let new_col = if left > 0 && to < left.saturating_add(left_margin) {
+            let col = max_col.min(left.saturating_sub(step)).max(min_col);
+            self.snap_scroll_col(col)
+        } else if caret_end.saturating_add(right_margin) >= left.saturating_add(width) {
+            let col = min_col.max(left.saturating_add(step)).min(max_col);
+            // 向右滚动时向后对齐，避免光标被挤出右边缘
+            self.snap_scroll_col_forward(col)
         } else {
-            false
+            return;
         };
-        if offset_changed {
+        if new_col != left {
+            self.scroll_offset.col = new_col;
             self.set_needs_redraw(true);
         }
     }
     fn scroll_text_location_into_view(&mut self) {
-        let Position { row, col } = self.text_location_to_position();
+        self.reveal_line(self.text_location.line_idx);
+        let Position { row, col } = self.text_location_to_screen_position();
         self.scroll_vertically(row);
         self.scroll_horizontally(col);
     }
+    // 将光标移动到光标之后的下一个拼写错误（到达文末后从头查找），没有拼写错误时返回 false
+    #[cfg(feature = "spellcheck")]
+    pub fn move_to_next_misspelling(&mut self) -> bool {
+        let mut highlighter =
+            Highlighter::for_spell_check(self.buffer.get_file_info().get_file_type());
+        let mut misspellings = Vec::new();
+        for line_idx in 0..self.buffer.height() {
+            let Some(line) = self.buffer.get_line(line_idx) else {
+                continue;
+            };
+            highlighter.highlight(line_idx, line);
+            misspellings.extend(
+                highlighter
+                    .get_annotations(line_idx)
+                    .iter()
+                    .filter(|annotation| annotation.annotation_type == AnnotationType::Misspelled)
+                    .filter_map(|annotation| line.byte_idx_to_grapheme_idx(annotation.start))
+                    .map(|grapheme_idx| Location {
+                        line_idx,
+                        grapheme_idx,
+                    }),
+            );
+        }
+        let caret = (self.text_location.line_idx, self.text_location.grapheme_idx);
+        let Some(&location) = misspellings
+            .iter()
+            .find(|location| (location.line_idx, location.grapheme_idx) > caret)
+            .or_else(|| misspellings.first())
+        else {
+            return false;
+        };
+        self.clear_selection();
+        self.collapse_carets();
+        self.text_location = location;
+        self.center_text_location();
+        true
+    }
     fn center_text_location(&mut self) {
+        self.reveal_line(self.text_location.line_idx);
         let Size { height, width } = self.size;
-        let Position { row, col } = self.text_location_to_position();
+        let Position { row, col } = self.text_location_to_screen_position();
         let vertical_mid = height.div_ceil(2);
         let horizontal_mid = width.div_ceil(2);
         self.scroll_offset.row = row.saturating_sub(vertical_mid);
-        self.scroll_offset.col = col.saturating_sub(horizontal_mid);
+        self.scroll_offset.col = self.snap_scroll_col(col.saturating_sub(horizontal_mid));
+        self.scroll_below_sticky_lines(row);
+        self.set_needs_redraw(true);
+    }
+
+    // 调整水平滚动位置，使光标所在行左边缘的第一个文本列落在字素起点，避免从双宽字素的中间
+    // 开始渲染。水平滚动后最左列被 "«" 标记占用，因此第一个文本列是 col + 1。
+    fn snap_scroll_col(&self, col: ColIdx) -> ColIdx {
+        if col == 0 {
+            return 0;
+        }
+        self.buffer
+            .get_line(self.text_location.line_idx)
+            .map_or(col, |line| {
+                let text_start = line.width_until(line.grapheme_idx_at_col(col.saturating_add(1)));
+                text_start.saturating_sub(1)
+            })
+    }
+    // 与 snap_scroll_col 相同，但向后对齐：第一个文本列是 col + 1 之后（含）最近的字素起点，
+    // 跳过跨越该列的双宽字素或制表符。超出行尾时不需要对齐
+    fn snap_scroll_col_forward(&self, col: ColIdx) -> ColIdx {
+        if col == 0 {
+            return 0;
+        }
+        self.buffer
+            .get_line(self.text_location.line_idx)
+            .map_or(col, |line| {
+                let text_col = col.saturating_add(1);
+                let grapheme_idx = line.grapheme_idx_at_col(text_col);
+                let text_start = if line.width_until(grapheme_idx) >= text_col {
+                    line.width_until(grapheme_idx)
+                } else {
+                    line.width_until(grapheme_idx.saturating_add(1))
+                };
+                text_start.saturating_sub(1).max(col)
+            })
+    }
+
+    pub fn set_horizontal_scrolling(&mut self, step: usize, margin: usize) {
+        self.horizontal_scroll_step = step;
+        self.horizontal_scroll_margin = margin;
+    }
+
+    // 只在启动时调用，尺寸由随后的 resize 按照行号和行标记列重新计算
+    pub fn set_gutter(&mut self, line_numbers: bool, sign_column: bool, separator: Option<char>) {
+        self.show_line_numbers = line_numbers;
+        self.sign_column = sign_column;
+        self.gutter_separator = separator;
+    }
+
+    pub fn toggle_line_numbers(&mut self) -> bool {
+        self.show_line_numbers = !self.show_line_numbers;
+        self.update_text_width();
+        self.set_needs_redraw(true);
+        self.show_line_numbers
+    }
+
+    // 行号列的宽度：最大行号的位数加上与之后各列隔开的一个空格；不显示行号时为 0
+    fn line_number_width(&self) -> ColIdx {
+        if !self.show_line_numbers {
+            return 0;
+        }
+        let digits = self.buffer.height().max(1).to_string().len();
+        digits.saturating_add(1)
+    }
+
+    // 文本左侧的行号列、行标记列和分隔符占用的列数
+    fn gutter_width(&self) -> ColIdx {
+        let separator_width = self
+            .gutter_separator
+            .map_or(0, |separator| Line::from(&separator.to_string()).width());
+        self.line_number_width()
+            .saturating_add(usize::from(self.sign_column))
+            .saturating_add(separator_width)
+    }
+
+    // 按当前的左侧各列重新计算文本区域的宽度。行号的位数随行数变化，因此每次绘制前都要检查
+    fn update_text_width(&mut self) {
+        let width = self.outer_width.saturating_sub(self.gutter_width());
+        if width != self.size.width {
+            self.size.width = width;
+            self.scroll_text_location_into_view();
+        }
+    }
+
+    // 某一行在行标记列中显示的标记。以后的诊断信息、版本控制标记也在这里加入
+    fn line_sign(&self, line_idx: LineIdx) -> Option<(&'static str, AnnotationType)> {
+        let query = self
+            .search_info
+            .as_ref()
+            .and_then(|search_info| search_info.query.as_deref());
+        if query.is_some_and(|query| self.buffer.has_match(line_idx, query)) {
+            Some(("*", AnnotationType::Match))
+        } else {
+            self.line_change(line_idx).map(|change| match change {
+                LineChange::Added => ("+", AnnotationType::Dim),
+                LineChange::Modified => ("!", AnnotationType::Dim),
+                LineChange::RemovedAbove => ("_", AnnotationType::Dim),
+            })
+        }
+    }
+
+    // 重新计算文件相对 git 索引的变更，未启用 git 功能时不做任何事
+    #[cfg(feature = "git")]
+    fn refresh_git_hunks(&mut self) {
+        self.git_hunks = self
+            .buffer
+            .get_file_info()
+            .get_path()
+            .map(GitHunks::load)
+            .unwrap_or_default();
+    }
+    #[cfg(not(feature = "git"))]
+    #[allow(clippy::unused_self)]
+    fn refresh_git_hunks(&mut self) {}
+
+    // 一行的变更：编辑后尚未保存的变更优先，其次是已保存的文件相对 git 索引的变更
+    fn line_change(&self, line_idx: LineIdx) -> Option<LineChange> {
+        let change = self.buffer.line_change(line_idx);
+        #[cfg(feature = "git")]
+        let change = change.or_else(|| {
+            let saved_line_idx = self.buffer.saved_line_idx(line_idx)?;
+            let is_last = line_idx.saturating_add(1) == self.buffer.height();
+            // 文件末尾被删除的行之后没有其他行，标记在最后一行上
+            self.git_hunks.change_at(saved_line_idx).or_else(|| {
+                self.git_hunks
+                    .change_at(saved_line_idx.saturating_add(1))
+                    .filter(|change| is_last && *change == LineChange::RemovedAbove)
+            })
+        });
+        change
+    }
+
+    // 将光标移动到下一个（或上一个）连续变更行的第一行，到达文档一端后从另一端继续查找；
+    // 没有变更时返回 false
+    pub fn move_to_change(&mut self, forward: bool) -> bool {
+        let height = self.buffer.height();
+        let changes: Vec<_> = (0..height).map(|line_idx| self.line_change(line_idx)).collect();
+        let starts: Vec<LineIdx> = (0..height)
+            .filter(|&line_idx| {
+                changes[line_idx].is_some()
+                    && (line_idx == 0 || changes[line_idx.saturating_sub(1)] != changes[line_idx])
+            })
+            .collect();
+        let caret = self.text_location.line_idx;
+        let target = if forward {
+            starts.iter().find(|&&start| start > caret).or_else(|| starts.first())
+        } else {
+            starts.iter().rev().find(|&&start| start < caret).or_else(|| starts.last())
+        };
+        let Some(&line_idx) = target else {
+            return false;
+        };
+        self.clear_selection();
+        self.collapse_carets();
+        self.text_location = Location {
+            line_idx,
+            grapheme_idx: 0,
+        };
+        self.center_text_location();
+        true
+    }
+
+    // 在一行可见内容前加上行号、行标记和分隔符；line_idx 为 None 表示文档之外的空行
+    fn prepend_gutter(&self, annotated_string: &mut AnnotatedString, line_idx: Option<LineIdx>) {
+        if let Some(separator) = self.gutter_separator {
+            annotated_string.prepend_annotated(&separator.to_string(), AnnotationType::Dim);
+        }
+        if self.sign_column {
+            match line_idx.and_then(|line_idx| self.line_sign(line_idx)) {
+                Some((sign, annotation_type)) => {
+                    annotated_string.prepend_annotated(sign, annotation_type);
+                }
+                None => annotated_string.replace(0, 0, " "),
+            }
+        }
+        let number_width = self.line_number_width();
+        if number_width > 0 {
+            // 行号右对齐，之后留一个空格
+            let number = line_idx.map_or_else(String::new, |line_idx| {
+                line_idx.saturating_add(1).to_string()
+            });
+            let digits_width = number_width.saturating_sub(1);
+            let number = format!("{number:>digits_width$} ");
+            annotated_string.prepend_annotated(&number, AnnotationType::Dim);
+        }
+    }
+
+    pub fn toggle_indent_guides(&mut self) -> bool {
+        self.show_indent_guides = !self.show_indent_guides;
+        self.set_needs_redraw(true);
+        self.show_indent_guides
+    }
+
+    // 在可见文本（从显示列 visible.start 开始）的缩进参考线位置绘制暗色竖线。
+    // 缩进只由单列单字节的空白字符组成，因此参考线的字节偏移等于其相对显示列
+    // 加上之前插入的参考线多占的字节；空白行的参考线可能超出行尾，需要先用空格补齐。
+    fn draw_indent_guides(
+        &self,
+        annotated_string: &mut AnnotatedString,
+        line_idx: LineIdx,
+        visible: Range<ColIdx>,
+    ) {
+        const GUIDE: &str = "│";
+        let mut shift: ByteIdx = 0;
+        for col in self.buffer.indent_guide_cols(line_idx) {
+            if !visible.contains(&col) {
+                continue;
+            }
+            let offset = col.saturating_sub(visible.start).saturating_add(shift);
+            let len = annotated_string.to_string().len();
+            if offset < len {
+                annotated_string.replace(offset, offset.saturating_add(1), GUIDE);
+                annotated_string.add_annotation(
+                    AnnotationType::Dim,
+                    offset,
+                    offset.saturating_add(GUIDE.len()),
+                );
+            } else {
+                let padding = " ".repeat(offset.saturating_sub(len));
+                annotated_string.append_annotated(&format!("{padding}{GUIDE}"), AnnotationType::Dim);
+            }
+            shift = shift.saturating_add(GUIDE.len().saturating_sub(1));
+        }
+    }
+
+    // 绘制固定的上下文行和文档区域的各行
+    fn draw_rows(&self, origin_row: RowIdx, highlighter: &Highlighter) -> Result<(), Error> {
+        let Size { height, width } = self.size;
+        let end_y = origin_row.saturating_add(height);
+        let top_third = height.div_ceil(3);
+        let scroll_top = self.scroll_offset.row;
+        // 固定在顶部的上下文行占用文档区域最上方的若干行
+        let sticky_lines = self.sticky_lines(scroll_top);
+        let content_origin = origin_row.saturating_add(sticky_lines.len());
+        for (current_row, &line_idx) in (origin_row..).zip(&sticky_lines) {
+            if let Some(mut annotated_string) = self.build_annotated_line(line_idx, highlighter) {
+                self.prepend_gutter(&mut annotated_string, Some(line_idx));
+                Terminal::print_annotated_row(current_row, &annotated_string)?;
+            }
+        }
+        for current_row in content_origin..end_y {
+            // 要获取正确的行索引，我们必须取 current_row（屏幕上的绝对行），
+            // 减去 content_origin 获取相对于文档区域的当前行
+            // 并加上滚动偏移量，再跳过被折叠隐藏的行。
+            let line_idx = self.row_to_line(
+                current_row
+                    .saturating_sub(content_origin)
+                    .saturating_add(scroll_top),
+            );
+            if let Some(mut annotated_string) = self.build_annotated_line(line_idx, highlighter) {
+                self.prepend_gutter(&mut annotated_string, Some(line_idx));
+                Terminal::print_annotated_row(current_row, &annotated_string)?;
+            } else {
+                let text = if current_row == top_third && self.buffer.is_empty() {
+                    Self::build_welcome_message(width)
+                } else {
+                    String::from("~")
+                };
+                let mut annotated_string = AnnotatedString::from(&text);
+                self.prepend_gutter(&mut annotated_string, None);
+                Terminal::print_annotated_row(current_row, &annotated_string)?;
+            }
+        }
+        Ok(())
+    }
+
+    // 生成一行在当前水平滚动下的可见内容，包括各种标记；行不存在时返回 None
+    fn build_annotated_line(
+        &self,
+        line_idx: LineIdx,
+        highlighter: &Highlighter,
+    ) -> Option<AnnotatedString> {
+        let width = self.size.width;
+        let left = self.scroll_offset.col;
+        let right = self.scroll_offset.col.saturating_add(width);
+        // 行内容超出可见范围时，在边缘列显示标记，标记占用的列不再显示文本
+        let line_width = self.buffer.width(line_idx);
+        let overflow_left = left > 0 && line_width > 0;
+        let overflow_right = line_width > right;
+        let text_left = if overflow_left { left.saturating_add(1) } else { left };
+        let text_right = if overflow_right { right.saturating_sub(1) } else { right };
+        let mut annotated_string =
+            self.buffer
+                .get_highlighted_substring(line_idx, text_left..text_right, highlighter)?;
+        if !overflow_right && self.has_secondary_caret_at_line_end(line_idx, left) {
+            annotated_string.append_annotated(" ", AnnotationType::SecondaryCaret);
+        }
+        if self.show_indent_guides {
+            self.draw_indent_guides(&mut annotated_string, line_idx, text_left..text_right);
+        }
+        if overflow_left {
+            annotated_string.prepend_annotated("«", AnnotationType::Dim);
+        }
+        if overflow_right {
+            annotated_string.append_annotated(">", AnnotationType::Dim);
+        } else if let Some(fold) = self.fold_starting_at(line_idx) {
+            let marker = format!(" ⋯ 已折叠 {} 行", fold.hidden_count());
+            annotated_string.append_annotated(&marker, AnnotationType::Dim);
+        }
+        Some(annotated_string)
+    }
+
+    pub fn toggle_overwrite(&mut self) -> bool {
+        self.overwrite = !self.overwrite;
+        self.overwrite
+    }
+
+    pub fn is_overwrite(&self) -> bool {
+        self.overwrite
+    }
+
+    pub fn toggle_read_only(&mut self) -> bool {
+        self.read_only = !self.read_only;
+        self.read_only
+    }
+
+    pub const fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn toggle_sticky_lines(&mut self) -> bool {
+        self.show_sticky_lines = !self.show_sticky_lines;
+        self.scroll_text_location_into_view();
         self.set_needs_redraw(true);
+        self.show_sticky_lines
+    }
+
+    // 滚动到 scroll_top 时需要固定在顶部显示的上下文行（由外到内）：
+    // 即包围首个可见行、但已滚出视图的块的摘要行，最多占用一半的视图高度
+    fn sticky_lines(&self, scroll_top: RowIdx) -> Vec<LineIdx> {
+        if !self.show_sticky_lines {
+            return Vec::new();
+        }
+        let mut headers = self.buffer.enclosing_headers(self.row_to_line(scroll_top));
+        let max_count = min(MAX_STICKY_LINES, self.size.height.saturating_div(2));
+        headers.drain(..headers.len().saturating_sub(max_count));
+        headers
+    }
+
+    // 行尾的次光标没有字素可以标记，需要在行尾追加一个占位块
+    fn has_secondary_caret_at_line_end(&self, line_idx: LineIdx, left: ColIdx) -> bool {
+        let grapheme_count = self.buffer.grapheme_count(line_idx);
+        self.buffer.width(line_idx) >= left
+            && self
+                .secondary_carets
+                .iter()
+                .any(|caret| caret.line_idx == line_idx && caret.grapheme_idx >= grapheme_count)
     }
 
     // 位置和坐标处理
+    // 主光标在文档中的位置
+    pub const fn caret(&self) -> Location {
+        self.text_location
+    }
+    pub const fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+    // 终端光标在视图中的位置。主光标被折叠隐藏时落在折叠摘要行的行首；
+    // 超出视图时限制在视图范围内，避免终端光标跑到状态栏等其他组件上
     pub fn caret_position(&self) -> Position {
-        self.text_location_to_position()
-            .saturating_sub(self.scroll_offset)
+        let sticky_count = self.sticky_lines(self.scroll_offset.row).len();
+        let mut position = self.text_location_to_screen_position();
+        if self.is_hidden(self.text_location.line_idx) {
+            position.col = 0;
+        }
+        let Position { row, col } = position.saturating_sub(self.scroll_offset);
+        Position {
+            row: min(
+                row.saturating_add(sticky_count),
+                self.size.height.saturating_sub(1),
+            ),
+            col: min(col, self.size.width.saturating_sub(1)).saturating_add(self.gutter_width()),
+        }
+    }
+
+    // 主光标被折叠隐藏或位于可见区域之外时，展开折叠并滚动到光标处
+    pub fn reveal_caret(&mut self) {
+        let Size { height, width } = self.size;
+        if height == 0 || width == 0 {
+            return;
+        }
+        let Position { row, col } = self.text_location_to_screen_position();
+        let top = self
+            .scroll_offset
+            .row
+            .saturating_add(self.sticky_lines(self.scroll_offset.row).len());
+        let is_off_screen = row < top
+            || row >= self.scroll_offset.row.saturating_add(height)
+            || col < self.scroll_offset.col
+            || col >= self.scroll_offset.col.saturating_add(width);
+        if is_off_screen || self.is_hidden(self.text_location.line_idx) {
+            self.scroll_text_location_into_view();
+        }
     }
 
+    // 光标在文档中的位置（行为文档行）
     fn text_location_to_position(&self) -> Position {
         let row = self.text_location.line_idx;
         debug_assert!(row.saturating_sub(1) <= self.buffer.height());
@@ -276,14 +2729,25 @@ impl View {
             .width_until(row, self.text_location.grapheme_idx);
         Position { col, row }
     }
+    // 光标的显示位置（行为考虑折叠后的显示行）
+    fn text_location_to_screen_position(&self) -> Position {
+        let Position { row, col } = self.text_location_to_position();
+        Position {
+            row: self.line_to_row(row),
+            col,
+        }
+    }
 
     // 文本位置移动
+    // 上下移动按显示行计算，跳过被折叠隐藏的行
     fn move_up(&mut self, step: usize) {
-        self.text_location.line_idx = self.text_location.line_idx.saturating_sub(step);
+        let row = self.line_to_row(self.text_location.line_idx).saturating_sub(step);
+        self.text_location.line_idx = self.row_to_line(row);
         self.snap_to_valid_grapheme();
     }
     fn move_down(&mut self, step: usize) {
-        self.text_location.line_idx = self.text_location.line_idx.saturating_add(step);
+        let row = self.line_to_row(self.text_location.line_idx).saturating_add(step);
+        self.text_location.line_idx = self.row_to_line(row);
         self.snap_to_valid_grapheme();
         self.snap_to_valid_line();
     }
@@ -313,6 +2777,16 @@ impl View {
         self.text_location.grapheme_idx = self.buffer.grapheme_count(self.text_location.line_idx);
     }
 
+    fn move_to_start_of_document(&mut self) {
+        self.text_location = Location::default();
+        self.scroll_offset.col = 0; // 回到文档开头时同时重置水平滚动
+        self.set_needs_redraw(true);
+    }
+    fn move_to_end_of_document(&mut self) {
+        self.text_location.line_idx = self.buffer.height().saturating_sub(1);
+        self.move_to_end_of_line();
+    }
+
     // 确保 self.location.grapheme_idx 指向有效的字素索引，如果适当则向左移动到最左边的字素。
     // 不触发滚动。
     fn snap_to_valid_grapheme(&mut self) {
@@ -337,50 +2811,388 @@ impl UIComponent for View {
         self.needs_redraw
     }
     fn set_size(&mut self, size: Size) {
-        self.size = size;
+        // size 只记录文本区域，不包括左侧的行号和行标记列
+        self.outer_width = size.width;
+        self.size = Size {
+            width: size.width.saturating_sub(self.gutter_width()),
+            ..size
+        };
+        self.clamp_scroll_offset_to_content();
         self.scroll_text_location_into_view();
     }
 
     fn draw(&mut self, origin_row: RowIdx) -> Result<(), Error> {
-        let Size { height, width } = self.size;
-        let end_y = origin_row.saturating_add(height);
-        let top_third = height.div_ceil(3);
-        let scroll_top = self.scroll_offset.row;
+        self.update_text_width();
+        let syntax_cache = self.take_syntax_cache();
+        let highlighter = self.highlight_for_draw(syntax_cache);
+        let result = self.draw_rows(origin_row, &highlighter);
+        self.syntax_cache = highlighter.into_syntax_cache();
+        result
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::{register_builtin_highlighters, Highlighter, Path, UIComponent, View};
+    use crate::editor::{
+        command::{Edit, Move},
+        AnnotationType, FileType,
+    };
+    use crate::prelude::*;
+    use std::{env, fs, process};
 
-        let query = self
-            .search_info
-            .as_ref()
-            .and_then(|search_info| search_info.query.as_deref());
-        let selected_match = query.is_some().then_some(self.text_location);
-        let mut highlighter = Highlighter::new(
-            query,
-            selected_match,
-            self.buffer.get_file_info().get_file_type(),
-        );
+    #[test]
+    fn status_uses_path_cached_on_load_and_save() {
+        let path = env::temp_dir().join(format!("tzt-view-{}-status.txt", process::id()));
+        let renamed = path.with_extension("rs");
+        fs::write(&path, "hello\n").unwrap();
+        let mut view = View::default();
+        view.load(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(view.take_status_changed());
+        view.set_needs_redraw(false);
+        // 没有任何变化时不需要重新读取状态
+        assert!(!view.take_status_changed());
+        let cached = view.buffer.get_file_info().get_absolute_path().map(Path::to_path_buf);
+        assert_eq!(cached.as_deref(), Some(path.as_path()));
+        assert_eq!(view.get_status().path, cached);
+
+        // 另存为之后缓存的是新路径，状态随之改变
+        view.save_as(renamed.to_str().unwrap()).unwrap();
+        fs::remove_file(&renamed).unwrap();
+        assert!(view.take_status_changed());
+        assert_eq!(view.get_status().path.as_deref(), Some(renamed.as_path()));
+    }
 
-        for current_row in 0..end_y.saturating_add(scroll_top) {
-            self.buffer.highlight(current_row, &mut highlighter); //从文档开始高亮到可见区域结束，确保所有注释都是最新的。
+    #[test]
+    fn caret_stays_inside_last_row_and_column() {
+        let mut view = View::default();
+        view.load_from_reader("abcdefghijklmnop\n".repeat(5).as_bytes()).unwrap();
+        let size = Size {
+            height: 3,
+            width: 10,
+        };
+        view.resize(size);
+        for _ in 0..4 {
+            view.handle_move_command(Move::Down);
         }
-        for current_row in origin_row..end_y {
-            // 要获取正确的行索引，我们必须取 current_row（屏幕上的绝对行），
-            // 减去 origin_row 获取相对于视图的当前行（范围从 0 到 self.size.height）
-            // 并加上滚动偏移量。
-            let line_idx = current_row
-                .saturating_sub(origin_row)
-                .saturating_add(scroll_top);
-            let left = self.scroll_offset.col;
-            let right = self.scroll_offset.col.saturating_add(width);
-            if let Some(annotated_string) =
-                self.buffer
-                    .get_highlighted_substring(line_idx, left..right, &highlighter)
-            {
-                Terminal::print_annotated_row(current_row, &annotated_string)?;
-            } else if current_row == top_third && self.buffer.is_empty() {
-                Self::render_line(current_row, &Self::build_welcome_message(width))?;
-            } else {
-                Self::render_line(current_row, "~")?;
+        view.handle_move_command(Move::EndOfLine);
+        let position = view.caret_position();
+        assert_eq!(position.row, size.height.saturating_sub(1));
+        assert_eq!(position.col, size.width.saturating_sub(1));
+    }
+
+    #[test]
+    fn growing_view_scrolls_up_to_fill_with_content() {
+        let mut view = View::default();
+        view.load_from_reader("x\n".repeat(100).as_bytes()).unwrap();
+        view.resize(Size {
+            height: 10,
+            width: 20,
+        });
+        view.handle_move_command(Move::EndOfDocument);
+        assert_eq!(view.scroll_offset.row, 90);
+        // 变高后文档末尾之后只保留一行
+        view.resize(Size {
+            height: 50,
+            width: 20,
+        });
+        assert_eq!(view.scroll_offset.row, 51);
+        assert_eq!(view.caret_position().row, 48);
+        // 视图比整个文档还高时从第一行开始显示
+        view.resize(Size {
+            height: 200,
+            width: 20,
+        });
+        assert_eq!(view.scroll_offset.row, 0);
+    }
+
+    // 每行形如 "line 7"，视图宽 20 列
+    fn view_with_lines(count: usize, height: usize) -> View {
+        let mut view = View::default();
+        let text: String = (0..count).map(|idx| format!("line {idx}\n")).collect();
+        view.load_from_reader(text.as_bytes()).unwrap();
+        view.resize(Size { height, width: 20 });
+        view
+    }
+
+    fn last_search_query(view: &View) -> Option<String> {
+        view.last_search_query.as_ref().map(ToString::to_string)
+    }
+
+    #[test]
+    fn accepting_search_keeps_match_visible_after_resize() {
+        let mut view = view_with_lines(100, 20);
+        view.enter_search();
+        view.search("line 80");
+        assert_eq!(view.text_location.line_idx, 80);
+        view.resize(Size {
+            height: 5,
+            width: 20,
+        });
+        view.exit_search();
+        assert!(view.search_info.is_none());
+        assert_eq!(view.text_location.line_idx, 80);
+        assert!(view.caret_position().row < 5);
+        assert_eq!(last_search_query(&view).as_deref(), Some("line 80"));
+    }
+
+    #[test]
+    fn dismissing_search_restores_position_and_last_query() {
+        let mut view = view_with_lines(100, 20);
+        view.enter_search();
+        view.search("line 5");
+        view.exit_search();
+        let location = view.text_location;
+        view.enter_search();
+        view.search("line 90");
+        assert_eq!(view.text_location.line_idx, 90);
+        view.resize(Size {
+            height: 4,
+            width: 20,
+        });
+        view.dismiss_search();
+        assert!(view.search_info.is_none());
+        assert_eq!(view.text_location, location);
+        assert!(view.caret_position().row < 4);
+        // 取消的查询不会替换上次确认的查询
+        assert_eq!(last_search_query(&view).as_deref(), Some("line 5"));
+    }
+
+    #[test]
+    fn centering_starts_at_a_full_width_grapheme() {
+        let mut view = View::default();
+        view.load_from_reader("中".repeat(50).as_bytes()).unwrap();
+        for width in [10, 11] {
+            view.resize(Size { height: 3, width });
+            for grapheme_idx in 10..50 {
+                view.text_location = Location {
+                    grapheme_idx,
+                    line_idx: 0,
+                };
+                view.center_text_location();
+                // 最左列是 « 标记，其后的第一个文本列落在某个字素的起点（偶数列）
+                let first_text_col = view.scroll_offset.col.saturating_add(1);
+                assert_eq!(first_text_col % 2, 0, "{width} {grapheme_idx}");
+                let caret_col = grapheme_idx.saturating_mul(2);
+                assert!(caret_col > view.scroll_offset.col);
+                assert!(caret_col < view.scroll_offset.col.saturating_add(width));
             }
         }
-        Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn repeated_search_follows_last_explicit_direction() {
+        let mut view = View::default();
+        view.load_from_reader("-\nx\n-\nx\n-\nx\n-\n".as_bytes()).unwrap();
+        view.text_location.line_idx = 4;
+        view.enter_search();
+        view.search("x");
+        assert_eq!(view.text_location.line_idx, 5);
+        // 箭头键指定方向，F3 沿该方向继续，Shift-F3 反向
+        view.search_prev();
+        assert_eq!(view.text_location.line_idx, 3);
+        view.search_again();
+        assert_eq!(view.text_location.line_idx, 1);
+        view.search_again_reversed();
+        assert_eq!(view.text_location.line_idx, 3);
+        view.exit_search();
+        // 确认后在搜索提示之外重复时沿用确认时的方向
+        assert_eq!(view.repeat_search_next(), Some(true));
+        assert_eq!(view.text_location.line_idx, 1);
+        assert_eq!(view.repeat_search_prev(), Some(true));
+        assert_eq!(view.text_location.line_idx, 3);
+        // 下一次搜索从记住的方向开始
+        view.enter_search();
+        view.search("x");
+        view.search_again();
+        assert_eq!(view.text_location.line_idx, 1);
+        view.search_next();
+        view.search_again();
+        assert_eq!(view.text_location.line_idx, 5);
+    }
+
+    #[test]
+    fn horizontal_scroll_keeps_full_width_graphemes_off_the_markers() {
+        for margin in [0, 2] {
+            let mut view = View::default();
+            view.load_from_reader("中".repeat(30).as_bytes()).unwrap();
+            view.set_horizontal_scrolling(1, margin);
+            view.resize(Size {
+                height: 3,
+                width: 11,
+            });
+            let check = |view: &View| {
+                let left = view.scroll_offset.col;
+                let caret_col = view.text_location.grapheme_idx.saturating_mul(2);
+                let screen_col = caret_col.saturating_sub(left);
+                if left > 0 {
+                    // 最左列是 « 标记，其后的第一个文本列是字素起点，光标与标记之间保留 margin 列
+                    assert_eq!(left.saturating_add(1) % 2, 0, "{margin} {left}");
+                    assert!(screen_col > margin, "{margin} {left} {caret_col}");
+                }
+                // 双宽光标完整显示；光标之后还有文本时不压在右边缘的 > 标记上
+                let right_marker = usize::from(caret_col.saturating_add(2) < 60);
+                assert!(
+                    screen_col.saturating_add(2).saturating_add(right_marker) <= 11,
+                    "{margin} {left} {caret_col}"
+                );
+            };
+            for _ in 0..30 {
+                view.handle_move_command(Move::Right);
+                check(&view);
+            }
+            assert!(view.scroll_offset.col > 0);
+            for _ in 0..30 {
+                view.handle_move_command(Move::Left);
+                check(&view);
+            }
+            assert_eq!(view.scroll_offset.col, 0);
+        }
+    }
+
+    // 以 Rust 语法加载文本，视图高 10 行
+    fn rust_view(text: &str) -> View {
+        register_builtin_highlighters();
+        let mut view = View::default();
+        view.load_from_reader(text.as_bytes()).unwrap();
+        view.set_file_type(FileType::Syntax("Rust"));
+        view.resize(Size {
+            height: 10,
+            width: 40,
+        });
+        view
+    }
+
+    // 按当前滚动位置计算可见的行，返回绘制时光标处括号的标记
+    fn caret_bracket_marks(view: &mut View) -> Option<(Vec<(LineIdx, ByteIdx)>, AnnotationType)> {
+        let first_visible_line_idx = view.scroll_offset.row;
+        let visible_lines =
+            first_visible_line_idx..first_visible_line_idx.saturating_add(view.size.height);
+        let mut highlighter = Highlighter::from_syntax_cache(view.take_syntax_cache());
+        view.caret_bracket_marks(visible_lines, &mut highlighter)
+    }
+
+    #[test]
+    fn marks_bracket_whose_partner_is_scrolled_out_of_view() {
+        // 字符串中的 '}' 在可见区域之外，也要按语法跳过
+        let text = format!("fn main() {{\n{}}}\n", "    let s = \"}\";\n".repeat(50));
+        let mut view = rust_view(&text);
+        view.text_location = Location {
+            line_idx: 0,
+            grapheme_idx: 10,
+        };
+        let marks = caret_bracket_marks(&mut view);
+        assert_eq!(marks, Some((vec![(0, 10), (51, 0)], AnnotationType::MatchingBracket)));
+        // 配对括号在可见区域之上
+        view.handle_move_command(Move::EndOfDocument);
+        assert!(view.scroll_offset.row > 0);
+        view.text_location = Location {
+            line_idx: 51,
+            grapheme_idx: 0,
+        };
+        let marks = caret_bracket_marks(&mut view);
+        assert_eq!(marks, Some((vec![(51, 0), (0, 10)], AnnotationType::MatchingBracket)));
+    }
+
+    #[test]
+    fn marks_unmatched_bracket_only_after_scanning_whole_document() {
+        let mut view = rust_view(&format!("{{\n{}", "x\n".repeat(50)));
+        let marks = caret_bracket_marks(&mut view);
+        assert_eq!(marks, Some((vec![(0, 0)], AnnotationType::Error)));
+        // 扫描范围被截断时无法确定是否匹配，不做标记
+        let mut view = rust_view(&format!("{{\n{}", "x\n".repeat(2000)));
+        assert_eq!(caret_bracket_marks(&mut view), None);
+    }
+
+    #[test]
+    fn jump_highlights_only_up_to_the_matching_bracket() {
+        let text = format!("fn f() {{\n    \"}}\"\n}}\n{}", "x\n".repeat(100));
+        let mut view = rust_view(&text);
+        view.text_location = Location {
+            line_idx: 0,
+            grapheme_idx: 7,
+        };
+        assert_eq!(view.jump_to_matching_bracket(), Some(true));
+        assert_eq!(view.text_location.line_idx, 2);
+        let highlighter = Highlighter::from_syntax_cache(std::mem::take(&mut view.syntax_cache));
+        assert_eq!(highlighter.syntax_highlighted_lines(), 3);
+        // 向前查找时光标所在行之前的结果已经缓存，不必重新高亮
+        view.syntax_cache = highlighter.into_syntax_cache();
+        assert_eq!(view.jump_to_matching_bracket(), Some(true));
+        assert_eq!(view.text_location.line_idx, 0);
+        let highlighter = Highlighter::from_syntax_cache(std::mem::take(&mut view.syntax_cache));
+        assert_eq!(highlighter.syntax_highlighted_lines(), 3);
+    }
+
+    // 按绘制的方式高亮（可见区域之上的行只推进语法高亮），返回已高亮的行中含有注释的行
+    fn draw_comment_lines(view: &mut View) -> Vec<LineIdx> {
+        let syntax_cache = view.take_syntax_cache();
+        let highlighter = view.highlight_for_draw(syntax_cache);
+        let comment_lines = (0..highlighter.syntax_highlighted_lines())
+            .filter(|&line_idx| {
+                highlighter.syntax_annotations(line_idx).is_some_and(|annotations| {
+                    annotations
+                        .iter()
+                        .any(|annotation| annotation.annotation_type == AnnotationType::Comment)
+                })
+            })
+            .collect();
+        view.syntax_cache = highlighter.into_syntax_cache();
+        comment_lines
+    }
+
+    fn insert_at(view: &mut View, line_idx: LineIdx, grapheme_idx: GraphemeIdx, text: &str) {
+        view.text_location = Location {
+            line_idx,
+            grapheme_idx,
+        };
+        for character in text.chars() {
+            view.handle_edit_command(Edit::Insert(character), false);
+        }
+    }
+
+    #[test]
+    fn block_comment_edits_update_lines_below_on_redraw() {
+        let mut view = rust_view(&format!("fn f() {{}}\n{}", "let x = 1;\n".repeat(30)));
+        assert!(draw_comment_lines(&mut view).is_empty());
+        // 打开注释后，滚动到文档末尾时其上的行只推进语法高亮，之后的行都在注释中
+        insert_at(&mut view, 2, 0, "/*");
+        draw_comment_lines(&mut view);
+        view.handle_move_command(Move::EndOfDocument);
+        assert!(view.scroll_offset.row > 5);
+        assert_eq!(draw_comment_lines(&mut view), (2..31).collect::<Vec<_>>());
+        // 在注释内编辑不改变注释的范围
+        insert_at(&mut view, 3, 3, " y");
+        draw_comment_lines(&mut view);
+        view.handle_move_command(Move::EndOfDocument);
+        assert_eq!(draw_comment_lines(&mut view), (2..31).collect::<Vec<_>>());
+        // 闭合注释后，其后的行恢复为代码
+        insert_at(&mut view, 5, 0, "*/");
+        draw_comment_lines(&mut view);
+        view.handle_move_command(Move::EndOfDocument);
+        assert_eq!(draw_comment_lines(&mut view), [2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn soft_tab_pads_each_caret_to_its_own_tab_stop() {
+        let mut view = View::default();
+        view.load_from_reader("a\nabc\nab\n".as_bytes()).unwrap();
+        let at = |line_idx, grapheme_idx| Location {
+            line_idx,
+            grapheme_idx,
+        };
+        view.text_location = at(0, 1);
+        view.secondary_carets = vec![at(1, 3), at(2, 0), at(2, 1)];
+        assert!(view.handle_edit_command(Edit::Insert('\t'), true));
+        let lines: Vec<String> = (0..3)
+            .filter_map(|line_idx| view.buffer.get_line(line_idx).map(ToString::to_string))
+            .collect();
+        assert_eq!(lines, ["a   ", "abc ", "    a   b"]);
+        // 每个光标都停在制表位上
+        assert_eq!(view.text_location, at(0, 4));
+        let mut carets = view.secondary_carets.clone();
+        carets.sort_by_key(|caret| (caret.line_idx, caret.grapheme_idx));
+        assert_eq!(carets, [at(1, 4), at(2, 4), at(2, 8)]);
+    }
+}
@@ -4,3 +4,12 @@ pub enum SearchDirection {
     Forward,
     Backward,
 }
+
+impl SearchDirection {
+    pub const fn reversed(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+        }
+    }
+}
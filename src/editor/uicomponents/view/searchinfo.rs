@@ -1,8 +1,11 @@
 use crate::editor::Line;
 use crate::prelude::*;
 
+use super::SearchDirection;
+
 pub struct SearchInfo {
     pub prev_location: Location,
     pub prev_scroll_offset: Position,
     pub query: Option<Line>,
+    pub direction: SearchDirection, // 最近一次明确选择的方向，F3 / Shift-F3 以此为准
 }
@@ -0,0 +1,69 @@
+use std::ops::Range;
+
+use crate::editor::Line;
+use crate::prelude::*;
+
+// 选区：块选区为跨越多行的矩形列区域，字符选区为两个位置之间（不含 end）的连续文本
+#[derive(Clone, Copy)]
+pub enum Selection {
+    Block {
+        top: LineIdx,
+        bottom: LineIdx,
+        left: ColIdx,
+        right: ColIdx,
+    },
+    Stream {
+        start: Location,
+        end: Location,
+    },
+}
+
+impl Selection {
+    // 返回选区跨越的第一行和最后一行，供按行操作的命令把选区扩展为整行
+    pub const fn lines(&self) -> (LineIdx, LineIdx) {
+        match *self {
+            Self::Block { top, bottom, .. } => (top, bottom),
+            Self::Stream { start, end } => (start.line_idx, end.line_idx),
+        }
+    }
+
+    // 零宽度的块选区只是一列光标，起止相同的字符选区只是一个光标，都没有选中任何文本
+    pub const fn is_zero_width(&self) -> bool {
+        match *self {
+            Self::Block { left, right, .. } => left == right,
+            Self::Stream { start, end } => {
+                start.line_idx == end.line_idx && start.grapheme_idx == end.grapheme_idx
+            }
+        }
+    }
+
+    // 返回选区在指定行中覆盖的字素范围，不在选区内的行返回 None
+    pub fn grapheme_range(&self, line_idx: LineIdx, line: &Line) -> Option<Range<GraphemeIdx>> {
+        match *self {
+            Self::Block {
+                top,
+                bottom,
+                left,
+                right,
+            } => (top..=bottom).contains(&line_idx).then(|| {
+                line.grapheme_idx_at_col(left)..line.grapheme_idx_at_col(right)
+            }),
+            Self::Stream { start, end } => {
+                (start.line_idx..=end.line_idx).contains(&line_idx).then(|| {
+                    let count = line.grapheme_count();
+                    let from = if line_idx == start.line_idx {
+                        start.grapheme_idx.min(count)
+                    } else {
+                        0
+                    };
+                    let to = if line_idx == end.line_idx {
+                        end.grapheme_idx.min(count)
+                    } else {
+                        count
+                    };
+                    from..to.max(from)
+                })
+            }
+        }
+    }
+}
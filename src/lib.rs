@@ -0,0 +1,9 @@
+// 编辑器核心，也可以作为库嵌入其他程序：Editor 是占用终端的完整编辑器，
+// Core、Buffer 和 Line 不依赖终端，可以用来执行编辑命令、读取和检查文档内容
+mod editor;
+pub use editor::command::{Command, Edit, Move, MultiCursor, System};
+pub use editor::annotationtype::AnnotationType;
+pub use editor::{Annotation, Buffer, Core, Editor, Line};
+
+mod prelude;
+pub use prelude::Location;
@@ -1,6 +1,6 @@
 use super::{GraphemeIdx, LineIdx};
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub struct Location {
     pub grapheme_idx: GraphemeIdx,
     pub line_idx: LineIdx,
@@ -15,3 +15,14 @@ pub use size::Size;
 
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// 未配置时的制表符宽度；实际宽度由各缓冲区保存，并传给其中的每一行
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+// 从 col 列按制表符展开后到达的下一个制表位，tab_width 为 0 时按 1 处理
+pub fn next_tab_stop(col: ColIdx, tab_width: usize) -> ColIdx {
+    let tab_width = tab_width.max(1);
+    col.saturating_div(tab_width)
+        .saturating_add(1)
+        .saturating_mul(tab_width)
+}
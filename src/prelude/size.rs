@@ -1,4 +1,4 @@
-#[derive(Default, Copy, Clone, Eq, PartialEq)]
+#[derive(Default, Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Size {
     pub height: usize,
     pub width: usize,
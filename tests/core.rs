@@ -0,0 +1,174 @@
+// 通过 Core 执行编辑命令，检查光标位置和文档内容
+use tzt_text_editor::{Core, Edit, Location, Move, MultiCursor, System};
+
+fn at(line_idx: usize, grapheme_idx: usize) -> Location {
+    Location {
+        grapheme_idx,
+        line_idx,
+    }
+}
+
+fn type_text(core: &mut Core, text: &str) {
+    for character in text.chars() {
+        core.apply(Edit::Insert(character));
+    }
+}
+
+#[test]
+fn moves_over_graphemes_and_lines() {
+    let mut core = Core::from_text("中文ab\nx\n");
+    assert_eq!(core.caret(), at(0, 0));
+    assert!(core.apply(Move::Right));
+    assert_eq!(core.caret(), at(0, 1));
+    core.apply(Move::EndOfLine);
+    assert_eq!(core.caret(), at(0, 4));
+    // 行尾右移到下一行行首
+    core.apply(Move::Right);
+    assert_eq!(core.caret(), at(1, 0));
+    core.apply(Move::Left);
+    assert_eq!(core.caret(), at(0, 4));
+    core.apply(Move::EndOfDocument);
+    assert_eq!(core.caret().line_idx, 1);
+    core.apply(Move::StartOfDocument);
+    assert_eq!(core.caret(), at(0, 0));
+}
+
+#[test]
+fn inserts_and_deletes() {
+    let mut core = Core::from_text("ac\n");
+    core.apply(Move::Right);
+    type_text(&mut core, "b");
+    assert_eq!(core.buffer().to_string(), "abc\n");
+    assert_eq!(core.caret(), at(0, 2));
+    core.apply(Edit::InsertNewline);
+    assert_eq!(core.buffer().to_string(), "ab\nc\n");
+    assert_eq!(core.caret(), at(1, 0));
+    core.apply(Edit::DeleteBackward);
+    assert_eq!(core.buffer().to_string(), "abc\n");
+    assert_eq!(core.caret(), at(0, 2));
+    core.apply(Edit::Delete);
+    assert_eq!(core.buffer().to_string(), "ab\n");
+    assert_eq!(core.caret(), at(0, 2));
+}
+
+#[test]
+fn undo_and_redo_restore_text_and_caret() {
+    let mut core = Core::from_text("x\n");
+    core.apply(Move::EndOfLine);
+    // 连续输入的字符合并为一个撤销步骤
+    type_text(&mut core, "yz");
+    core.apply(Edit::InsertNewline);
+    assert_eq!(core.buffer().to_string(), "xyz\n\n");
+    assert!(core.apply(System::Undo));
+    assert_eq!(core.buffer().to_string(), "xyz\n");
+    assert_eq!(core.caret(), at(0, 3));
+    assert!(core.apply(System::Undo));
+    assert_eq!(core.buffer().to_string(), "x\n");
+    assert_eq!(core.caret(), at(0, 1));
+    assert!(!core.apply(System::Undo));
+    assert!(core.apply(System::Redo));
+    assert_eq!(core.buffer().to_string(), "xyz\n");
+}
+
+#[test]
+fn edits_at_every_caret() {
+    let mut core = Core::from_text("one\ntwo\nthree\n");
+    core.apply(MultiCursor::AddBelow);
+    core.apply(MultiCursor::AddBelow);
+    type_text(&mut core, "- ");
+    assert_eq!(core.buffer().to_string(), "- one\n- two\n- three\n");
+    assert_eq!(core.caret(), at(2, 2));
+    core.apply(Edit::DeleteBackward);
+    assert_eq!(core.buffer().to_string(), "-one\n-two\n-three\n");
+    // 撤销一次撤回所有光标处的修改
+    core.apply(System::Undo);
+    assert_eq!(core.buffer().to_string(), "- one\n- two\n- three\n");
+    core.apply(MultiCursor::RemoveLast);
+    core.apply(MultiCursor::RemoveLast);
+    type_text(&mut core, "!");
+    assert_eq!(core.buffer().to_string(), "- one\n- two\n- !three\n");
+}
+
+#[test]
+fn adds_carets_at_next_occurrences() {
+    let mut core = Core::from_text("ab ab\nab\n");
+    core.apply(MultiCursor::AddNextOccurrence);
+    core.apply(MultiCursor::AddNextOccurrence);
+    core.apply(Edit::Delete);
+    assert_eq!(core.buffer().to_string(), "b b\nb\n");
+}
+
+#[test]
+fn enter_at_line_end_and_document_end() {
+    let mut core = Core::from_text("中文\nb");
+    core.apply(Move::EndOfLine);
+    core.apply(Edit::InsertNewline);
+    assert_eq!(core.buffer().to_string(), "中文\n\nb");
+    assert_eq!(core.caret(), at(1, 0));
+    // 在多字节字素之后换行
+    core.apply(Move::Up);
+    core.apply(Move::Right);
+    core.apply(Edit::InsertNewline);
+    assert_eq!(core.buffer().to_string(), "中\n文\n\nb");
+    assert_eq!(core.caret(), at(1, 0));
+    // 在文档末尾换行，最后一行之后追加一个空行
+    core.apply(Move::EndOfDocument);
+    core.apply(Move::EndOfLine);
+    core.apply(Edit::InsertNewline);
+    assert_eq!(core.buffer().to_string(), "中\n文\n\nb\n");
+    assert_eq!(core.caret(), at(4, 0));
+}
+
+#[test]
+fn deleting_at_document_boundaries_changes_nothing() {
+    for text in ["ab\ncd\n", "ab\ncd", ""] {
+        let mut core = Core::from_text(text);
+        assert!(!core.apply(Edit::DeleteBackward), "{text:?}");
+        assert!(!core.apply(Edit::DeleteWordBackward), "{text:?}");
+        core.apply(Move::EndOfDocument);
+        core.apply(Move::EndOfLine);
+        let end = core.caret();
+        assert!(!core.apply(Edit::Delete), "{text:?}");
+        assert_eq!(core.buffer().to_string(), text);
+        assert_eq!(core.caret(), end);
+        // 无事可撤销
+        assert!(!core.apply(System::Undo), "{text:?}");
+    }
+}
+
+#[test]
+fn transposes_graphemes_around_the_caret() {
+    // 行中：交换光标前后的字素，光标移到两者之后
+    let mut core = Core::from_text("中文ab\n");
+    core.apply(Move::Right);
+    core.apply(Move::Right);
+    assert!(core.apply(Edit::Transpose));
+    assert_eq!(core.buffer().to_string(), "中a文b\n");
+    assert_eq!(core.caret(), at(0, 3));
+    // 组合字符与基本字符作为一个字素移动
+    let mut core = Core::from_text("e\u{301}x\n");
+    core.apply(Move::Right);
+    core.apply(Edit::Transpose);
+    assert_eq!(core.buffer().to_string(), "xe\u{301}\n");
+    assert_eq!(core.caret(), at(0, 2));
+}
+
+#[test]
+fn transpose_at_line_start_and_end() {
+    // 行首没有前一个字素，不做任何事
+    let mut core = Core::from_text("ab\n");
+    assert!(!core.apply(Edit::Transpose));
+    assert_eq!(core.buffer().to_string(), "ab\n");
+    assert_eq!(core.caret(), at(0, 0));
+    // 行尾交换最后两个字素
+    let mut core = Core::from_text("abc中\n");
+    core.apply(Move::EndOfLine);
+    core.apply(Edit::Transpose);
+    assert_eq!(core.buffer().to_string(), "ab中c\n");
+    assert_eq!(core.caret(), at(0, 4));
+    // 一次撤销恢复原文和光标
+    assert!(core.apply(System::Undo));
+    assert_eq!(core.buffer().to_string(), "abc中\n");
+    assert_eq!(core.caret(), at(0, 4));
+    assert!(!core.apply(System::Undo));
+}